@@ -0,0 +1,206 @@
+//! Keeps the last path mode walk in the plugin process, so redrawing the same
+//! tree with different display flags doesn't read a huge directory again.
+//!
+//! Nushell keeps plugins running between calls, so the cache lives as long as
+//! the plugin does. It is only used when `--cached` or `--refresh` is given.
+//! The parsed `LS_COLORS` are kept the same way, and reused on every call.
+
+use crate::utils;
+use crate::view::Walk;
+use lscolors::LsColors;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex, MutexGuard};
+
+/// Everything that changes which entries a walk returns. Flags that only change
+/// how entries are drawn, like `--numbered` or `--columns`, don't belong here.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct WalkKey {
+    pub path: PathBuf,
+    pub level: Option<usize>,
+    pub dirs_only: bool,
+    pub all: bool,
+    pub gitignore: bool,
+    pub git_status: bool,
+    pub git_ignored: bool,
+    pub git_dirty_only: bool,
+    pub git_submodules: bool,
+    pub git_diff_stats: bool,
+    pub git_age: bool,
+    pub dereference: bool,
+    pub follow_symlinks: bool,
+    pub one_file_system: bool,
+    pub file_limit: Option<usize>,
+    pub max_entries: Option<usize>,
+    pub include: Vec<String>,
+    pub exclude: Vec<String>,
+    pub glob: Option<String>,
+}
+
+/// Whether a call reuses the cached walk.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CacheMode {
+    /// Walk again without touching the cache.
+    Off,
+    /// Reuse the cached walk if it has the same key, otherwise walk and cache it.
+    Cached,
+    /// Walk again and replace the cached walk.
+    Refresh,
+}
+
+#[derive(Default)]
+pub struct WalkCache {
+    last: Mutex<Option<(WalkKey, Arc<Walk>)>>,
+}
+
+impl WalkCache {
+    /// Returns the walk for `key`, from the cache or from `walk`.
+    ///
+    /// The cache is only locked to look up and store a walk, not while
+    /// walking, so other calls don't wait for a long walk to end. An
+    /// interrupted walk is never stored, so the next call walks again.
+    pub fn walk<E>(
+        &self,
+        key: WalkKey,
+        mode: CacheMode,
+        walk: impl FnOnce() -> Result<Walk, E>,
+    ) -> Result<Arc<Walk>, E> {
+        if mode == CacheMode::Off {
+            return walk().map(Arc::new);
+        }
+        if mode == CacheMode::Cached
+            && let Some((cached_key, walk)) = self.lock().as_ref()
+            && *cached_key == key
+        {
+            return Ok(walk.clone());
+        }
+        let walk = Arc::new(walk()?);
+        if !walk.interrupted {
+            *self.lock() = Some((key, walk.clone()));
+        }
+        Ok(walk)
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Option<(WalkKey, Arc<Walk>)>> {
+        self.last.lock().unwrap_or_else(|err| err.into_inner())
+    }
+}
+
+/// The colors parsed from the last `LS_COLORS` value seen, which rarely
+/// changes while Nushell runs.
+#[derive(Default)]
+pub struct LsColorsCache {
+    last: Mutex<Option<(Option<String>, Arc<LsColors>)>>,
+}
+
+impl LsColorsCache {
+    /// Returns the colors of `env`, parsing them only when it changed.
+    pub fn get(&self, env: Option<String>) -> Arc<LsColors> {
+        let mut last = self.last.lock().unwrap_or_else(|err| err.into_inner());
+        if let Some((cached_env, ls_colors)) = last.as_ref()
+            && *cached_env == env
+        {
+            return ls_colors.clone();
+        }
+        let ls_colors = Arc::new(utils::get_ls_colors(env.clone()));
+        *last = Some((env, ls_colors.clone()));
+        ls_colors
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::Cell;
+
+    fn key(path: &str) -> WalkKey {
+        WalkKey {
+            path: PathBuf::from(path),
+            level: None,
+            dirs_only: false,
+            all: false,
+            gitignore: false,
+            git_status: false,
+            git_ignored: false,
+            git_dirty_only: false,
+            git_submodules: false,
+            git_diff_stats: false,
+            git_age: false,
+            dereference: false,
+            follow_symlinks: false,
+            one_file_system: false,
+            file_limit: None,
+            max_entries: None,
+            include: Vec::new(),
+            exclude: Vec::new(),
+            glob: None,
+        }
+    }
+
+    #[test]
+    fn test_cached_walks_are_reused_until_refreshed() {
+        let cache = WalkCache::default();
+        let walks = Cell::new(0);
+        let walk = |path: &str, mode: CacheMode| {
+            cache
+                .walk(key(path), mode, || -> Result<Walk, ()> {
+                    walks.set(walks.get() + 1);
+                    Ok(Walk::default())
+                })
+                .unwrap();
+        };
+
+        walk("a", CacheMode::Cached);
+        walk("a", CacheMode::Cached);
+        assert_eq!(walks.get(), 1);
+
+        walk("b", CacheMode::Cached);
+        walk("b", CacheMode::Refresh);
+        walk("b", CacheMode::Off);
+        assert_eq!(walks.get(), 4);
+
+        walk("b", CacheMode::Cached);
+        assert_eq!(walks.get(), 4);
+    }
+
+    #[test]
+    fn test_interrupted_walks_are_not_cached() {
+        let cache = WalkCache::default();
+        let walks = Cell::new(0);
+        let walk = || {
+            cache
+                .walk(key("a"), CacheMode::Cached, || -> Result<Walk, ()> {
+                    walks.set(walks.get() + 1);
+                    Ok(Walk {
+                        interrupted: true,
+                        ..Default::default()
+                    })
+                })
+                .unwrap();
+        };
+
+        walk();
+        walk();
+        assert_eq!(walks.get(), 2);
+    }
+
+    #[test]
+    fn test_cache_is_unlocked_while_walking() {
+        let cache = WalkCache::default();
+        cache
+            .walk(key("a"), CacheMode::Refresh, || -> Result<Walk, ()> {
+                // Another call looks up the cache during the walk.
+                assert!(cache.last.try_lock().is_ok());
+                Ok(Walk::default())
+            })
+            .unwrap();
+    }
+
+    #[test]
+    fn test_ls_colors_are_parsed_once_per_value() {
+        let cache = LsColorsCache::default();
+        let first = cache.get(Some("di=34".to_string()));
+        assert!(Arc::ptr_eq(&first, &cache.get(Some("di=34".to_string()))));
+        assert!(!Arc::ptr_eq(&first, &cache.get(Some("di=35".to_string()))));
+        assert!(!Arc::ptr_eq(&first, &cache.get(Some("di=34".to_string()))));
+    }
+}
@@ -0,0 +1,414 @@
+//! Implements `tree cargo`, which renders the dependency graph of a cargo
+//! workspace from `cargo metadata`, much like `cargo tree`.
+//!
+//! The metadata can be piped in as a record (so it can be filtered with Nushell
+//! first), or the command runs `cargo metadata` in the current directory.
+
+use crate::{TreePlugin, config, stream_tree};
+use nu_plugin::{EngineInterface, EvaluatedCall, PluginCommand};
+use nu_protocol::{
+    Category, Example, LabeledError, PipelineData, Record, Signature, Span, SyntaxShape, Type,
+    Value,
+};
+use ptree::TreeBuilder;
+use ptree::item::StringItem;
+use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
+use std::process::Command;
+
+pub struct TreeCargo;
+
+impl PluginCommand for TreeCargo {
+    type Plugin = TreePlugin;
+
+    fn name(&self) -> &str {
+        "tree cargo"
+    }
+
+    fn signature(&self) -> Signature {
+        Signature::build(self.name())
+            .input_output_types(vec![
+                (Type::Nothing, Type::String),
+                (Type::record(), Type::String),
+            ])
+            .named(
+                "manifest-path",
+                SyntaxShape::Filepath,
+                "the Cargo.toml to read when no metadata is piped in",
+                None,
+            )
+            .named(
+                "depth",
+                SyntaxShape::Int,
+                "maximum depth of dependencies to show",
+                Some('d'),
+            )
+            .category(Category::Experimental)
+    }
+
+    fn description(&self) -> &str {
+        "View the dependency graph of a cargo workspace as a tree."
+    }
+
+    fn examples(&self) -> Vec<Example<'_>> {
+        vec![
+            Example {
+                example: "tree cargo --depth 1",
+                description: "Show the direct dependencies of every workspace member",
+                result: None,
+            },
+            Example {
+                example: "cargo metadata --format-version 1 | from json | tree cargo",
+                description: "Render metadata that was produced or filtered elsewhere",
+                result: None,
+            },
+        ]
+    }
+
+    fn run(
+        &self,
+        _plugin: &TreePlugin,
+        engine: &EngineInterface,
+        call: &EvaluatedCall,
+        input: PipelineData,
+    ) -> Result<PipelineData, LabeledError> {
+        let metadata = match input {
+            PipelineData::Empty | PipelineData::Value(Value::Nothing { .. }, _) => {
+                let json = run_cargo_metadata(engine, call)?;
+                from_json(engine, json, call.head)?
+            }
+            input => input.into_value(call.head)?,
+        };
+        let depth = call
+            .get_flag::<i64>("depth")?
+            .map(|depth| depth.max(0) as usize);
+
+        let graph = Graph::from_metadata(metadata.as_record()?)?;
+
+        let nu_config = engine.get_config()?;
+        let color = config::color_choice(engine, &nu_config)?;
+        let branches = config::branch_style(engine, call)?;
+        Ok(stream_tree(
+            graph.tree(depth),
+            branches,
+            color,
+            engine,
+            call.head,
+        ))
+    }
+}
+
+fn run_cargo_metadata(
+    engine: &EngineInterface,
+    call: &EvaluatedCall,
+) -> Result<String, LabeledError> {
+    let cwd = PathBuf::from(engine.get_current_dir()?);
+    let mut command = Command::new("cargo");
+    command
+        .args(["metadata", "--format-version", "1"])
+        .current_dir(&cwd);
+    if let Some(manifest) = call.get_flag::<PathBuf>("manifest-path")? {
+        command.arg("--manifest-path").arg(cwd.join(manifest));
+    }
+
+    let output = command.output().map_err(|err| {
+        LabeledError::new(format!("Error running cargo metadata: {err}"))
+            .with_label("is cargo installed?", call.head)
+    })?;
+    if !output.status.success() {
+        return Err(LabeledError::new("cargo metadata failed")
+            .with_label(String::from_utf8_lossy(&output.stderr).trim(), call.head));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// Parses JSON with Nushell's own `from json`.
+fn from_json(engine: &EngineInterface, json: String, span: Span) -> Result<Value, LabeledError> {
+    let Some(from_json) = engine.find_decl("from json")? else {
+        return Err(LabeledError::new("The from json command is not available"));
+    };
+    Ok(engine
+        .call_decl(
+            from_json,
+            EvaluatedCall::new(span),
+            PipelineData::Value(Value::string(json, span), None),
+            true,
+            false,
+        )?
+        .into_value(span)?)
+}
+
+/// The parts of `cargo metadata` needed to draw the tree.
+struct Graph {
+    members: Vec<String>,
+    /// Package id to `name vX.Y.Z`.
+    packages: HashMap<String, String>,
+    /// Package id to its resolved dependencies and enabled features.
+    nodes: HashMap<String, Node>,
+}
+
+struct Node {
+    deps: Vec<Dep>,
+    features: Vec<String>,
+}
+
+struct Dep {
+    pkg: String,
+    /// Set when the dependency is only used for `dev` or `build`.
+    kind: Option<String>,
+}
+
+impl Graph {
+    fn from_metadata(metadata: &Record) -> Result<Self, LabeledError> {
+        let mut packages = HashMap::new();
+        for package in get_list(metadata, "packages")? {
+            let package = package.as_record()?;
+            packages.insert(
+                get_str(package, "id")?,
+                format!(
+                    "{} v{}",
+                    get_str(package, "name")?,
+                    get_str(package, "version")?
+                ),
+            );
+        }
+
+        let resolve = metadata
+            .get("resolve")
+            .and_then(|resolve| resolve.as_record().ok())
+            .ok_or_else(|| {
+                LabeledError::new("The metadata has no resolved dependency graph")
+                    .with_help("don't pass --no-deps to cargo metadata")
+            })?;
+        let mut nodes = HashMap::new();
+        for node in get_list(resolve, "nodes")? {
+            let node = node.as_record()?;
+            let mut deps = Vec::new();
+            for dep in get_list(node, "deps")? {
+                let dep = dep.as_record()?;
+                deps.push(Dep {
+                    pkg: get_str(dep, "pkg")?,
+                    kind: dep_kind(dep),
+                });
+            }
+            let features = match node.get("features") {
+                Some(features) => features
+                    .as_list()?
+                    .iter()
+                    .filter_map(|f| f.coerce_str().ok().map(|f| f.into_owned()))
+                    .collect(),
+                None => Vec::new(),
+            };
+            nodes.insert(get_str(node, "id")?, Node { deps, features });
+        }
+
+        let mut members = Vec::new();
+        for member in get_list(metadata, "workspace_members")? {
+            members.push(member.coerce_string()?);
+        }
+
+        Ok(Graph {
+            members,
+            packages,
+            nodes,
+        })
+    }
+
+    /// The tree of every workspace member and its dependencies, down to `depth`.
+    fn tree(&self, depth: Option<usize>) -> StringItem {
+        let mut builder = TreeBuilder::new("workspace".to_string());
+        for member in &self.members {
+            let mut seen = HashSet::new();
+            self.add_package(member, None, &mut builder, &mut seen, depth);
+        }
+        builder.build()
+    }
+
+    /// Adds a package and its dependencies. Packages that were already expanded
+    /// under the same member are marked with `(*)`, like `cargo tree` does.
+    fn add_package(
+        &self,
+        id: &str,
+        kind: Option<&str>,
+        builder: &mut TreeBuilder,
+        seen: &mut HashSet<String>,
+        depth: Option<usize>,
+    ) {
+        let mut label = self
+            .packages
+            .get(id)
+            .cloned()
+            .unwrap_or_else(|| id.to_string());
+        let node = self.nodes.get(id);
+        if let Some(node) = node.filter(|node| !node.features.is_empty()) {
+            label.push_str(&format!(" [{}]", node.features.join(", ")));
+        }
+        if let Some(kind) = kind {
+            label.push_str(&format!(" ({kind})"));
+        }
+
+        let deps = node.map(|node| node.deps.as_slice()).unwrap_or_default();
+        if deps.is_empty() || depth == Some(0) {
+            builder.add_empty_child(label);
+            return;
+        }
+        if !seen.insert(id.to_string()) {
+            builder.add_empty_child(format!("{label} (*)"));
+            return;
+        }
+
+        builder.begin_child(label);
+        for dep in deps {
+            self.add_package(
+                &dep.pkg,
+                dep.kind.as_deref(),
+                builder,
+                seen,
+                depth.map(|depth| depth - 1),
+            );
+        }
+        builder.end_child();
+    }
+}
+
+/// Returns `dev` or `build` when a dependency isn't used as a normal dependency.
+fn dep_kind(dep: &Record) -> Option<String> {
+    let mut kinds = Vec::new();
+    for kind in dep.get("dep_kinds")?.as_list().ok()? {
+        // Normal dependencies have a `null` kind.
+        match kind.as_record().ok()?.get("kind") {
+            Some(Value::String { val, .. }) => kinds.push(val.as_str()),
+            _ => return None,
+        }
+    }
+    (!kinds.is_empty()).then(|| kinds.join(", "))
+}
+
+fn get_list<'a>(record: &'a Record, column: &str) -> Result<&'a [Value], LabeledError> {
+    match record.get(column) {
+        Some(value) => Ok(value.as_list()?),
+        None => Err(LabeledError::new(format!(
+            "The metadata is missing the '{column}' column"
+        ))),
+    }
+}
+
+fn get_str(record: &Record, column: &str) -> Result<String, LabeledError> {
+    match record.get(column) {
+        Some(value) => Ok(value.coerce_string()?),
+        None => Err(LabeledError::new(format!(
+            "The metadata is missing the '{column}' column"
+        ))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{data::DataArgs, json};
+
+    const METADATA: &str = r#"{
+        "packages": [
+            {"id": "app 0.1.0", "name": "app", "version": "0.1.0"},
+            {"id": "cli 0.1.0", "name": "cli", "version": "0.1.0"},
+            {"id": "log 0.4.0", "name": "log", "version": "0.4.0"},
+            {"id": "cc 1.0.0", "name": "cc", "version": "1.0.0"},
+            {"id": "fmt 2.0.0", "name": "fmt", "version": "2.0.0"}
+        ],
+        "workspace_members": ["app 0.1.0", "cli 0.1.0"],
+        "resolve": {
+            "nodes": [
+                {
+                    "id": "app 0.1.0",
+                    "deps": [
+                        {"pkg": "log 0.4.0", "dep_kinds": [{"kind": null}]},
+                        {"pkg": "cc 1.0.0", "dep_kinds": [{"kind": "build"}]}
+                    ],
+                    "features": ["default"]
+                },
+                {
+                    "id": "cli 0.1.0",
+                    "deps": [
+                        {"pkg": "app 0.1.0", "dep_kinds": [{"kind": null}]},
+                        {"pkg": "log 0.4.0", "dep_kinds": [{"kind": "dev"}]}
+                    ],
+                    "features": []
+                },
+                {
+                    "id": "log 0.4.0",
+                    "deps": [{"pkg": "fmt 2.0.0", "dep_kinds": [{"kind": null}]}],
+                    "features": ["std"]
+                },
+                {"id": "cc 1.0.0", "deps": [], "features": []},
+                {"id": "fmt 2.0.0", "deps": [], "features": []}
+            ]
+        }
+    }"#;
+
+    fn graph(json: &str) -> Result<Graph, LabeledError> {
+        let metadata =
+            json::read(json.as_bytes(), &DataArgs::default(), Span::test_data()).unwrap();
+        Graph::from_metadata(metadata.as_record().unwrap())
+    }
+
+    /// The labels of a tree, indented by depth.
+    fn lines(item: &StringItem, depth: usize, out: &mut Vec<String>) {
+        out.push(format!("{}{}", "  ".repeat(depth), item.text));
+        for child in &item.children {
+            lines(child, depth + 1, out);
+        }
+    }
+
+    fn drawn(graph: &Graph, depth: Option<usize>) -> Vec<String> {
+        let mut out = Vec::new();
+        lines(&graph.tree(depth), 0, &mut out);
+        out
+    }
+
+    #[test]
+    fn test_workspace_tree() {
+        let graph = graph(METADATA).unwrap();
+
+        assert_eq!(
+            drawn(&graph, None),
+            vec![
+                "workspace",
+                "  app v0.1.0 [default]",
+                "    log v0.4.0 [std]",
+                "      fmt v2.0.0",
+                "    cc v1.0.0 (build)",
+                "  cli v0.1.0",
+                "    app v0.1.0 [default]",
+                "      log v0.4.0 [std]",
+                "        fmt v2.0.0",
+                "      cc v1.0.0 (build)",
+                "    log v0.4.0 [std] (dev) (*)",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_workspace_tree_depth() {
+        let graph = graph(METADATA).unwrap();
+
+        assert_eq!(
+            drawn(&graph, Some(1)),
+            vec![
+                "workspace",
+                "  app v0.1.0 [default]",
+                "    log v0.4.0 [std]",
+                "    cc v1.0.0 (build)",
+                "  cli v0.1.0",
+                "    app v0.1.0 [default]",
+                "    log v0.4.0 [std] (dev)",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_metadata_without_resolve() {
+        let json = r#"{"packages": [], "workspace_members": []}"#;
+        let err = graph(json).err().unwrap();
+        assert!(err.msg.contains("no resolved dependency graph"));
+    }
+}
@@ -0,0 +1,332 @@
+//! Reads the plugin configuration from `$env.config.plugins.tree` and merges it
+//! with the flags of the current call.
+//!
+//! ```nushell
+//! $env.config.plugins.tree = {
+//!     indent: 2
+//!     charset: ascii
+//!     characters: { down_and_right: "+", turn_right: "\\", right: "-" }
+//!     summary: "{dirs} dirs / {files} files / {size}"
+//!     threads: 4
+//!     explore_keys: { down: [j ctrl-n], up: [k ctrl-p] }
+//! }
+//! ```
+//!
+//! Flags always take precedence over the configuration, which in turn takes
+//! precedence over the theme file (see [`crate::theme`]).
+
+use crate::keymap::{Action, Key, Keymap};
+use crate::theme;
+use crate::view::{BranchStyle, Charset, ColorChoice, Column, Summary, ViewArgs};
+use nu_plugin::{EngineInterface, EvaluatedCall};
+use nu_protocol::{Config, LabeledError, Record, UseAnsiColoring, Value};
+
+/// The smallest indent that still leaves room for a connector and a space.
+pub const MIN_INDENT: usize = 2;
+
+/// Returns the plugin configuration record, if the user has set one.
+pub fn plugin_config(engine: &EngineInterface) -> Result<Option<Record>, LabeledError> {
+    match engine.get_plugin_config()? {
+        Some(Value::Record { val, .. }) => Ok(Some(val.into_owned())),
+        Some(Value::Nothing { .. }) | None => Ok(None),
+        Some(other) => Err(
+            LabeledError::new("Invalid tree plugin configuration").with_label(
+                format!("expected a record, got {}", other.get_type()),
+                other.span(),
+            ),
+        ),
+    }
+}
+
+/// Builds the branch style from the theme file, the plugin configuration and
+/// the `--charset`/`--indent` flags.
+pub fn branch_style(
+    engine: &EngineInterface,
+    call: &EvaluatedCall,
+) -> Result<BranchStyle, LabeledError> {
+    let mut style = theme::load()?.branches.clone();
+
+    if let Some(config) = plugin_config(engine)? {
+        if let Some(charset) = config.get("charset") {
+            style.characters = parse_charset(&charset.coerce_string()?, charset)?;
+        }
+        if let Some(characters) = config.get("characters") {
+            let span = characters.span();
+            let characters = characters.as_record()?;
+            for (name, slot) in [
+                ("down_and_right", &mut style.characters.down_and_right),
+                ("down", &mut style.characters.down),
+                ("turn_right", &mut style.characters.turn_right),
+                ("right", &mut style.characters.right),
+                ("empty", &mut style.characters.empty),
+            ] {
+                if let Some(value) = characters.get(name) {
+                    *slot = value.coerce_string()?;
+                }
+            }
+            if let Some(unknown) = characters.columns().find(|name| {
+                !["down_and_right", "down", "turn_right", "right", "empty"].contains(&name.as_str())
+            }) {
+                return Err(LabeledError::new("Invalid tree plugin configuration")
+                    .with_label(format!("unknown branch character '{unknown}'"), span));
+            }
+        }
+        if let Some(indent) = config.get("indent") {
+            style.indent = parse_indent(indent.as_int()?, indent)?;
+        }
+    }
+
+    if let Some(charset) = call.get_flag_value("charset") {
+        style.characters = parse_charset(&charset.coerce_string()?, &charset)?;
+    }
+    if let Some(indent) = call.get_flag_value("indent") {
+        style.indent = parse_indent(indent.as_int()?, &indent)?;
+    }
+
+    Ok(style)
+}
+
+/// Reads the summary line format from the plugin configuration: `counts` (the
+/// default), `gnu`, `eza` (no summary) or a template.
+pub fn summary(engine: &EngineInterface) -> Result<Summary, LabeledError> {
+    let Some(value) = plugin_config(engine)?.and_then(|config| config.get("summary").cloned())
+    else {
+        return Ok(Summary::default());
+    };
+    value.coerce_str()?.parse().map_err(|err| {
+        LabeledError::new("Invalid tree plugin configuration").with_label(err, value.span())
+    })
+}
+
+/// Reads the `tree explore` key bindings from the `explore_keys` configuration,
+/// a record of actions and the key or list of keys bound to them.
+pub fn keymap(engine: &EngineInterface) -> Result<Keymap, LabeledError> {
+    let mut keymap = Keymap::default();
+    let Some(value) = plugin_config(engine)?.and_then(|config| config.get("explore_keys").cloned())
+    else {
+        return Ok(keymap);
+    };
+    let invalid = |err: String, value: &Value| {
+        LabeledError::new("Invalid tree plugin configuration").with_label(err, value.span())
+    };
+    for (name, keys) in value.as_record()?.iter() {
+        let action: Action = name.parse().map_err(|err| invalid(err, &value))?;
+        let keys = match keys {
+            Value::List { vals, .. } => vals.iter().collect(),
+            key => vec![key],
+        };
+        let mut parsed = Vec::with_capacity(keys.len());
+        for key in keys {
+            parsed.push(
+                key.coerce_str()?
+                    .parse::<Key>()
+                    .map_err(|err| invalid(err, key))?,
+            );
+        }
+        keymap.bind(action, parsed);
+    }
+    Ok(keymap)
+}
+
+/// Sets the walker's thread count from the `--threads` flag or the `threads`
+/// configuration, and lets the walk stop when Nushell is interrupted and show
+/// its progress when it takes a while.
+pub fn walk_tuning(
+    engine: &EngineInterface,
+    call: &EvaluatedCall,
+    args: &mut ViewArgs,
+) -> Result<(), LabeledError> {
+    args.signals = Some(engine.signals().clone());
+    args.progress = true;
+    let config = plugin_config(engine)?;
+    let setting = |name: &str| config.as_ref().and_then(|config| config.get(name).cloned());
+    if let Some(threads) = call
+        .get_flag_value("threads")
+        .or_else(|| setting("threads"))
+    {
+        args.threads = parse_positive(&threads)?;
+    }
+    Ok(())
+}
+
+fn parse_positive(value: &Value) -> Result<usize, LabeledError> {
+    usize::try_from(value.as_int()?)
+        .ok()
+        .filter(|n| *n > 0)
+        .ok_or_else(|| {
+            LabeledError::new("Invalid walk setting")
+                .with_label("expected a positive number", value.span())
+        })
+}
+
+/// Reads the `--pattern` or `--ignore` flag: a list of patterns, or a string
+/// of patterns separated by `|` as in GNU `tree -P`.
+pub fn patterns(call: &EvaluatedCall, name: &str) -> Result<Vec<String>, LabeledError> {
+    let Some(value) = call.get_flag_value(name) else {
+        return Ok(Vec::new());
+    };
+    match value {
+        Value::List { vals, .. } => vals
+            .into_iter()
+            .map(|val| Ok(val.coerce_into_string()?))
+            .collect(),
+        value => Ok(value
+            .coerce_into_string()?
+            .split('|')
+            .map(str::to_string)
+            .collect()),
+    }
+}
+
+/// Parses the `--columns` flag, keeping the order the columns were given in.
+pub fn columns(call: &EvaluatedCall) -> Result<Option<Vec<Column>>, LabeledError> {
+    let Some(value) = call.get_flag_value("columns") else {
+        return Ok(None);
+    };
+    let mut columns = Vec::new();
+    for name in value.as_list()? {
+        let column = name
+            .coerce_str()?
+            .parse::<Column>()
+            .map_err(|err| LabeledError::new("Invalid column").with_label(err, name.span()))?;
+        if !columns.contains(&column) {
+            columns.push(column);
+        }
+    }
+    Ok(Some(columns))
+}
+
+/// The options a path mode record may set, besides its `path`.
+const PATH_OPTIONS: &[&str] = &[
+    "level",
+    "all",
+    "gitignore",
+    "dirs_only",
+    "icons",
+    "size",
+    "permissions",
+    "git_status",
+    "numbered",
+    "max_entries",
+];
+
+/// Returns the path of a path mode input: a path, or a record like
+/// `{path: src, level: 2, icons: false}` whose other fields are applied to
+/// the tree with [`path_options`].
+pub fn root_path(value: &Value) -> Result<String, LabeledError> {
+    match value {
+        Value::Record { val, .. } => match val.get("path") {
+            Some(path) => Ok(path.coerce_string()?),
+            None => Err(LabeledError::new("Missing path")
+                .with_label("the record needs a `path` field", value.span())),
+        },
+        value => Ok(value.coerce_string()?),
+    }
+}
+
+/// Applies the fields of a path mode record on top of the flags.
+pub fn path_options(value: &Value, args: &mut ViewArgs) -> Result<(), LabeledError> {
+    let Value::Record { val, .. } = value else {
+        return Ok(());
+    };
+    for (name, value) in val.iter() {
+        match name.as_str() {
+            "path" => {}
+            "level" => args.level = Some(parse_positive(value)?),
+            "all" => args.all = value.as_bool()?,
+            "gitignore" => args.gitignore = value.as_bool()?,
+            "dirs_only" => args.dirs_only = value.as_bool()?,
+            "icons" => args.icons = value.as_bool()?,
+            "size" => args.size = value.as_bool()?,
+            "permissions" => args.permissions = value.as_bool()?,
+            "git_status" => args.git_status = value.as_bool()?,
+            "numbered" => args.numbered = value.as_bool()?,
+            "max_entries" => {
+                args.max_entries = usize::try_from(value.as_int()?).ok().filter(|max| *max > 0)
+            }
+            _ => {
+                return Err(LabeledError::new("Unknown path option").with_label(
+                    format!("expected path, {}", PATH_OPTIONS.join(", ")),
+                    value.span(),
+                ));
+            }
+        }
+    }
+    Ok(())
+}
+
+fn parse_charset(
+    name: &str,
+    value: &Value,
+) -> Result<ptree::print_config::IndentChars, LabeledError> {
+    name.parse::<Charset>()
+        .map(|charset| charset.indent_chars())
+        .map_err(|err| LabeledError::new("Invalid charset").with_label(err, value.span()))
+}
+
+fn parse_indent(indent: i64, value: &Value) -> Result<usize, LabeledError> {
+    usize::try_from(indent)
+        .ok()
+        .filter(|indent| *indent >= MIN_INDENT)
+        .ok_or_else(|| {
+            LabeledError::new("Invalid indent").with_label(
+                format!("the indent must be at least {MIN_INDENT}"),
+                value.span(),
+            )
+        })
+}
+
+/// Decides whether output should be colored, following `$env.config.use_ansi_coloring`.
+///
+/// The rendered tree is handed back to Nushell rather than written to the
+/// terminal, so in `auto` mode only `NO_COLOR` can turn color off.
+pub fn color_choice(
+    engine: &EngineInterface,
+    config: &Config,
+) -> Result<ColorChoice, LabeledError> {
+    Ok(match config.use_ansi_coloring {
+        UseAnsiColoring::True => ColorChoice::Always,
+        UseAnsiColoring::False => ColorChoice::Never,
+        UseAnsiColoring::Auto => {
+            let no_color = engine
+                .get_env_var("NO_COLOR")?
+                .is_some_and(|v| v.coerce_bool().unwrap_or(true));
+            if no_color {
+                ColorChoice::Never
+            } else {
+                ColorChoice::Auto
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nu_protocol::record;
+
+    #[test]
+    fn test_path_records_set_options() {
+        let root = Value::test_record(record! {
+            "path" => Value::test_string("src"),
+            "level" => Value::test_int(2),
+            "icons" => Value::test_bool(false),
+            "max_entries" => Value::test_int(0),
+        });
+        let mut args = ViewArgs {
+            icons: true,
+            max_entries: Some(10),
+            ..Default::default()
+        };
+        assert_eq!(root_path(&root).unwrap(), "src");
+        path_options(&root, &mut args).unwrap();
+        assert_eq!(
+            (args.level, args.icons, args.max_entries),
+            (Some(2), false, None)
+        );
+
+        let root = Value::test_record(record! { "depth" => Value::test_int(2) });
+        assert!(root_path(&root).is_err());
+        assert!(path_options(&root, &mut args).is_err());
+    }
+}
@@ -0,0 +1,1343 @@
+//! Builds trees from pipeline data, as in `ls | tree` or `$env | tree`.
+
+use nu_ansi_term::Style;
+use nu_plugin::EngineInterface;
+use nu_protocol::{Config, DataSource, PipelineMetadata, Signals, Span, Type, Value};
+use ptree::TreeBuilder;
+use ptree::item::StringItem;
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::sync::Arc;
+
+/// Records and lists smaller than this many nodes are drawn again when
+/// repeated, since a reference wouldn't be any shorter.
+const DEDUP_MIN_NODES: usize = 3;
+
+/// How many fields and list items are drawn unless `--max-nodes` says otherwise.
+pub const DEFAULT_MAX_NODES: usize = 100_000;
+
+/// How many bytes of binary values are shown by default.
+pub const BINARY_PREVIEW: usize = 8;
+
+/// Controls how values are turned into tree nodes.
+#[derive(Debug, Default, Clone)]
+pub struct DataArgs {
+    /// Annotate nodes with the type of their value, e.g. `PATH: list<string>` or `3: int`.
+    pub types: bool,
+    /// Lists of plain values longer than this are shown as a count instead of item by item.
+    pub collapse: Option<usize>,
+    /// Annotate lists and records with how many items they hold, e.g. `users [20]`.
+    pub counts: bool,
+    /// Show at most this many items of each list, followed by how many were left out.
+    pub max_items: Option<usize>,
+    /// Truncate strings longer than this many characters.
+    pub max_string: Option<usize>,
+    /// Nodes nested deeper than this are replaced by a `…` placeholder.
+    pub depth: Option<usize>,
+    /// Draw the fields of records in alphabetical order rather than their own.
+    pub sort_keys: bool,
+    /// Follow each plain value with the cell path that gets it, like `(users.0.name)`.
+    pub cell_paths: bool,
+    /// Fold chains of records with a single field into one node, like `a.b.c → 1`.
+    pub compact: bool,
+    /// How many bytes of binary values are shown in hex, after their length.
+    pub binary_preview: usize,
+    /// Write durations as their number of nanoseconds instead of like `2hr 3min 4sec`.
+    pub raw_durations: bool,
+    /// Write dates with this `strftime` format instead of the way tables write them.
+    pub date_format: Option<String>,
+    /// Cut values that would make their line wider than this many columns.
+    pub max_width: Option<usize>,
+    /// The width of each level of the tree, to know where a value's line starts.
+    pub indent: usize,
+    /// The style of plain values by type, from `$env.config.color_config`.
+    /// Empty when values aren't colored.
+    pub styles: HashMap<String, Style>,
+    /// Draw repeated records and lists once, replacing their later copies
+    /// with a reference to the first.
+    pub dedup: bool,
+    /// Follow each node with the span of its value, like `@12..18`.
+    pub debug: bool,
+    /// Stop adding fields and list items after this many, so huge values
+    /// don't flood the terminal.
+    pub max_nodes: Option<usize>,
+    /// How many fields and list items were added so far.
+    pub nodes: Cell<usize>,
+    /// Stops building the tree when Nushell is interrupted with ctrl-c.
+    pub signals: Option<Signals>,
+    /// Whether the interruption was drawn already, so it is drawn once.
+    pub interrupted: Cell<bool>,
+    /// Reads the source of closures, to show their parameters.
+    pub engine: Option<EngineInterface>,
+}
+
+impl DataArgs {
+    /// Returns the settings of a named preset.
+    ///
+    /// - `json`: tuned for exploring API payloads, with types, counts, at most
+    ///   10 items per list and strings cut at 60 characters.
+    pub fn preset(name: &str) -> Result<Self, String> {
+        match name {
+            "json" => Ok(DataArgs {
+                types: true,
+                counts: true,
+                max_items: Some(10),
+                max_string: Some(60),
+                binary_preview: BINARY_PREVIEW,
+                ..Default::default()
+            }),
+            _ => Err(format!("unknown preset '{name}', expected json")),
+        }
+    }
+
+    /// Counts a field or list item about to be added, returning false once
+    /// `max_nodes` of them were.
+    fn take_node(&self) -> bool {
+        if self.max_nodes.is_some_and(|max| self.nodes.get() >= max) {
+            return false;
+        }
+        self.nodes.set(self.nodes.get() + 1);
+        true
+    }
+
+    /// Whether Nushell was interrupted, adding `… interrupted` below the
+    /// first node that notices it.
+    fn stop(&self, builder: &mut TreeBuilder) -> bool {
+        if !self.signals.as_ref().is_some_and(Signals::interrupted) {
+            return false;
+        }
+        if !self.interrupted.replace(true) {
+            builder.add_empty_child("… interrupted".to_string());
+        }
+        true
+    }
+}
+
+/// Builds the tree of the input value, drawn under `root`, or of empty input.
+/// Streams are collected into a value first, or drawn by [`ListTree`].
+pub fn from_value(
+    input: Option<&Value>,
+    root: &str,
+    args: &DataArgs,
+    config: Arc<Config>,
+) -> StringItem {
+    let mut tree = TreeBuilder::new("".to_string());
+    let builder = &mut tree;
+
+    match input {
+        None => {
+            builder.add_empty_child("empty".to_string());
+        }
+        Some(value) => {
+            builder.begin_child(field_label(root, value, args));
+            let dedup = Dedup::default();
+            from_value_helper(value, builder, args, &dedup, config, &Place::top());
+            builder.end_child();
+        }
+    }
+
+    builder.build()
+}
+
+/// Where the nodes of a value go: how many levels below the root, and with
+/// `--cell-paths` or `--dedup`, the cell path that gets the value.
+#[derive(Clone, Debug, Default)]
+pub struct Place {
+    depth: usize,
+    path: String,
+}
+
+impl Place {
+    /// The place of the input value, whose nodes are the root's children.
+    pub fn top() -> Self {
+        Place {
+            depth: 1,
+            path: String::new(),
+        }
+    }
+
+    /// The place of the value of a field, whose nodes are a level deeper.
+    fn field(&self, key: &str, args: &DataArgs) -> Self {
+        Place {
+            depth: self.depth + 1,
+            path: self.member(key, args),
+        }
+    }
+
+    /// The place of a list item, whose nodes are drawn among the list's.
+    fn item(&self, index: usize, args: &DataArgs) -> Self {
+        Place {
+            depth: self.depth,
+            path: self.member(&index.to_string(), args),
+        }
+    }
+
+    /// The same value, drawn a level deeper below a node of its own.
+    fn below(&self) -> Self {
+        Place {
+            depth: self.depth + 1,
+            path: self.path.clone(),
+        }
+    }
+
+    fn member(&self, member: &str, args: &DataArgs) -> String {
+        if !args.cell_paths && !args.dedup {
+            return String::new();
+        }
+        // Keys that wouldn't read back as one member of a cell path are quoted.
+        let plain = !member.is_empty()
+            && member
+                .chars()
+                .all(|c| c.is_alphanumeric() || c == '_' || c == '-');
+        let member = if plain {
+            member.to_string()
+        } else {
+            format!("\"{}\"", member.replace('\\', "\\\\").replace('"', "\\\""))
+        };
+        if self.path.is_empty() {
+            member
+        } else {
+            format!("{}.{member}", self.path)
+        }
+    }
+
+    /// The line of a plain value, after its field's `label` when it is drawn
+    /// on the same line and followed by its type when `typed`. The line is cut
+    /// to fit, the value is painted with the style of its type, and its cell
+    /// path is added when asked for.
+    fn leaf(
+        &self,
+        value: &Value,
+        label: Option<&str>,
+        typed: bool,
+        args: &DataArgs,
+        config: &Config,
+    ) -> String {
+        let before = label.map_or(String::new(), |label| format!("{label} → "));
+        let shown = leaf_text(value, args, config);
+        let after = if typed {
+            format!(": {}", value.get_type())
+        } else {
+            String::new()
+        };
+        let text = fit(format!("{before}{shown}{after}"), args, self.depth);
+        let text = match args.styles.get(&value.get_type().to_string()) {
+            Some(style) => paint(&text, before.chars().count(), shown.chars().count(), *style),
+            None => text,
+        };
+        let text = if args.cell_paths && !self.path.is_empty() {
+            format!("{text} ({})", self.path)
+        } else {
+            text
+        };
+        if args.debug {
+            format!("{text} {}", span_text(value.span()))
+        } else {
+            text
+        }
+    }
+}
+
+/// Adds the nodes of a value at its place in the tree.
+pub fn from_value_helper<'a>(
+    value: &'a Value,
+    builder: &mut TreeBuilder,
+    args: &DataArgs,
+    dedup: &Dedup<'a>,
+    config: Arc<Config>,
+    at: &Place,
+) {
+    let depth = at.depth;
+    if args.stop(builder) {
+        return;
+    }
+    if args.depth.is_some_and(|max| depth > max) {
+        if !is_empty(value) {
+            builder.add_empty_child("…".to_string());
+        }
+        return;
+    }
+    if args.dedup
+        && !at.path.is_empty()
+        && !is_plain(value)
+        && let Some(first) = dedup.earlier(value, &at.path)
+    {
+        builder.add_empty_child(format!("↑ same as {first}"));
+        return;
+    }
+    match value {
+        Value::Record { val, .. } => {
+            let mut fields: Vec<_> = val.iter().collect();
+            if args.sort_keys {
+                fields.sort_by_key(|(key, _)| *key);
+            }
+            let count = fields.len();
+            for (index, (k, v)) in fields.into_iter().enumerate() {
+                if args.stop(builder) {
+                    return;
+                }
+                if !args.take_node() {
+                    builder.add_empty_child(format!("… {} more", count - index));
+                    return;
+                }
+                if args.compact {
+                    from_compact_field(k, v, builder, args, dedup, config.clone(), at);
+                    continue;
+                }
+                builder.begin_child(field_label(k, v, args));
+                from_value_helper(v, builder, args, dedup, config.clone(), &at.field(k, args));
+                builder.end_child();
+            }
+        }
+        Value::List { vals, .. } => {
+            if let Some(limit) = args.collapse
+                && vals.len() > limit
+                && vals.iter().all(is_plain)
+            {
+                builder.add_empty_child(format!("[{} items]", vals.len()));
+                return;
+            }
+            // The items of a nested list of one type are described by its field.
+            let typed = args.types
+                && (depth == 1
+                    || matches!(value.get_type(), Type::List(item) if *item == Type::Any));
+            let shown = args.max_items.unwrap_or(vals.len()).min(vals.len());
+            for (index, value) in vals[..shown].iter().enumerate() {
+                if args.stop(builder) {
+                    return;
+                }
+                if !args.take_node() {
+                    builder.add_empty_child(format!("… {} more", vals.len() - index));
+                    return;
+                }
+                let item = at.item(index, args);
+                from_list_item(value, builder, args, dedup, config.clone(), &item, typed);
+            }
+            if shown < vals.len() {
+                builder.add_empty_child(format!("… {} more", vals.len() - shown));
+            }
+        }
+        value => {
+            builder.add_empty_child(at.leaf(value, None, false, args, &config));
+        }
+    }
+}
+
+/// Adds a field with the records holding a single field below it folded into
+/// its node, as `a.b.c`, and a plain value at the end of the chain drawn on the
+/// same line, as `a.b.c → 1`.
+fn from_compact_field<'a>(
+    key: &str,
+    value: &'a Value,
+    builder: &mut TreeBuilder,
+    args: &DataArgs,
+    dedup: &Dedup<'a>,
+    config: Arc<Config>,
+    at: &Place,
+) {
+    let mut keys = key.to_string();
+    let mut value = value;
+    let mut place = at.field(key, args);
+    while let Value::Record { val, .. } = value
+        && val.len() == 1
+        && let Some((key, inner)) = val.iter().next()
+    {
+        keys = format!("{keys}.{key}");
+        value = inner;
+        place.path = place.member(key, args);
+    }
+    let label = field_label(&keys, value, args);
+    if is_plain(value) {
+        // The value is drawn on the line of its field.
+        place.depth = at.depth;
+        builder.add_empty_child(place.leaf(value, Some(&label), false, args, &config));
+    } else {
+        builder.begin_child(label);
+        from_value_helper(value, builder, args, dedup, config, &place);
+        builder.end_child();
+    }
+}
+
+/// The length of a binary value and its first `preview` bytes, in hex and
+/// as text with `.` for bytes that aren't printable ASCII, like
+/// `12 bytes: 48 65 6c 6c … |Hell|`.
+fn binary_text(bytes: &[u8], preview: usize) -> String {
+    let len = match bytes.len() {
+        1 => "1 byte".to_string(),
+        len => format!("{len} bytes"),
+    };
+    let shown = &bytes[..preview.min(bytes.len())];
+    if shown.is_empty() {
+        return len;
+    }
+    let hex: Vec<String> = shown.iter().map(|byte| format!("{byte:02x}")).collect();
+    let ascii: String = shown
+        .iter()
+        .map(|&byte| match byte {
+            b' '..=b'~' => byte as char,
+            _ => '.',
+        })
+        .collect();
+    let more = if shown.len() < bytes.len() {
+        " …"
+    } else {
+        ""
+    };
+    format!("{len}: {}{more} |{ascii}|", hex.join(" "))
+}
+
+/// Describes a closure by its parameters, read from its `source`, and how many
+/// variables it captures, like `closure |x, y| (1 capture)`.
+fn closure_text(source: Option<&[u8]>, captures: usize) -> String {
+    let mut text = "closure".to_string();
+    if let Some(params) = source.and_then(closure_params) {
+        text = format!("{text} |{params}|");
+    }
+    match captures {
+        0 => text,
+        1 => format!("{text} (1 capture)"),
+        captures => format!("{text} ({captures} captures)"),
+    }
+}
+
+/// The parameters of a closure's source, like `x, y` in `{|x, y| $x + $y }`.
+fn closure_params(source: &[u8]) -> Option<String> {
+    let source = String::from_utf8_lossy(source);
+    let rest = source
+        .trim()
+        .strip_prefix('{')?
+        .trim_start()
+        .strip_prefix('|')?;
+    // Default values may hold a `|` of their own, in quotes or brackets.
+    let mut nesting = 0usize;
+    let mut quote = None;
+    for (i, c) in rest.char_indices() {
+        match (quote, c) {
+            (Some(open), c) if c == open => quote = None,
+            (Some(_), _) => {}
+            (None, '"' | '\'' | '`') => quote = Some(c),
+            (None, '(' | '[' | '{') => nesting += 1,
+            (None, ')' | ']' | '}') => nesting = nesting.saturating_sub(1),
+            (None, '|') if nesting == 0 => {
+                let params: Vec<&str> = rest[..i].split_whitespace().collect();
+                return Some(params.join(" "));
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Paints the `len` characters of `text` that start at character `start`,
+/// which may have been cut short by `fit`.
+fn paint(text: &str, start: usize, len: usize, style: Style) -> String {
+    let byte = |chars: usize| {
+        text.char_indices()
+            .nth(chars)
+            .map_or(text.len(), |(i, _)| i)
+    };
+    let (from, to) = (byte(start), byte(start + len));
+    if from == to {
+        return text.to_string();
+    }
+    format!(
+        "{}{}{}",
+        &text[..from],
+        style.paint(&text[from..to]),
+        &text[to..]
+    )
+}
+
+/// The records and lists drawn so far with `--dedup`, borrowed from the value
+/// the tree is built from.
+#[derive(Default)]
+pub struct Dedup<'a> {
+    /// The fingerprint and node count of each record and list hashed so far,
+    /// by address, so nested values are hashed once rather than at every
+    /// level above them.
+    hashes: RefCell<HashMap<*const Value, (u64, usize)>>,
+    /// The values drawn so far by fingerprint, with their cell paths. Values
+    /// are compared to tell apart the ones that share a fingerprint.
+    drawn: RefCell<HashMap<u64, Vec<(&'a Value, String)>>>,
+}
+
+impl<'a> Dedup<'a> {
+    /// The cell path of an equal value drawn earlier, or `None` after noting
+    /// that `value` is drawn at `path`. Small values are drawn again.
+    fn earlier(&self, value: &'a Value, path: &str) -> Option<String> {
+        let (hash, nodes) = self.fingerprint(value);
+        if nodes < DEDUP_MIN_NODES {
+            return None;
+        }
+        let mut drawn = self.drawn.borrow_mut();
+        let copies = drawn.entry(hash).or_default();
+        match copies.iter().find(|(first, _)| *first == value) {
+            Some((_, first)) => Some(first.clone()),
+            None => {
+                copies.push((value, path.to_string()));
+                None
+            }
+        }
+    }
+
+    /// A hash of the contents of a value, equal for equal values, and how
+    /// many nodes the value is drawn with. Records and lists combine the
+    /// fingerprints of their contents.
+    fn fingerprint(&self, value: &Value) -> (u64, usize) {
+        if let Some(known) = self.hashes.borrow().get(&(value as *const Value)) {
+            return *known;
+        }
+        let mut state = DefaultHasher::new();
+        std::mem::discriminant(value).hash(&mut state);
+        let nodes = match value {
+            Value::Record { val, .. } => val
+                .iter()
+                .map(|(key, value)| {
+                    let (hash, nodes) = self.fingerprint(value);
+                    (key, hash).hash(&mut state);
+                    1 + nodes
+                })
+                .sum(),
+            Value::List { vals, .. } => {
+                vals.len().hash(&mut state);
+                vals.iter()
+                    .map(|value| {
+                        let (hash, nodes) = self.fingerprint(value);
+                        hash.hash(&mut state);
+                        1 + nodes
+                    })
+                    .sum()
+            }
+            value => {
+                hash_leaf(value, &mut state);
+                1
+            }
+        };
+        let fingerprint = (state.finish(), nodes);
+        if !is_plain(value) {
+            self.hashes.borrow_mut().insert(value, fingerprint);
+        }
+        fingerprint
+    }
+}
+
+/// Feeds a value without nested structure to `state`.
+fn hash_leaf(value: &Value, state: &mut impl Hasher) {
+    match value {
+        Value::Bool { val, .. } => val.hash(state),
+        Value::Int { val, .. } => val.hash(state),
+        Value::Float { val, .. } => val.to_bits().hash(state),
+        Value::Filesize { val, .. } => val.get().hash(state),
+        Value::Duration { val, .. } => val.hash(state),
+        Value::Date { val, .. } => val.hash(state),
+        Value::String { val, .. } | Value::Glob { val, .. } => val.hash(state),
+        Value::Binary { val, .. } => val.hash(state),
+        Value::Closure { val, .. } => val.block_id.get().hash(state),
+        Value::Range { val, .. } => val.to_string().hash(state),
+        Value::CellPath { val, .. } => val.to_string().hash(state),
+        Value::Error { .. }
+        | Value::Custom { .. }
+        | Value::Nothing { .. }
+        | Value::Record { .. }
+        | Value::List { .. } => {}
+    }
+}
+
+/// The text of a value without nested structure. Durations are written like
+/// `2hr 3min 4sec`, and filesizes, floats and dates follow the `filesize`,
+/// `float_precision` and `datetime_format.table` settings, as they do in tables.
+fn leaf_text(value: &Value, args: &DataArgs, config: &Config) -> String {
+    match value {
+        Value::Bool { val, .. } => val.to_string(),
+        Value::Int { val, .. } => val.to_string(),
+        Value::Float { val, .. } => {
+            let precision = config.float_precision.max(0) as usize;
+            format!("{val:.precision$}")
+        }
+        Value::String { val, .. } => truncate(val, args.max_string),
+        Value::Glob { val, .. } => val.to_string(),
+        Value::Filesize { val, .. } => config.filesize.format(*val).to_string(),
+        Value::Duration { val, .. } if args.raw_durations => val.to_string(),
+        Value::Duration { .. } => value.to_expanded_string("", config),
+        Value::Date { val, .. } => match &args.date_format {
+            Some(format) => val.format(format).to_string(),
+            None => value.to_abbreviated_string(config),
+        },
+        Value::Range { val, .. } => val.to_string(),
+        Value::Closure { val, .. } => {
+            let source = args
+                .engine
+                .as_ref()
+                .and_then(|engine| engine.get_span_contents(value.span()).ok());
+            closure_text(source.as_deref(), val.captures.len())
+        }
+        Value::Error { error, .. } => error.to_string(),
+        Value::Binary { val, .. } => binary_text(val, args.binary_preview),
+        Value::CellPath { val, .. } => val.to_string(),
+        Value::Custom { .. } => "custom".to_string(),
+        Value::Nothing { .. } => "null".to_string(),
+        Value::Record { .. } | Value::List { .. } => String::new(),
+    }
+}
+
+/// Adds the nodes of one item of a list, among the nodes of the list's items.
+/// With `typed`, an item without nested structure is followed by its type.
+fn from_list_item<'a>(
+    value: &'a Value,
+    builder: &mut TreeBuilder,
+    args: &DataArgs,
+    dedup: &Dedup<'a>,
+    config: Arc<Config>,
+    at: &Place,
+    typed: bool,
+) {
+    // Rows with a name, like the output of `ls` or `scope modules`, are
+    // grouped under that name instead of mixing their fields together.
+    match named_row(value) {
+        Some(name) if args.depth.is_none_or(|max| at.depth <= max) => {
+            builder.begin_child(field_label(name, value, args));
+            from_value_helper(value, builder, args, dedup, config, &at.below());
+            builder.end_child();
+        }
+        None if typed && is_plain(value) => {
+            builder.add_empty_child(at.leaf(value, None, true, args, &config));
+        }
+        _ => from_value_helper(value, builder, args, dedup, config, at),
+    }
+}
+
+/// Builds the tree of a list stream one item at a time, giving the same
+/// nodes as the collected list would. Items are dropped once drawn, so
+/// `--dedup` only finds repeats within an item; streams are collected for it.
+pub struct ListTree {
+    args: DataArgs,
+    config: Arc<Config>,
+    /// With `--collapse`, the items seen while they are all plain values,
+    /// since the list is only collapsed if all of it is.
+    held: Option<Vec<Value>>,
+    shown: usize,
+    left_out: usize,
+}
+
+impl ListTree {
+    pub fn new(args: DataArgs, config: Arc<Config>) -> Self {
+        ListTree {
+            held: args.collapse.map(|_| Vec::new()),
+            args,
+            config,
+            shown: 0,
+            left_out: 0,
+        }
+    }
+
+    /// The label of the list's root, annotated like the root of a collected
+    /// list as far as it can be before the items arrive. Their type and
+    /// number aren't known yet, so `--types` shows `list<any>` and `--counts`
+    /// adds nothing.
+    pub fn root_label(&self, root: &str) -> String {
+        if self.args.types {
+            format!("{root}: {}", Type::List(Box::new(Type::Any)))
+        } else {
+            root.to_string()
+        }
+    }
+
+    /// Adds an item, returning the nodes that are ready to be drawn as the
+    /// children of an unnamed root.
+    pub fn push(&mut self, value: Value) -> StringItem {
+        let mut builder = TreeBuilder::new(String::new());
+        if let Some(held) = &mut self.held {
+            let plain = is_plain(&value);
+            held.push(value);
+            if plain {
+                return builder.build();
+            }
+            for value in self.held.take().unwrap_or_default() {
+                self.add(&value, &mut builder);
+            }
+        } else {
+            self.add(&value, &mut builder);
+        }
+        builder.build()
+    }
+
+    /// Returns the nodes that were left once the list has ended.
+    pub fn finish(mut self) -> StringItem {
+        let mut builder = TreeBuilder::new(String::new());
+        if let Some(held) = self.held.take() {
+            if self.args.collapse.is_some_and(|limit| held.len() > limit) {
+                builder.add_empty_child(format!("[{} items]", held.len()));
+                return builder.build();
+            }
+            for value in &held {
+                self.add(value, &mut builder);
+            }
+        }
+        if self.left_out > 0 {
+            builder.add_empty_child(format!("… {} more", self.left_out));
+        }
+        builder.build()
+    }
+
+    fn add(&mut self, value: &Value, builder: &mut TreeBuilder) {
+        if self.args.stop(builder) {
+            return;
+        }
+        if self.args.max_items.is_some_and(|max| self.shown >= max) || !self.args.take_node() {
+            self.left_out += 1;
+            return;
+        }
+        let item = Place::top().item(self.shown, &self.args);
+        self.shown += 1;
+        from_list_item(
+            value,
+            builder,
+            &self.args,
+            &Dedup::default(),
+            self.config.clone(),
+            &item,
+            self.args.types,
+        );
+    }
+}
+
+/// Numbers the nodes below the root in the order they are drawn.
+pub fn number_items(item: &mut StringItem) {
+    number_below(item, &mut 1);
+}
+
+/// Numbers the nodes below `item` in the order they are drawn, starting at `next`.
+pub fn number_below(item: &mut StringItem, next: &mut usize) {
+    for child in &mut item.children {
+        child.text = format!("{}. {}", next, child.text);
+        *next += 1;
+        number_below(child, next);
+    }
+}
+
+fn field_label(key: &str, value: &Value, args: &DataArgs) -> String {
+    let mut label = if args.types {
+        format!("{key}: {}", value.get_type())
+    } else {
+        key.to_string()
+    };
+    if args.counts {
+        match value {
+            Value::List { vals, .. } => label.push_str(&format!(" [{}]", vals.len())),
+            Value::Record { val, .. } => label.push_str(&format!(" {{{}}}", val.len())),
+            _ => {}
+        }
+    }
+    if args.debug {
+        label = format!("{label} {}", span_text(value.span()));
+    }
+    label
+}
+
+fn span_text(span: Span) -> String {
+    format!("@{}..{}", span.start, span.end)
+}
+
+/// Describes where pipeline data came from, for the root of a `--debug` tree,
+/// like `[source: ls, content type: application/json]`.
+pub fn metadata_text(metadata: Option<&PipelineMetadata>) -> String {
+    let Some(metadata) = metadata else {
+        return "[no metadata]".to_string();
+    };
+    let mut parts = Vec::new();
+    match &metadata.data_source {
+        DataSource::Ls => parts.push("source: ls".to_string()),
+        DataSource::HtmlThemes => parts.push("source: html themes".to_string()),
+        DataSource::FilePath(path) => parts.push(format!("source: {}", path.display())),
+        DataSource::None => {}
+    }
+    if let Some(content_type) = &metadata.content_type {
+        parts.push(format!("content type: {content_type}"));
+    }
+    if !metadata.custom.is_empty() {
+        let keys: Vec<&str> = metadata.custom.columns().map(String::as_str).collect();
+        parts.push(format!("custom: {}", keys.join(", ")));
+    }
+    if parts.is_empty() {
+        return "[no metadata]".to_string();
+    }
+    format!("[{}]", parts.join(", "))
+}
+
+fn truncate(text: &str, max: Option<usize>) -> String {
+    match max {
+        Some(max) if text.chars().count() > max => {
+            let mut truncated: String = text.chars().take(max).collect();
+            truncated.push('…');
+            truncated
+        }
+        _ => text.to_string(),
+    }
+}
+
+/// Cuts the text of a node `depth` levels below the root to `--max-width`,
+/// leaving at least a few characters of it however deep it is.
+fn fit(text: String, args: &DataArgs, depth: usize) -> String {
+    const MIN_ROOM: usize = 8;
+    match args.max_width {
+        Some(width) => {
+            let room = width.saturating_sub((depth + 1) * args.indent + 1);
+            truncate(&text, Some(room.max(MIN_ROOM)))
+        }
+        None => text,
+    }
+}
+
+/// Returns the `name` of a record that has one.
+pub fn named_row(value: &Value) -> Option<&str> {
+    value.as_record().ok()?.get("name")?.as_str().ok()
+}
+
+/// Whether a value is an empty record or list, which adds no nodes.
+fn is_empty(value: &Value) -> bool {
+    match value {
+        Value::Record { val, .. } => val.is_empty(),
+        Value::List { vals, .. } => vals.is_empty(),
+        _ => false,
+    }
+}
+
+/// Whether a value has no nested structure.
+fn is_plain(value: &Value) -> bool {
+    !matches!(value, Value::Record { .. } | Value::List { .. })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nu_protocol::{DatetimeFormatConfig, FilesizeConfig, Span, record};
+    use std::sync::atomic::AtomicBool;
+
+    fn texts(item: &StringItem) -> Vec<String> {
+        item.children.iter().map(|c| c.text.clone()).collect()
+    }
+
+    fn build(value: Value, args: &DataArgs) -> StringItem {
+        let mut builder = TreeBuilder::new("root".to_string());
+        from_value_helper(
+            &value,
+            &mut builder,
+            args,
+            &Dedup::default(),
+            Arc::new(Config::default()),
+            &Place::top(),
+        );
+        builder.build()
+    }
+
+    #[test]
+    fn test_numbers_follow_the_config() {
+        let record = Value::test_record(record! {
+            "ratio" => Value::test_float(2.5),
+            "size" => Value::test_filesize(2048),
+        });
+        let config = Config {
+            float_precision: 3,
+            filesize: FilesizeConfig {
+                unit: "KiB".parse().unwrap(),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let mut builder = TreeBuilder::new("root".to_string());
+        from_value_helper(
+            &record,
+            &mut builder,
+            &DataArgs::default(),
+            &Dedup::default(),
+            Arc::new(config),
+            &Place::top(),
+        );
+        let tree = builder.build();
+
+        assert_eq!(texts(&tree.children[0]), vec!["2.500"]);
+        assert_eq!(texts(&tree.children[1]), vec!["2.0 KiB"]);
+    }
+
+    #[test]
+    fn test_durations_are_human_friendly() {
+        let duration = Value::test_duration(7_384_000_000_000);
+        let config = Config::default();
+
+        assert_eq!(
+            leaf_text(&duration, &DataArgs::default(), &config),
+            "2hr 3min 4sec"
+        );
+        let args = DataArgs {
+            raw_durations: true,
+            ..Default::default()
+        };
+        assert_eq!(leaf_text(&duration, &args, &config), "7384000000000");
+    }
+
+    #[test]
+    fn test_max_nodes_leaves_out_the_rest() {
+        let record = Value::test_record(record! {
+            "a" => Value::test_list((1..=5).map(Value::test_int).collect()),
+            "b" => Value::test_int(6),
+            "c" => Value::test_int(7),
+        });
+        let args = DataArgs {
+            max_nodes: Some(3),
+            ..Default::default()
+        };
+        let tree = build(record, &args);
+
+        assert_eq!(texts(&tree), vec!["a", "… 2 more"]);
+        assert_eq!(texts(&tree.children[0]), vec!["1", "2", "… 3 more"]);
+    }
+
+    #[test]
+    fn test_interruption_is_drawn_once() {
+        let args = DataArgs {
+            signals: Some(Signals::new(Arc::new(AtomicBool::new(true)))),
+            ..Default::default()
+        };
+        let mut list = ListTree::new(args, Arc::new(Config::default()));
+        let mut drawn = Vec::new();
+        for value in [
+            Value::test_int(1),
+            Value::test_list(vec![]),
+            Value::test_int(3),
+        ] {
+            drawn.extend(texts(&list.push(value)));
+        }
+        drawn.extend(texts(&list.finish()));
+        assert_eq!(drawn, vec!["… interrupted"]);
+    }
+
+    #[test]
+    fn test_dedup_references_repeated_records() {
+        let address = || {
+            Value::test_record(record! {
+                "city" => Value::test_string("Oslo"),
+                "zip" => Value::test_string("0150"),
+            })
+        };
+        let record = Value::test_record(record! {
+            "home" => address(),
+            "work" => address(),
+            "small" => Value::test_list(vec![Value::test_int(1)]),
+            "again" => Value::test_list(vec![Value::test_int(1)]),
+        });
+        let args = DataArgs {
+            dedup: true,
+            ..Default::default()
+        };
+        let tree = build(record, &args);
+
+        assert_eq!(texts(&tree.children[0]), vec!["city", "zip"]);
+        assert_eq!(texts(&tree.children[1]), vec!["↑ same as home"]);
+        assert_eq!(texts(&tree.children[3]), vec!["1"]);
+    }
+
+    #[test]
+    fn test_dedup_checks_values_behind_equal_fingerprints() {
+        let address = Value::test_record(record! {
+            "city" => Value::test_string("Oslo"),
+            "zip" => Value::test_string("0150"),
+        });
+        let dedup = Dedup::default();
+        let (hash, _) = dedup.fingerprint(&address);
+        // Another value that happens to have the same fingerprint.
+        let other = Value::test_record(record! { "city" => Value::test_string("Bergen") });
+        dedup
+            .drawn
+            .borrow_mut()
+            .insert(hash, vec![(&other, "elsewhere".to_string())]);
+        let args = DataArgs {
+            dedup: true,
+            ..Default::default()
+        };
+        let record = Value::test_record(record! { "home" => address });
+        let mut builder = TreeBuilder::new("root".to_string());
+        from_value_helper(
+            &record,
+            &mut builder,
+            &args,
+            &dedup,
+            Arc::new(Config::default()),
+            &Place::top(),
+        );
+        let tree = builder.build();
+
+        assert_eq!(texts(&tree.children[0]), vec!["city", "zip"]);
+    }
+
+    #[test]
+    fn test_debug_shows_spans_and_metadata() {
+        let record = Value::test_record(record! {
+            "a" => Value::int(1, Span::new(4, 5)),
+        });
+        let args = DataArgs {
+            debug: true,
+            ..Default::default()
+        };
+        let tree = build(record, &args);
+
+        assert_eq!(tree.children[0].text, "a @4..5");
+        assert_eq!(texts(&tree.children[0]), vec!["1 @4..5"]);
+
+        let metadata = PipelineMetadata {
+            data_source: DataSource::FilePath("data.json".into()),
+            content_type: Some("application/json".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(
+            metadata_text(Some(&metadata)),
+            "[source: data.json, content type: application/json]"
+        );
+        assert_eq!(metadata_text(None), "[no metadata]");
+    }
+
+    #[test]
+    fn test_closures_show_their_params() {
+        assert_eq!(
+            closure_text(Some(b"{|x, y| $x + $y }"), 0),
+            "closure |x, y|"
+        );
+        assert_eq!(
+            closure_text(Some(b"{ |sep = '|'  ,n: int| $n }"), 2),
+            "closure |sep = '|' ,n: int| (2 captures)"
+        );
+        assert_eq!(closure_text(Some(b"{ ls }"), 1), "closure (1 capture)");
+        assert_eq!(closure_text(None, 0), "closure");
+    }
+
+    #[test]
+    fn test_binary_preview() {
+        assert_eq!(
+            binary_text(b"Hello\n", 8),
+            "6 bytes: 48 65 6c 6c 6f 0a |Hello.|"
+        );
+        assert_eq!(binary_text(b"Hello", 2), "5 bytes: 48 65 … |He|");
+        assert_eq!(binary_text(b"H", 0), "1 byte");
+    }
+
+    #[test]
+    fn test_dates_follow_the_format() {
+        let date = Value::test_date("2020-01-02T03:04:05+00:00".parse().unwrap());
+        let config = Config {
+            datetime_format: DatetimeFormatConfig {
+                table: Some("%d/%m/%Y".to_string()),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        assert_eq!(
+            leaf_text(&date, &DataArgs::default(), &config),
+            "02/01/2020"
+        );
+        let args = DataArgs {
+            date_format: Some("%Y-%m-%d %H:%M".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(leaf_text(&date, &args, &config), "2020-01-02 03:04");
+    }
+
+    #[test]
+    fn test_named_rows_are_grouped() {
+        let span = Span::test_data();
+        let rows = Value::test_list(vec![
+            Value::test_record(record! {
+                "name" => Value::string("std", span),
+                "commands" => Value::test_list(vec![]),
+            }),
+            Value::test_record(record! {
+                "name" => Value::string("log", span),
+                "commands" => Value::test_list(vec![]),
+            }),
+        ]);
+        let tree = build(rows, &DataArgs::default());
+
+        assert_eq!(texts(&tree), vec!["std", "log"]);
+        assert_eq!(texts(&tree.children[0]), vec!["name", "commands"]);
+    }
+
+    #[test]
+    fn test_long_lists_collapse_with_types() {
+        let path = Value::test_list((0..20).map(|i| Value::test_string(i.to_string())).collect());
+        let env = Value::test_record(record! { "PATH" => path });
+        let args = DataArgs {
+            types: true,
+            collapse: Some(10),
+            ..Default::default()
+        };
+        let tree = build(env, &args);
+
+        assert_eq!(texts(&tree), vec!["PATH: list<string>"]);
+        assert_eq!(texts(&tree.children[0]), vec!["[20 items]"]);
+    }
+
+    #[test]
+    fn test_types_annotate_every_node() {
+        let span = Span::test_data();
+        let rows = Value::test_list(vec![
+            Value::test_record(record! { "name" => Value::string("std", span) }),
+            Value::test_int(3),
+            Value::test_list(vec![Value::test_filesize(2048)]),
+        ]);
+        let env = Value::test_record(record! {
+            "PATH" => Value::test_list(vec![Value::test_string("/bin")]),
+            "mixed" => Value::test_list(vec![Value::test_int(1), Value::test_string("a")]),
+        });
+        let args = DataArgs {
+            types: true,
+            ..Default::default()
+        };
+        let tree = build(rows, &args);
+
+        assert_eq!(
+            texts(&tree),
+            vec!["std: record<name: string>", "3: int", "2.0 kB: filesize"]
+        );
+        assert_eq!(texts(&tree.children[0]), vec!["name: string"]);
+
+        let tree = build(env, &args);
+        assert_eq!(texts(&tree.children[0]), vec!["/bin"]);
+        assert_eq!(texts(&tree.children[1]), vec!["1: int", "a: string"]);
+    }
+
+    #[test]
+    fn test_long_values_fit_the_width() {
+        let record = Value::test_record(record! {
+            "short" => Value::test_string("fits"),
+            "long" => Value::test_string("x".repeat(100)),
+        });
+        let args = DataArgs {
+            max_width: Some(24),
+            indent: 4,
+            ..Default::default()
+        };
+        let tree = build(record, &args);
+
+        assert_eq!(texts(&tree.children[0]), vec!["fits"]);
+        // The value starts 12 columns in, after the root and its field.
+        assert_eq!(
+            texts(&tree.children[1]),
+            vec![format!("{}…", "x".repeat(11))]
+        );
+    }
+
+    #[test]
+    fn test_compact_folds_single_field_chains() {
+        let config = Value::test_record(record! {
+            "a" => Value::test_record(record! {
+                "b" => Value::test_record(record! { "c" => Value::test_int(1) }),
+            }),
+            "server" => Value::test_record(record! {
+                "host" => Value::test_string("localhost"),
+                "tls" => Value::test_record(record! { "enabled" => Value::test_bool(true) }),
+            }),
+        });
+        let args = DataArgs {
+            compact: true,
+            ..Default::default()
+        };
+        let tree = build(config, &args);
+
+        assert_eq!(texts(&tree), vec!["a.b.c → 1", "server"]);
+        assert!(tree.children[0].children.is_empty());
+        assert_eq!(
+            texts(&tree.children[1]),
+            vec!["host → localhost", "tls.enabled → true"]
+        );
+    }
+
+    #[test]
+    fn test_cell_paths_follow_leaves() {
+        let span = Span::test_data();
+        let commands = Value::test_list(vec![Value::test_record(record! {
+            "name" => Value::string("ls", span),
+            "my flags" => Value::test_list(vec![Value::test_string("-a")]),
+        })]);
+        let value = Value::test_record(record! { "commands" => commands });
+        let args = DataArgs {
+            cell_paths: true,
+            ..Default::default()
+        };
+        let tree = build(value.clone(), &args);
+        let row = &tree.children[0].children[0];
+
+        assert_eq!(row.text, "ls");
+        assert_eq!(texts(&row.children[0]), vec!["ls (commands.0.name)"]);
+        assert_eq!(
+            texts(&row.children[1]),
+            vec!["-a (commands.0.\"my flags\".0)"]
+        );
+
+        let compact = build(
+            value,
+            &DataArgs {
+                compact: true,
+                ..args
+            },
+        );
+        assert_eq!(
+            texts(&compact.children[0].children[0]),
+            vec!["name → ls (commands.0.name)", "my flags"]
+        );
+    }
+
+    #[test]
+    fn test_values_are_colored_by_type() {
+        use nu_ansi_term::Color;
+
+        let record = Value::test_record(record! {
+            "name" => Value::test_string("ls"),
+            "size" => Value::test_int(3),
+            "tags" => Value::test_list(vec![Value::test_string("a")]),
+        });
+        let args = DataArgs {
+            styles: HashMap::from([("int".to_string(), Color::Cyan.normal())]),
+            ..Default::default()
+        };
+        let tree = build(record.clone(), &args);
+
+        assert_eq!(texts(&tree.children[0]), vec!["ls"]);
+        assert_eq!(texts(&tree.children[1]), vec!["\u{1b}[36m3\u{1b}[0m"]);
+
+        let compact = build(
+            record,
+            &DataArgs {
+                compact: true,
+                ..args
+            },
+        );
+        assert_eq!(compact.children[1].text, "size → \u{1b}[36m3\u{1b}[0m");
+    }
+
+    #[test]
+    fn test_sort_keys() {
+        let record = Value::test_record(record! {
+            "b" => Value::test_int(1),
+            "c" => Value::test_record(record! {
+                "z" => Value::test_int(2),
+                "y" => Value::test_int(3),
+            }),
+            "a" => Value::test_int(4),
+        });
+        let args = DataArgs {
+            sort_keys: true,
+            ..Default::default()
+        };
+        let tree = build(record, &args);
+
+        assert_eq!(texts(&tree), vec!["a", "b", "c"]);
+        assert_eq!(texts(&tree.children[2]), vec!["y", "z"]);
+    }
+
+    #[test]
+    fn test_depth_limit_leaves_placeholders() {
+        let nested = Value::test_record(record! {
+            "a" => Value::test_record(record! {
+                "b" => Value::test_record(record! { "c" => Value::test_int(1) }),
+                "empty" => Value::test_record(record! {}),
+            }),
+            "x" => Value::test_int(2),
+        });
+        let args = DataArgs {
+            depth: Some(2),
+            ..Default::default()
+        };
+        let tree = build(nested, &args);
+
+        assert_eq!(texts(&tree), vec!["a", "x"]);
+        assert_eq!(texts(&tree.children[0]), vec!["b", "empty"]);
+        assert_eq!(texts(&tree.children[0].children[0]), vec!["…"]);
+        assert!(tree.children[0].children[1].children.is_empty());
+        assert_eq!(texts(&tree.children[1]), vec!["2"]);
+    }
+
+    #[test]
+    fn test_number_items_in_drawing_order() {
+        let env = Value::test_record(record! {
+            "a" => Value::test_int(1),
+            "b" => Value::test_int(2),
+        });
+        let mut tree = build(env, &DataArgs::default());
+        number_items(&mut tree);
+
+        assert_eq!(texts(&tree), vec!["1. a", "3. b"]);
+        assert_eq!(texts(&tree.children[0]), vec!["2. 1"]);
+    }
+
+    #[test]
+    fn test_json_preset() {
+        let users = Value::test_list(
+            (0..12)
+                .map(|i| Value::test_record(record! { "id" => Value::test_int(i) }))
+                .collect(),
+        );
+        let payload = Value::test_record(record! {
+            "users" => users,
+            "note" => Value::test_string("x".repeat(80)),
+        });
+        let tree = build(payload, &DataArgs::preset("json").unwrap());
+
+        assert_eq!(
+            texts(&tree),
+            vec!["users: table<id: int> [12]", "note: string"]
+        );
+        assert_eq!(tree.children[0].children.len(), 11);
+        assert_eq!(tree.children[0].children[10].text, "… 2 more");
+        assert_eq!(
+            tree.children[1].children[0].text,
+            format!("{}…", "x".repeat(60))
+        );
+    }
+
+    #[test]
+    fn test_stream_roots_show_their_type() {
+        let typed = ListTree::new(
+            DataArgs {
+                types: true,
+                ..Default::default()
+            },
+            Arc::new(Config::default()),
+        );
+        assert_eq!(typed.root_label("value"), "value: list<any>");
+        let plain = ListTree::new(DataArgs::default(), Arc::new(Config::default()));
+        assert_eq!(plain.root_label("value"), "value");
+    }
+
+    #[test]
+    fn test_streamed_lists_match_collected_ones() {
+        let streamed = |values: &[Value], args: &DataArgs| {
+            let mut list = ListTree::new(args.clone(), Arc::new(Config::default()));
+            let mut texts_so_far = Vec::new();
+            for value in values {
+                texts_so_far.extend(texts(&list.push(value.clone())));
+            }
+            texts_so_far.extend(texts(&list.finish()));
+            texts_so_far
+        };
+        let plain: Vec<Value> = (0..6).map(Value::test_int).collect();
+        let mut mixed = plain.clone();
+        mixed.push(Value::test_record(
+            record! { "name" => Value::test_string("x") },
+        ));
+
+        for values in [plain, mixed] {
+            for args in [
+                DataArgs::default(),
+                DataArgs::preset("json").unwrap(),
+                DataArgs {
+                    collapse: Some(3),
+                    max_items: Some(4),
+                    ..Default::default()
+                },
+            ] {
+                let collected = build(Value::test_list(values.clone()), &args);
+                assert_eq!(streamed(&values, &args), texts(&collected));
+            }
+        }
+    }
+}
@@ -0,0 +1,400 @@
+//! Implements `tree diff`, which compares two directories and draws the union
+//! of their trees with every entry marked as added, removed, modified or
+//! unchanged.
+
+use crate::{TreePlugin, config, string_output, utils, view};
+use nu_ansi_term::{Color, Style};
+use nu_plugin::{EngineInterface, EvaluatedCall, PluginCommand};
+use nu_protocol::{
+    Category, Example, LabeledError, PipelineData, Signature, Span, SyntaxShape, Type, Value,
+    record,
+};
+use std::collections::{BTreeMap, HashSet};
+use std::fs::File;
+use std::io::{BufReader, Read, Write};
+use std::path::{Path, PathBuf};
+
+pub struct TreeDiff;
+
+impl PluginCommand for TreeDiff {
+    type Plugin = TreePlugin;
+
+    fn name(&self) -> &str {
+        "tree diff"
+    }
+
+    fn signature(&self) -> Signature {
+        Signature::build(self.name())
+            .input_output_types(vec![
+                (Type::Nothing, Type::String),
+                (Type::Nothing, Type::table()),
+            ])
+            .required("old", SyntaxShape::Filepath, "the directory to compare from")
+            .required("new", SyntaxShape::Filepath, "the directory to compare to")
+            .switch("all", "include hidden files", Some('a'))
+            .switch(
+                "gitignore",
+                "respect .gitignore and other ignore files",
+                Some('g'),
+            )
+            .switch(
+                "contents",
+                "compare the contents of files of the same size instead of their modification times",
+                Some('c'),
+            )
+            .named(
+                "threads",
+                SyntaxShape::Int,
+                "walk the tree on this many threads (default 1)",
+                None,
+            )
+            .switch("changed", "leave out unchanged entries", None)
+            .switch(
+                "as-records",
+                "return a table of entries and their status instead of a drawn tree",
+                None,
+            )
+            .category(Category::Experimental)
+    }
+
+    fn description(&self) -> &str {
+        "Compare two directories as a tree of added, removed and modified entries."
+    }
+
+    fn examples(&self) -> Vec<Example<'_>> {
+        vec![
+            Example {
+                example: "tree diff dist.old dist",
+                description: "Show what changed between two builds",
+                result: None,
+            },
+            Example {
+                example: "tree diff backup project --contents --as-records | where status == modified",
+                description: "List the files whose contents differ from a backup",
+                result: None,
+            },
+        ]
+    }
+
+    fn run(
+        &self,
+        _plugin: &TreePlugin,
+        engine: &EngineInterface,
+        call: &EvaluatedCall,
+        _input: PipelineData,
+    ) -> Result<PipelineData, LabeledError> {
+        let cwd = PathBuf::from(engine.get_current_dir()?);
+        let old: PathBuf = call.req(0)?;
+        let new: PathBuf = call.req(1)?;
+        let walk = |path: PathBuf| -> Result<(view::ViewArgs, Vec<view::Entry>), LabeledError> {
+            let mut args = view::ViewArgs {
+                path: cwd.join(path),
+                all: call.has_flag("all")?,
+                gitignore: call.has_flag("gitignore")?,
+                ..Default::default()
+            };
+            config::walk_tuning(engine, call, &mut args)?;
+            let entries = view::walk(&args, true)
+                .and_then(view::Walk::complete)
+                .map_err(|err| {
+                    LabeledError::new(format!("Error trying to walk the path: {}", err))
+                        .with_label("can't compare this path", call.head)
+                })?;
+            Ok((args, entries))
+        };
+        let (old_args, old_entries) = walk(old)?;
+        let (new_args, new_entries) = walk(new)?;
+
+        let mut diffs = compare(
+            &old_args.path,
+            &old_entries,
+            &new_args.path,
+            &new_entries,
+            call.has_flag("contents")?,
+            old_args.threads,
+        );
+        if call.has_flag("changed")? {
+            diffs.retain(|diff| diff.status != Status::Unchanged);
+        }
+
+        if call.has_flag("as-records")? {
+            let rows = diffs.iter().map(|diff| diff.to_value(call.head)).collect();
+            return Ok(PipelineData::Value(Value::list(rows, call.head), None));
+        }
+
+        let config = engine.get_config()?;
+        let args = view::ViewArgs {
+            path: new_args.path,
+            color: config::color_choice(engine, &config)?,
+            branches: config::branch_style(engine, call)?,
+            summary: view::Summary::None,
+            ..Default::default()
+        };
+        let mut out = Vec::new();
+        render(&args, &old_args.path, &diffs, &mut out);
+        Ok(string_output(out, call.head))
+    }
+}
+
+/// How an entry differs between the two directories.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Status {
+    Added,
+    Removed,
+    Modified,
+    Unchanged,
+}
+
+impl Status {
+    fn name(&self) -> &'static str {
+        match self {
+            Status::Added => "added",
+            Status::Removed => "removed",
+            Status::Modified => "modified",
+            Status::Unchanged => "unchanged",
+        }
+    }
+
+    fn marker(&self) -> char {
+        match self {
+            Status::Added => '+',
+            Status::Removed => '-',
+            Status::Modified => '~',
+            Status::Unchanged => ' ',
+        }
+    }
+
+    fn style(&self) -> Style {
+        match self {
+            Status::Added => Color::Green.normal(),
+            Status::Removed => Color::Red.normal(),
+            Status::Modified => Color::Yellow.normal(),
+            Status::Unchanged => Style::new().dimmed(),
+        }
+    }
+}
+
+/// An entry of either directory, identified by its path below the root.
+#[derive(Debug, PartialEq)]
+struct Diff {
+    path: PathBuf,
+    is_dir: bool,
+    status: Status,
+    old_size: Option<u64>,
+    new_size: Option<u64>,
+}
+
+impl Diff {
+    fn depth(&self) -> usize {
+        self.path.components().count()
+    }
+
+    fn to_value(&self, span: Span) -> Value {
+        let size = |size: Option<u64>| {
+            size.map_or(Value::nothing(span), |size| {
+                Value::filesize(size as i64, span)
+            })
+        };
+        Value::record(
+            record! {
+                "path" => Value::string(self.path.to_string_lossy(), span),
+                "type" => Value::string(if self.is_dir { "dir" } else { "file" }, span),
+                "status" => Value::string(self.status.name(), span),
+                "old_size" => size(self.old_size),
+                "new_size" => size(self.new_size),
+            },
+            span,
+        )
+    }
+}
+
+/// Pairs up the entries of both walks by their path below the root, in
+/// depth-first order. A directory counts as modified when anything below it is.
+///
+/// Files are compared on up to `threads` threads, since comparing their
+/// contents reads both of them.
+fn compare(
+    old_root: &Path,
+    old: &[view::Entry],
+    new_root: &Path,
+    new: &[view::Entry],
+    contents: bool,
+    threads: usize,
+) -> Vec<Diff> {
+    let relative = |root: &Path, entry: &view::Entry| {
+        entry
+            .path
+            .strip_prefix(root)
+            .unwrap_or(&entry.path)
+            .to_path_buf()
+    };
+    let mut union: BTreeMap<PathBuf, (Option<&view::Entry>, Option<&view::Entry>)> =
+        BTreeMap::new();
+    for entry in old {
+        union.entry(relative(old_root, entry)).or_default().0 = Some(entry);
+    }
+    for entry in new {
+        union.entry(relative(new_root, entry)).or_default().1 = Some(entry);
+    }
+
+    let union: Vec<_> = union.into_iter().collect();
+    // Without `--contents`, files are compared by their metadata alone.
+    let threads = if contents { threads } else { 1 };
+    let statuses = utils::parallel_map(&union, threads, |(_, pair)| match *pair {
+        (Some(_), None) => Status::Removed,
+        (None, Some(_)) => Status::Added,
+        (Some(old), Some(new)) if old.is_dir != new.is_dir => Status::Modified,
+        (Some(old), Some(new)) if old.is_dir || files_match(old, new, contents) => {
+            Status::Unchanged
+        }
+        _ => Status::Modified,
+    });
+    let mut diffs: Vec<Diff> = union
+        .into_iter()
+        .zip(statuses)
+        .map(|((path, pair), status)| Diff {
+            path,
+            is_dir: pair.1.or(pair.0).is_some_and(|entry| entry.is_dir),
+            status,
+            old_size: pair.0.and_then(|entry| entry.size),
+            new_size: pair.1.and_then(|entry| entry.size),
+        })
+        .collect();
+
+    let changed_dirs: HashSet<PathBuf> = diffs
+        .iter()
+        .filter(|diff| diff.status != Status::Unchanged)
+        .flat_map(|diff| diff.path.ancestors().skip(1))
+        .map(Path::to_path_buf)
+        .collect();
+    for diff in &mut diffs {
+        if diff.status == Status::Unchanged && changed_dirs.contains(&diff.path) {
+            diff.status = Status::Modified;
+        }
+    }
+    diffs
+}
+
+/// Whether two files hold the same data, judged by size and then by either
+/// their contents or their modification times.
+fn files_match(old: &view::Entry, new: &view::Entry, contents: bool) -> bool {
+    if old.size != new.size {
+        return false;
+    }
+    if contents {
+        same_contents(&old.path, &new.path).unwrap_or(false)
+    } else {
+        old.modified == new.modified
+    }
+}
+
+fn same_contents(a: &Path, b: &Path) -> std::io::Result<bool> {
+    let mut a = BufReader::new(File::open(a)?);
+    let mut b = BufReader::new(File::open(b)?);
+    let mut buf_a = [0; 8192];
+    let mut buf_b = [0; 8192];
+    loop {
+        let read = a.read(&mut buf_a)?;
+        if read == 0 {
+            return Ok(b.read(&mut buf_b)? == 0);
+        }
+        b.read_exact(&mut buf_b[..read])?;
+        if buf_a[..read] != buf_b[..read] {
+            return Ok(false);
+        }
+    }
+}
+
+/// Draws the diffs as a tree below an `old → new` root line, followed by how
+/// many entries have each status.
+fn render(args: &view::ViewArgs, old_root: &Path, diffs: &[Diff], out: &mut Vec<u8>) {
+    let styled = |style: Style| args.color.style(style);
+    let _ = writeln!(
+        out,
+        "{}",
+        styled(Style::new().bold()).paint(format!(
+            "{} → {}",
+            old_root.display(),
+            args.path.display()
+        ))
+    );
+
+    let rows: Vec<view::Row> = diffs
+        .iter()
+        .map(|diff| {
+            let style = styled(diff.status.style());
+            let name = utils::escape_control(&utils::escape_invalid(
+                diff.path.file_name().unwrap_or(diff.path.as_os_str()),
+            ));
+            view::Row {
+                depth: diff.depth(),
+                leading: format!("{} ", style.paint(diff.status.marker().to_string())),
+                label: style.paint(name).to_string(),
+            }
+        })
+        .collect();
+    view::write_rows(args, &rows, view::Totals::default(), out);
+
+    let count = |status| diffs.iter().filter(|diff| diff.status == status).count();
+    let _ = write!(
+        out,
+        "\n{} added, {} removed, {} modified, {} unchanged",
+        count(Status::Added),
+        count(Status::Removed),
+        count(Status::Modified),
+        count(Status::Unchanged)
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::SystemTime;
+
+    fn entry(path: &str, size: Option<u64>, modified: u64) -> view::Entry {
+        view::Entry {
+            path: PathBuf::from(path),
+            name: path.rsplit('/').next().unwrap_or(path).to_string(),
+            depth: path.matches('/').count(),
+            is_dir: size.is_none(),
+            size,
+            modified: Some(SystemTime::UNIX_EPOCH + std::time::Duration::from_secs(modified)),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_compare_marks_entries_and_ancestors() {
+        let old = vec![
+            entry("a/src", None, 0),
+            entry("a/src/main.rs", Some(10), 1),
+            entry("a/src/old.rs", Some(5), 1),
+            entry("a/docs", None, 0),
+            entry("a/docs/guide.md", Some(3), 1),
+        ];
+        let new = vec![
+            entry("b/src", None, 0),
+            entry("b/src/main.rs", Some(10), 2),
+            entry("b/src/new.rs", Some(5), 1),
+            entry("b/docs", None, 0),
+            entry("b/docs/guide.md", Some(3), 1),
+        ];
+        let diffs = compare(Path::new("a"), &old, Path::new("b"), &new, false, 1);
+        let statuses: Vec<(&str, Status)> = diffs
+            .iter()
+            .map(|diff| (diff.path.to_str().unwrap(), diff.status))
+            .collect();
+
+        assert_eq!(
+            statuses,
+            vec![
+                ("docs", Status::Unchanged),
+                ("docs/guide.md", Status::Unchanged),
+                ("src", Status::Modified),
+                ("src/main.rs", Status::Modified),
+                ("src/new.rs", Status::Added),
+                ("src/old.rs", Status::Removed),
+            ]
+        );
+    }
+}
@@ -0,0 +1,220 @@
+//! Implements `tree du`, which totals the size of every directory below a path,
+//! as a structured alternative to `du -sh *`.
+
+use crate::{TreePlugin, config, view};
+use nu_plugin::{EngineInterface, EvaluatedCall, PluginCommand};
+use nu_protocol::{
+    Category, Example, LabeledError, PipelineData, Signature, Span, SyntaxShape, Type, Value,
+    record,
+};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+pub struct TreeDu;
+
+impl PluginCommand for TreeDu {
+    type Plugin = TreePlugin;
+
+    fn name(&self) -> &str {
+        "tree du"
+    }
+
+    fn signature(&self) -> Signature {
+        Signature::build(self.name())
+            .input_output_types(vec![(Type::Nothing, Type::table())])
+            .optional(
+                "path",
+                SyntaxShape::Filepath,
+                "the directory to total (defaults to the current directory)",
+            )
+            .named(
+                "depth",
+                SyntaxShape::Int,
+                "how many levels of directories to list (default 1)",
+                Some('d'),
+            )
+            .switch("all", "include hidden files", Some('a'))
+            .switch(
+                "gitignore",
+                "respect .gitignore and other ignore files",
+                Some('g'),
+            )
+            .named(
+                "threads",
+                SyntaxShape::Int,
+                "walk the tree on this many threads (default 1)",
+                None,
+            )
+            .category(Category::Experimental)
+    }
+
+    fn description(&self) -> &str {
+        "List the directories below a path with their total size, largest first."
+    }
+
+    fn examples(&self) -> Vec<Example<'_>> {
+        vec![
+            Example {
+                example: "tree du",
+                description: "Show how much space each directory in the current directory takes",
+                result: None,
+            },
+            Example {
+                example: "tree du ~/projects --depth 2 | where percent > 5",
+                description: "Find the directories two levels down that take more than 5% of the space",
+                result: None,
+            },
+        ]
+    }
+
+    fn run(
+        &self,
+        _plugin: &TreePlugin,
+        engine: &EngineInterface,
+        call: &EvaluatedCall,
+        _input: PipelineData,
+    ) -> Result<PipelineData, LabeledError> {
+        let cwd = PathBuf::from(engine.get_current_dir()?);
+        let path = match call.opt::<PathBuf>(0)? {
+            Some(path) => cwd.join(path),
+            None => cwd,
+        };
+        let depth = call
+            .get_flag::<i64>("depth")?
+            .map_or(1, |depth| depth.max(1) as usize);
+
+        let mut args = view::ViewArgs {
+            path,
+            all: call.has_flag("all")?,
+            gitignore: call.has_flag("gitignore")?,
+            ..Default::default()
+        };
+        config::walk_tuning(engine, call, &mut args)?;
+        let entries = view::walk(&args, true)
+            .and_then(view::Walk::complete)
+            .map_err(|err| {
+                LabeledError::new(format!("Error trying to walk the path: {}", err))
+                    .with_label("can't total this path", call.head)
+            })?;
+
+        let rows = totals(&args.path, &entries, depth)
+            .into_iter()
+            .map(|total| total.into_value(call.head))
+            .collect();
+        Ok(PipelineData::Value(Value::list(rows, call.head), None))
+    }
+}
+
+/// The cumulative size of a directory.
+#[derive(Debug, PartialEq)]
+struct DirTotal {
+    /// The directory's path relative to the root.
+    path: PathBuf,
+    size: u64,
+    files: u64,
+    /// The directory's share of the size of everything below the root.
+    percent: f64,
+}
+
+impl DirTotal {
+    fn into_value(self, span: Span) -> Value {
+        Value::record(
+            record! {
+                "path" => Value::string(self.path.to_string_lossy(), span),
+                "size" => Value::filesize(self.size as i64, span),
+                "files" => Value::int(self.files as i64, span),
+                "percent" => Value::float((self.percent * 10.0).round() / 10.0, span),
+            },
+            span,
+        )
+    }
+}
+
+/// Totals the files below each directory up to `depth` levels below the root,
+/// largest first.
+fn totals(root: &Path, entries: &[view::Entry], depth: usize) -> Vec<DirTotal> {
+    let mut dirs: HashMap<&Path, (u64, u64)> = entries
+        .iter()
+        .filter(|entry| entry.is_dir && entry.depth <= depth)
+        .map(|entry| (entry.path.as_path(), (0, 0)))
+        .collect();
+
+    let mut grand_total = 0;
+    for entry in entries.iter().filter(|entry| !entry.is_dir) {
+        let size = entry.size.unwrap_or(0);
+        grand_total += size;
+        for ancestor in entry.path.ancestors().skip(1) {
+            if ancestor == root {
+                break;
+            }
+            if let Some((dir_size, files)) = dirs.get_mut(ancestor) {
+                *dir_size += size;
+                *files += 1;
+            }
+        }
+    }
+
+    let mut totals: Vec<DirTotal> = dirs
+        .into_iter()
+        .map(|(path, (size, files))| DirTotal {
+            path: path.strip_prefix(root).unwrap_or(path).to_path_buf(),
+            size,
+            files,
+            percent: if grand_total == 0 {
+                0.0
+            } else {
+                size as f64 * 100.0 / grand_total as f64
+            },
+        })
+        .collect();
+    totals.sort_by(|a, b| b.size.cmp(&a.size).then_with(|| a.path.cmp(&b.path)));
+    totals
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(path: &str, depth: usize, size: Option<u64>) -> view::Entry {
+        view::Entry {
+            path: PathBuf::from(path),
+            name: path.rsplit('/').next().unwrap_or(path).to_string(),
+            depth,
+            is_dir: size.is_none(),
+            size,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_totals_are_cumulative_and_sorted() {
+        let entries = vec![
+            entry("root/docs", 1, None),
+            entry("root/docs/guide.md", 2, Some(100)),
+            entry("root/src", 1, None),
+            entry("root/src/bin", 2, None),
+            entry("root/src/bin/main.rs", 3, Some(500)),
+            entry("root/src/lib.rs", 2, Some(200)),
+            entry("root/README.md", 1, Some(200)),
+        ];
+        let totals = totals(Path::new("root"), &entries, 1);
+
+        assert_eq!(
+            totals,
+            vec![
+                DirTotal {
+                    path: PathBuf::from("src"),
+                    size: 700,
+                    files: 2,
+                    percent: 70.0,
+                },
+                DirTotal {
+                    path: PathBuf::from("docs"),
+                    size: 100,
+                    files: 1,
+                    percent: 10.0,
+                },
+            ]
+        );
+    }
+}
@@ -0,0 +1,187 @@
+//! Builds trees from tables where each row references its parent by id, such as
+//! the output of `ps` with its `pid` and `ppid` columns.
+
+use nu_protocol::{LabeledError, Record, Span, Value};
+use ptree::TreeBuilder;
+use ptree::item::StringItem;
+use std::collections::HashMap;
+
+/// The columns that link the rows of an edge table together.
+pub struct EdgeColumns<'a> {
+    pub id: &'a str,
+    pub parent: &'a str,
+}
+
+/// Builds a tree from `rows`, nesting every row under the row whose id matches its parent id.
+///
+/// Rows whose parent can't be found become top-level nodes. Rows that are only
+/// reachable through a cycle are also shown at the top level, so nothing is
+/// silently dropped.
+pub fn build(
+    rows: &[Value],
+    columns: &EdgeColumns,
+    root: &str,
+    label: impl Fn(&Record) -> String,
+    span: Span,
+) -> Result<StringItem, LabeledError> {
+    let mut records = Vec::with_capacity(rows.len());
+    for row in rows {
+        records.push(row.as_record().map_err(|_| {
+            LabeledError::new("Expected a table").with_label(
+                format!("expected a record, got {}", row.get_type()),
+                row.span(),
+            )
+        })?);
+    }
+
+    let ids = records
+        .iter()
+        .map(|record| required(record, columns.id, span).map(key))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let mut index = HashMap::new();
+    for (i, id) in ids.iter().enumerate() {
+        if let Some(id) = id {
+            index.entry(id.clone()).or_insert(i);
+        }
+    }
+
+    let mut children: Vec<Vec<usize>> = vec![Vec::new(); records.len()];
+    let mut roots = Vec::new();
+    for (i, record) in records.iter().enumerate() {
+        let parent = record
+            .get(columns.parent)
+            .and_then(key)
+            .and_then(|parent| index.get(&parent).copied())
+            .filter(|parent| *parent != i);
+        match parent {
+            Some(parent) => children[parent].push(i),
+            None => roots.push(i),
+        }
+    }
+
+    let mut builder = TreeBuilder::new(root.to_string());
+    let mut visited = vec![false; records.len()];
+    for i in roots.into_iter().chain(0..records.len()) {
+        if !visited[i] {
+            add_node(i, &records, &children, &label, &mut visited, &mut builder);
+        }
+    }
+    Ok(builder.build())
+}
+
+fn add_node(
+    i: usize,
+    records: &[&Record],
+    children: &[Vec<usize>],
+    label: &impl Fn(&Record) -> String,
+    visited: &mut [bool],
+    builder: &mut TreeBuilder,
+) {
+    visited[i] = true;
+    let pending: Vec<usize> = children[i]
+        .iter()
+        .copied()
+        .filter(|c| !visited[*c])
+        .collect();
+    if pending.is_empty() {
+        builder.add_empty_child(label(records[i]));
+        return;
+    }
+    builder.begin_child(label(records[i]));
+    for child in pending {
+        if !visited[child] {
+            add_node(child, records, children, label, visited, builder);
+        }
+    }
+    builder.end_child();
+}
+
+fn required<'a>(record: &'a Record, column: &str, span: Span) -> Result<&'a Value, LabeledError> {
+    record.get(column).ok_or_else(|| {
+        LabeledError::new(format!("Missing column '{column}'"))
+            .with_label("every row needs an id and a parent id", span)
+    })
+}
+
+/// Turns an id value into a lookup key. Empty parents (`null`) have no key.
+fn key(value: &Value) -> Option<String> {
+    match value {
+        Value::Nothing { .. } => None,
+        Value::String { val, .. } => Some(val.clone()),
+        Value::Int { val, .. } => Some(val.to_string()),
+        Value::Float { val, .. } => Some(val.to_string()),
+        Value::Bool { val, .. } => Some(val.to_string()),
+        other => Some(format!("{other:?}")),
+    }
+}
+
+/// The default label for a row: its `name` column followed by its id, or just the id.
+pub fn default_label<'a>(columns: &'a EdgeColumns) -> impl Fn(&Record) -> String + 'a {
+    move |record| {
+        let id = record.get(columns.id).and_then(key).unwrap_or_default();
+        match record.get("name").and_then(|name| name.coerce_str().ok()) {
+            Some(name) => format!("{name} ({id})"),
+            None => id,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nu_protocol::record;
+
+    fn process(pid: i64, ppid: i64) -> Value {
+        let span = Span::test_data();
+        Value::test_record(record! {
+            "pid" => Value::int(pid, span),
+            "ppid" => Value::int(ppid, span),
+        })
+    }
+
+    fn texts(item: &StringItem) -> Vec<String> {
+        item.children.iter().map(|c| c.text.clone()).collect()
+    }
+
+    #[test]
+    fn test_build_nests_rows_under_parents() {
+        let rows = vec![process(1, 0), process(2, 1), process(3, 2), process(4, 1)];
+        let columns = EdgeColumns {
+            id: "pid",
+            parent: "ppid",
+        };
+        let tree = build(
+            &rows,
+            &columns,
+            "root",
+            default_label(&columns),
+            Span::test_data(),
+        )
+        .unwrap();
+
+        assert_eq!(texts(&tree), vec!["1"]);
+        assert_eq!(texts(&tree.children[0]), vec!["2", "4"]);
+        assert_eq!(texts(&tree.children[0].children[0]), vec!["3"]);
+    }
+
+    #[test]
+    fn test_build_keeps_rows_in_cycles() {
+        let rows = vec![process(5, 6), process(6, 5)];
+        let columns = EdgeColumns {
+            id: "pid",
+            parent: "ppid",
+        };
+        let tree = build(
+            &rows,
+            &columns,
+            "root",
+            default_label(&columns),
+            Span::test_data(),
+        )
+        .unwrap();
+
+        assert_eq!(texts(&tree), vec!["5"]);
+        assert_eq!(texts(&tree.children[0]), vec!["6"]);
+    }
+}
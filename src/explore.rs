@@ -0,0 +1,1566 @@
+//! Implements `tree explore`, a full-screen tree that can be browsed with the
+//! keyboard.
+//!
+//! Entries are drawn by the classic view, with the same icons, git status and
+//! sizes as path mode, next to a preview of the selected entry. Typing `/`
+//! filters the tree by a fuzzy match on names, `e` opens the selected entry in
+//! the user's editor and `y` copies its path to the clipboard. Typing a number,
+//! or `:` and a number, jumps to that entry, as numbered by `--numbered`.
+//! Entries can be deleted, renamed and created after a prompt, like in a small
+//! file manager. Piped data is explored the same way, with records and lists
+//! folded like directories.
+//!
+//! Directories are only read when they are first expanded, so exploring a huge
+//! tree, or `/`, starts right away. Collapsed directories on screen show how
+//! many entries they hold, which only lists them without reading metadata. The
+//! filter reads the rest of the tree once.
+
+use crate::keymap::{Action, Keymap};
+use crate::{TreePlugin, config, data, git, theme, utils, view};
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use crossterm::{cursor, execute, queue, style, terminal};
+use lscolors::LsColors;
+use nu_ansi_term::Style;
+use nu_plugin::{EngineInterface, EvaluatedCall, PluginCommand};
+use nu_protocol::{
+    Category, Config, Example, LabeledError, PipelineData, Signature, SyntaxShape, Type, Value,
+};
+use std::fs::{self, File};
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// How much of a file is read for its preview.
+const PREVIEW_BYTES: u64 = 16 * 1024;
+
+/// Terminals narrower than this have no room for the preview pane.
+const MIN_PREVIEW_WIDTH: u16 = 60;
+
+/// The filter stops reading the rest of the tree once it holds this many entries.
+const FILTER_MAX_ENTRIES: usize = 100_000;
+
+/// How long the filter reads the rest of the tree before checking for keys.
+const FILTER_READ_TIME: Duration = Duration::from_millis(50);
+
+pub struct TreeExplore;
+
+impl PluginCommand for TreeExplore {
+    type Plugin = TreePlugin;
+
+    fn name(&self) -> &str {
+        "tree explore"
+    }
+
+    fn signature(&self) -> Signature {
+        Signature::build(self.name())
+            .input_output_types(vec![
+                (Type::Nothing, Type::List(Box::new(Type::String))),
+                (Type::Any, Type::List(Box::new(Type::Any))),
+            ])
+            .optional(
+                "path",
+                SyntaxShape::Filepath,
+                "the directory to explore (defaults to the current directory)",
+            )
+            .switch("all", "include hidden files", Some('a'))
+            .switch(
+                "gitignore",
+                "respect .gitignore and other ignore files",
+                Some('g'),
+            )
+            .switch(
+                "dereference",
+                "show the metadata of the targets of symlinks instead of the links, like ls -L",
+                None,
+            )
+            .switch(
+                "escape",
+                "also escape backslashes in names, so every name reads back unambiguously",
+                None,
+            )
+            .named(
+                "columns",
+                SyntaxShape::List(Box::new(SyntaxShape::String)),
+                "the metadata columns to show, in order: perms, size, modified, owner, git",
+                None,
+            )
+            .switch(
+                "numbered",
+                "number the visible entries, so they can be jumped to by typing their number",
+                Some('n'),
+            )
+            .category(Category::Experimental)
+    }
+
+    fn description(&self) -> &str {
+        "Browse a directory tree interactively, returning the paths marked with space, or the file picked with enter."
+    }
+
+    fn examples(&self) -> Vec<Example<'_>> {
+        vec![
+            Example {
+                example: "tree explore",
+                description: "Browse the current directory with the arrow keys",
+                result: None,
+            },
+            Example {
+                example: "open big.json | tree explore",
+                description: "Browse piped data, folding and unfolding records and lists",
+                result: None,
+            },
+            Example {
+                example: "tree explore src | each {|path| open $path }",
+                description: "Pick files and open them",
+                result: None,
+            },
+            Example {
+                example: "tree explore src | if ($in | is-not-empty) { ^$env.EDITOR ...$in }",
+                description: "Pick files to edit",
+                result: None,
+            },
+        ]
+    }
+
+    fn run(
+        &self,
+        plugin: &TreePlugin,
+        engine: &EngineInterface,
+        call: &EvaluatedCall,
+        input: PipelineData,
+    ) -> Result<PipelineData, LabeledError> {
+        let cwd = PathBuf::from(engine.get_current_dir()?);
+        let data = match input {
+            PipelineData::Empty => None,
+            input => Some(input.into_value(call.head)?),
+        };
+        let path = match call.opt::<PathBuf>(0)? {
+            Some(path) => cwd.join(path),
+            None => cwd,
+        };
+        if data.is_none() && !path.is_dir() {
+            return Err(
+                LabeledError::new(format!("'{}' is not a directory.", path.display()))
+                    .with_label("can't explore this path", call.head),
+            );
+        }
+
+        let config = engine.get_config()?;
+        // Entries are decorated like path mode, with the same columns.
+        let columns = config::columns(call)?;
+        let shows = |column| columns.as_ref().is_none_or(|c| c.contains(&column));
+        let args = view::ViewArgs {
+            path,
+            color: config::color_choice(engine, &config)?,
+            all: call.has_flag("all")?,
+            gitignore: call.has_flag("gitignore")?,
+            git_status: shows(view::Column::Git),
+            size: shows(view::Column::Size),
+            icons: true,
+            permissions: shows(view::Column::Permissions),
+            branches: config::branch_style(engine, call)?,
+            theme: Some(theme::load()?),
+            columns,
+            dereference: call.has_flag("dereference")?,
+            escape: call.has_flag("escape")?,
+            numbered: call.has_flag("numbered")?,
+            ..Default::default()
+        };
+        let ls_colors = plugin.ls_colors(engine)?;
+        let editor = editor(engine, &config.buffer_editor)?;
+        let keymap = config::keymap(engine)?;
+
+        let mut explorer = match data {
+            Some(value) => Explorer::from_value(args, ls_colors, keymap, config, value),
+            None => Explorer::new(args, ls_colors, editor, keymap, config).map_err(|err| {
+                LabeledError::new(format!("Error trying to explore the path: {err}"))
+            })?,
+        };
+
+        let _foreground = engine.enter_foreground()?;
+        let picked = explorer.run().map_err(|err| {
+            LabeledError::new(format!("Error while exploring: {err}"))
+                .with_label("terminal error", call.head)
+        })?;
+
+        let picked = picked
+            .into_iter()
+            .map(|i| match &explorer.nodes[i].item {
+                Item::Path(entry) => Value::string(entry.path.to_string_lossy(), call.head),
+                Item::Data { value, .. } => value.clone(),
+            })
+            .collect();
+        Ok(PipelineData::Value(Value::list(picked, call.head), None))
+    }
+}
+
+/// What a node of the explored tree holds.
+enum Item {
+    /// A file or directory.
+    Path(view::Entry),
+    /// A value of piped data, below a record key or list index.
+    Data {
+        depth: usize,
+        key: String,
+        value: Value,
+    },
+}
+
+impl Item {
+    fn depth(&self) -> usize {
+        match self {
+            Item::Path(entry) => entry.depth,
+            Item::Data { depth, .. } => *depth,
+        }
+    }
+
+    fn name(&self) -> &str {
+        match self {
+            Item::Path(entry) => &entry.name,
+            Item::Data { key, .. } => key,
+        }
+    }
+
+    /// Whether the item is a directory, record or list, which can be expanded.
+    fn is_dir(&self) -> bool {
+        match self {
+            Item::Path(entry) => entry.is_dir,
+            Item::Data { value, .. } => {
+                matches!(value, Value::Record { .. } | Value::List { .. })
+            }
+        }
+    }
+}
+
+/// An entry of the explored tree.
+struct Node {
+    item: Item,
+    expanded: bool,
+    /// The indices of the entries in this directory, record or list, once it
+    /// has been read.
+    children: Option<Vec<usize>>,
+    /// How many entries this directory holds, once it has been on screen.
+    /// `None` inside means it couldn't be read.
+    count: Option<Option<usize>>,
+}
+
+impl Node {
+    fn new(item: Item) -> Self {
+        Node {
+            item,
+            expanded: false,
+            children: None,
+            count: None,
+        }
+    }
+}
+
+/// A question asked on the status line before changing the filesystem.
+enum Prompt {
+    /// Waits for `y` before deleting an entry.
+    Delete(usize),
+    /// Reads the new name of an entry.
+    Rename { node: usize, name: String },
+    /// Reads the name of a directory to create in a directory, or in the
+    /// root when there is none.
+    NewDir { dir: Option<usize>, name: String },
+    /// Reads the number of the entry to move to.
+    Jump(String),
+}
+
+struct Explorer {
+    args: view::ViewArgs,
+    ls_colors: Arc<LsColors>,
+    git: Option<git::GitRepoStatus>,
+    nodes: Vec<Node>,
+    /// The entries directly below the root.
+    top: Vec<usize>,
+    /// The position of the selected entry among the visible ones.
+    cursor: usize,
+    /// The position of the first visible entry drawn on screen.
+    scroll: usize,
+    /// Whether the preview pane is shown.
+    preview: bool,
+    /// Only entries whose name fuzzy matches this, and their ancestors, are shown.
+    filter: String,
+    /// Whether keys are typed into the filter.
+    typing: bool,
+    /// The marked entries, in the order they were marked.
+    marked: Vec<usize>,
+    /// The program and arguments that `e` opens entries with.
+    editor: Option<Vec<String>>,
+    /// Shown instead of the key bindings until the next key is pressed.
+    message: Option<String>,
+    /// Shown instead of the key bindings until it is answered.
+    prompt: Option<Prompt>,
+    /// While filtering, the next entry to check for a directory that wasn't
+    /// read yet. The rest of the tree is read a little at a time between key
+    /// presses, until it is all read or too big.
+    reading: Option<usize>,
+    keymap: Keymap,
+    config: Arc<Config>,
+    /// Shown above the tree: the explored path, or the type of piped data.
+    title: String,
+    /// Set when piped data is explored, which leaves the files alone.
+    data: bool,
+}
+
+impl Explorer {
+    fn new(
+        args: view::ViewArgs,
+        ls_colors: Arc<LsColors>,
+        editor: Option<Vec<String>>,
+        keymap: Keymap,
+        config: Arc<Config>,
+    ) -> anyhow::Result<Self> {
+        // The status of the whole repository is read once and looked up as
+        // directories are expanded.
+        let git = if args.git_status {
+            git::load_status(
+                &dunce::canonicalize(&args.path)?,
+                git::StatusOptions {
+                    ignored: args.git_ignored,
+                    submodules: args.git_submodules,
+                    diff_stats: args.git_diff_stats,
+                },
+            )?
+        } else {
+            None
+        };
+        let title = args.path.display().to_string();
+        let mut explorer = Explorer {
+            args,
+            ls_colors,
+            git,
+            title,
+            data: false,
+            config,
+            nodes: Vec::new(),
+            top: Vec::new(),
+            cursor: 0,
+            scroll: 0,
+            preview: true,
+            filter: String::new(),
+            typing: false,
+            marked: Vec::new(),
+            editor,
+            message: None,
+            prompt: None,
+            reading: Some(0),
+            keymap,
+        };
+        explorer.top = explorer.read_dir(&explorer.args.path.clone(), 1)?;
+        Ok(explorer)
+    }
+
+    /// Explores piped data instead of a directory. Records and lists are
+    /// expanded like directories.
+    fn from_value(
+        args: view::ViewArgs,
+        ls_colors: Arc<LsColors>,
+        keymap: Keymap,
+        config: Arc<Config>,
+        value: Value,
+    ) -> Self {
+        let mut explorer = Explorer {
+            args,
+            ls_colors,
+            git: None,
+            title: value.get_type().to_string(),
+            data: true,
+            config,
+            nodes: Vec::new(),
+            top: Vec::new(),
+            cursor: 0,
+            scroll: 0,
+            preview: true,
+            filter: String::new(),
+            typing: false,
+            marked: Vec::new(),
+            editor: None,
+            message: None,
+            prompt: None,
+            reading: Some(0),
+            keymap,
+        };
+        explorer.top = match value {
+            Value::Record { .. } | Value::List { .. } => explorer.add_values(&value, 1),
+            value => {
+                explorer.nodes.push(Node::new(Item::Data {
+                    depth: 1,
+                    key: "value".to_string(),
+                    value,
+                }));
+                vec![0]
+            }
+        };
+        explorer
+    }
+
+    /// Adds the fields of a record or the items of a list to the tree.
+    fn add_values(&mut self, value: &Value, depth: usize) -> Vec<usize> {
+        let children: Vec<(String, Value)> = match value {
+            Value::Record { val, .. } => val
+                .iter()
+                .map(|(key, value)| (key.clone(), value.clone()))
+                .collect(),
+            // Rows with a name, like the output of `ls`, are shown by that name.
+            Value::List { vals, .. } => vals
+                .iter()
+                .enumerate()
+                .map(|(i, value)| {
+                    let key = data::named_row(value).map_or(i.to_string(), String::from);
+                    (key, value.clone())
+                })
+                .collect(),
+            _ => Vec::new(),
+        };
+        let mut indices = Vec::with_capacity(children.len());
+        for (key, value) in children {
+            indices.push(self.nodes.len());
+            self.nodes.push(Node::new(Item::Data { depth, key, value }));
+        }
+        indices
+    }
+
+    /// Reads the children of a directory, record or list.
+    fn read_children(&mut self, i: usize) -> anyhow::Result<Vec<usize>> {
+        let depth = self.nodes[i].item.depth() + 1;
+        match &self.nodes[i].item {
+            Item::Path(entry) => {
+                let path = entry.path.clone();
+                self.read_dir(&path, depth)
+            }
+            Item::Data { value, .. } => {
+                let value = value.clone();
+                Ok(self.add_values(&value, depth))
+            }
+        }
+    }
+
+    /// Reads the entries of a directory, sorted by name, adding them to the tree.
+    fn read_dir(&mut self, dir: &Path, depth: usize) -> anyhow::Result<Vec<usize>> {
+        let args = view::ViewArgs {
+            path: dir.to_path_buf(),
+            level: Some(1),
+            all: self.args.all,
+            gitignore: self.args.gitignore,
+            dereference: self.args.dereference,
+            ..Default::default()
+        };
+        let mut entries = view::walk(&args, true)?.entries;
+        entries.sort_by(|a, b| a.name.cmp(&b.name));
+        // Entries are looked up by the directory's place in the repository and
+        // their name, so only the directory is canonicalized.
+        let in_repo = self.git.as_ref().and_then(|status| {
+            let canonical = dunce::canonicalize(dir).ok()?;
+            Some((
+                status,
+                canonical.strip_prefix(&status.root).ok()?.to_path_buf(),
+            ))
+        });
+
+        let mut indices = Vec::with_capacity(entries.len());
+        for mut entry in entries {
+            entry.depth = depth;
+            if let Some((status, relative_dir)) = &in_repo
+                && let Some(name) = entry.path.file_name()
+            {
+                let relative = relative_dir.join(name);
+                entry.git_status = status.status(&relative);
+                entry.submodule = entry.is_dir && status.is_submodule(&relative);
+            }
+            indices.push(self.nodes.len());
+            self.nodes.push(Node::new(Item::Path(entry)));
+        }
+        Ok(indices)
+    }
+
+    /// Counts the entries of the collapsed directories in `shown`.
+    fn count_children(&mut self, shown: &[usize]) {
+        for &i in shown {
+            let node = &self.nodes[i];
+            let Item::Path(entry) = &node.item else {
+                continue;
+            };
+            if !entry.is_dir || node.count.is_some() {
+                continue;
+            }
+            let count = match &node.children {
+                Some(children) => Some(children.len()),
+                None => {
+                    let args = view::ViewArgs {
+                        path: entry.path.clone(),
+                        level: Some(1),
+                        all: self.args.all,
+                        gitignore: self.args.gitignore,
+                        ..Default::default()
+                    };
+                    view::walk(&args, false).ok().map(|walk| walk.entries.len())
+                }
+            };
+            self.nodes[i].count = Some(count);
+        }
+    }
+
+    /// Reads directories that haven't been read yet for a moment, so the
+    /// filter can search the whole tree. Symlinks aren't followed, so this
+    /// always ends.
+    fn read_more(&mut self) {
+        let Some(mut i) = self.reading else {
+            return;
+        };
+        let started = Instant::now();
+        while i < self.nodes.len() && started.elapsed() < FILTER_READ_TIME {
+            let node = &self.nodes[i];
+            let is_symlink = matches!(&node.item, Item::Path(entry) if entry.is_symlink);
+            if node.item.is_dir() && !is_symlink && node.children.is_none() {
+                // A directory that can't be read is shown as empty.
+                let children = self.read_children(i).unwrap_or_default();
+                self.nodes[i].children = Some(children);
+            }
+            i += 1;
+        }
+        self.reading = if i == self.nodes.len() {
+            None
+        } else if self.nodes.len() >= FILTER_MAX_ENTRIES {
+            self.message = Some(format!(
+                "The filter only searches the first {} entries",
+                self.nodes.len()
+            ));
+            None
+        } else {
+            Some(i)
+        };
+    }
+
+    /// The entries that can be seen: those whose ancestors are all expanded,
+    /// or while filtering, those that match and their ancestors.
+    fn visible(&self) -> Vec<usize> {
+        fn add(explorer: &Explorer, indices: &[usize], visible: &mut Vec<usize>) {
+            let filtering = !explorer.filter.is_empty();
+            for &i in indices {
+                let node = &explorer.nodes[i];
+                let at = visible.len();
+                visible.push(i);
+                if let Some(children) = &node.children
+                    && (filtering || node.expanded)
+                {
+                    add(explorer, children, visible);
+                }
+                if filtering
+                    && visible.len() == at + 1
+                    && !fuzzy_match(&explorer.filter, node.item.name())
+                {
+                    visible.truncate(at);
+                }
+            }
+        }
+        let mut visible = Vec::new();
+        add(self, &self.top, &mut visible);
+        visible
+    }
+
+    /// Expands or collapses a directory, reading it the first time.
+    fn toggle(&mut self, i: usize) -> anyhow::Result<()> {
+        if !self.nodes[i].item.is_dir() {
+            return Ok(());
+        }
+        if self.nodes[i].children.is_none() {
+            let children = self.read_children(i)?;
+            self.nodes[i].children = Some(children);
+        }
+        self.nodes[i].expanded = !self.nodes[i].expanded;
+        Ok(())
+    }
+
+    /// Marks an entry, or unmarks it if it is already marked.
+    fn mark(&mut self, i: usize) {
+        match self.marked.iter().position(|&j| j == i) {
+            Some(at) => {
+                self.marked.remove(at);
+            }
+            None => self.marked.push(i),
+        }
+    }
+
+    /// Opens an entry in the editor, giving it the terminal until it exits.
+    fn edit(&mut self, screen: &Screen, out: &mut impl Write, i: usize) -> io::Result<()> {
+        let Some((program, args)) = self.editor.as_ref().and_then(|e| e.split_first()) else {
+            self.message =
+                Some("Set $env.EDITOR or $env.config.buffer_editor to edit files".into());
+            return Ok(());
+        };
+        let Item::Path(entry) = &self.nodes[i].item else {
+            self.message = Some("Only files can be edited".into());
+            return Ok(());
+        };
+        let path = &entry.path;
+        let status = screen.suspend(out, || {
+            Command::new(program)
+                .args(args)
+                .arg(path)
+                .current_dir(&self.args.path)
+                .status()
+        })?;
+        match status {
+            Ok(status) if !status.success() => {
+                self.message = Some(format!("{program} exited with {status}"));
+            }
+            Err(err) => self.message = Some(format!("Can't run {program}: {err}")),
+            Ok(_) => {}
+        }
+        Ok(())
+    }
+
+    /// Copies the absolute path of an entry, or the text of a value, to the
+    /// clipboard. The terminal sets the clipboard when it sees an OSC 52
+    /// sequence, which also works over SSH.
+    fn copy(&mut self, out: &mut impl Write, i: usize) -> io::Result<()> {
+        let text = match &self.nodes[i].item {
+            Item::Path(entry) => entry.path.to_string_lossy().to_string(),
+            Item::Data { value, .. } => value.to_expanded_string("\n", &self.config),
+        };
+        write!(out, "\x1b]52;c;{}\x07", BASE64.encode(&text))?;
+        self.message = Some(format!(
+            "Copied {}",
+            text.lines().next().unwrap_or_default()
+        ));
+        Ok(())
+    }
+
+    /// The path of a node, unless it holds piped data.
+    fn path(&self, i: usize) -> Option<&Path> {
+        match &self.nodes[i].item {
+            Item::Path(entry) => Some(&entry.path),
+            Item::Data { .. } => None,
+        }
+    }
+
+    /// The directory holding a node, or `None` for the root.
+    fn parent(&self, i: usize) -> Option<usize> {
+        self.nodes
+            .iter()
+            .position(|node| node.children.as_ref().is_some_and(|c| c.contains(&i)))
+    }
+
+    /// Moves the cursor to a node, if it is visible.
+    fn select(&mut self, i: usize) {
+        if let Some(position) = self.visible().iter().position(|&j| j == i) {
+            self.cursor = position;
+        }
+    }
+
+    /// Reads a directory again after it was changed, or the root for `None`.
+    /// Entries that are still there keep their nodes, so expanded directories
+    /// stay expanded.
+    fn reload(&mut self, dir: Option<usize>) -> anyhow::Result<()> {
+        let (path, depth) = match dir {
+            Some(d) => (
+                self.path(d).unwrap_or(&self.args.path).to_path_buf(),
+                self.nodes[d].item.depth() + 1,
+            ),
+            None => (self.args.path.clone(), 1),
+        };
+        let fresh = self.read_dir(&path, depth)?;
+        let old = match dir {
+            Some(d) => self.nodes[d].children.take().unwrap_or_default(),
+            None => std::mem::take(&mut self.top),
+        };
+        let children = fresh
+            .into_iter()
+            .map(|i| {
+                let same = old.iter().find(|&&j| self.path(j) == self.path(i));
+                same.copied().unwrap_or(i)
+            })
+            .collect();
+        match dir {
+            Some(d) => {
+                self.nodes[d].children = Some(children);
+                self.nodes[d].count = None;
+            }
+            None => self.top = children,
+        }
+        let marked = std::mem::take(&mut self.marked);
+        self.marked = marked
+            .into_iter()
+            .filter(|&i| {
+                self.path(i)
+                    .is_some_and(|path| path.symlink_metadata().is_ok())
+            })
+            .collect();
+        Ok(())
+    }
+
+    /// Deletes an entry, with everything in it for a directory.
+    fn delete(&mut self, i: usize) -> anyhow::Result<String> {
+        let Item::Path(entry) = &self.nodes[i].item else {
+            anyhow::bail!("only files can be deleted");
+        };
+        if entry.is_dir && !entry.is_symlink {
+            fs::remove_dir_all(&entry.path)?;
+        } else {
+            fs::remove_file(&entry.path)?;
+        }
+        let message = format!("Deleted {}", entry.name);
+        self.reload(self.parent(i))?;
+        Ok(message)
+    }
+
+    fn rename(&mut self, i: usize, name: &str) -> anyhow::Result<String> {
+        check_name(name)?;
+        let Some(path) = self.path(i) else {
+            anyhow::bail!("only files can be renamed");
+        };
+        let renamed = path.with_file_name(name);
+        if renamed.symlink_metadata().is_ok() {
+            anyhow::bail!("'{name}' already exists");
+        }
+        fs::rename(path, &renamed)?;
+        let parent = self.parent(i);
+        self.reload(parent)?;
+        if let Some(j) = self.find(parent, &renamed) {
+            self.select(j);
+        }
+        Ok(format!("Renamed to {name}"))
+    }
+
+    fn new_dir(&mut self, dir: Option<usize>, name: &str) -> anyhow::Result<String> {
+        if self.data {
+            anyhow::bail!("only files can be changed");
+        }
+        check_name(name)?;
+        let path = match dir {
+            Some(d) => self.path(d).unwrap_or(&self.args.path),
+            None => &self.args.path,
+        }
+        .join(name);
+        fs::create_dir(&path)?;
+        self.reload(dir)?;
+        if let Some(d) = dir {
+            self.nodes[d].expanded = true;
+        }
+        if let Some(j) = self.find(dir, &path) {
+            self.select(j);
+        }
+        Ok(format!("Created {name}"))
+    }
+
+    /// The node for `path` among the entries of a directory, or of the root.
+    fn find(&self, dir: Option<usize>, path: &Path) -> Option<usize> {
+        let children = match dir {
+            Some(d) => self.nodes[d].children.as_deref()?,
+            None => &self.top,
+        };
+        children
+            .iter()
+            .copied()
+            .find(|&i| self.path(i) == Some(path))
+    }
+
+    /// Handles a key pressed while a prompt is shown.
+    fn answer(&mut self, mut prompt: Prompt, key: KeyEvent) {
+        let result = match (&mut prompt, key.code) {
+            (Prompt::Jump(number), KeyCode::Char(c)) if c.is_ascii_digit() => {
+                number.push(c);
+                self.prompt = Some(prompt);
+                return;
+            }
+            (Prompt::Jump(number), KeyCode::Enter) => {
+                match jump_target(number, self.visible().len()) {
+                    Some(position) => {
+                        self.cursor = position;
+                        return;
+                    }
+                    None => Err(anyhow::anyhow!("there is no entry {number}")),
+                }
+            }
+            (Prompt::Delete(i), KeyCode::Char('y' | 'Y')) => self.delete(*i),
+            (Prompt::Delete(_), _) | (_, KeyCode::Esc) => return,
+            (Prompt::Rename { name, .. } | Prompt::NewDir { name, .. }, KeyCode::Char(c))
+                if !key.modifiers.contains(KeyModifiers::CONTROL) =>
+            {
+                name.push(c);
+                self.prompt = Some(prompt);
+                return;
+            }
+            (
+                Prompt::Rename { name, .. } | Prompt::NewDir { name, .. } | Prompt::Jump(name),
+                KeyCode::Backspace,
+            ) => {
+                name.pop();
+                self.prompt = Some(prompt);
+                return;
+            }
+            (Prompt::Rename { node, name }, KeyCode::Enter) => self.rename(*node, name),
+            (Prompt::NewDir { dir, name }, KeyCode::Enter) => self.new_dir(*dir, name),
+            _ => {
+                self.prompt = Some(prompt);
+                return;
+            }
+        };
+        self.message = Some(result.unwrap_or_else(|err| format!("Error: {err}")));
+    }
+
+    /// Shows the tree until entries are picked or the explorer is closed,
+    /// returning the picked nodes.
+    fn run(&mut self) -> anyhow::Result<Vec<usize>> {
+        let mut out = io::stdout();
+        let screen = Screen::enter(&mut out)?;
+        loop {
+            let visible = self.visible();
+            let (_, height) = terminal::size()?;
+            // The first line shows the root and the last one the key bindings.
+            let page = (height as usize).saturating_sub(2).max(1);
+            self.cursor = self.cursor.min(visible.len().saturating_sub(1));
+            if self.cursor < self.scroll {
+                self.scroll = self.cursor;
+            } else if self.cursor >= self.scroll + page {
+                self.scroll = self.cursor + 1 - page;
+            }
+            let shown = visible.iter().skip(self.scroll).take(page);
+            self.count_children(&shown.copied().collect::<Vec<_>>());
+            self.draw(&mut out, &visible, page)?;
+
+            if !self.filter.is_empty() && self.reading.is_some() && !event::poll(Duration::ZERO)? {
+                self.read_more();
+                continue;
+            }
+            let Event::Key(key) = event::read()? else {
+                continue;
+            };
+            if key.kind != KeyEventKind::Press {
+                continue;
+            }
+            let selected = visible.get(self.cursor).copied();
+            self.message = None;
+            if let Some(prompt) = self.prompt.take() {
+                self.answer(prompt, key);
+                continue;
+            }
+            if self.typing {
+                match key.code {
+                    KeyCode::Char(c) if !key.modifiers.contains(KeyModifiers::CONTROL) => {
+                        self.filter.push(c);
+                        self.cursor = 0;
+                    }
+                    KeyCode::Backspace => {
+                        self.filter.pop();
+                        self.cursor = 0;
+                    }
+                    KeyCode::Enter => self.typing = false,
+                    KeyCode::Esc => {
+                        self.filter.clear();
+                        self.typing = false;
+                    }
+                    KeyCode::Up => self.cursor = self.cursor.saturating_sub(1),
+                    KeyCode::Down => self.cursor += 1,
+                    _ => {}
+                }
+                continue;
+            }
+            if key.code == KeyCode::Char('c') && key.modifiers == KeyModifiers::CONTROL {
+                return Ok(Vec::new());
+            }
+            if key.code == KeyCode::Esc && !self.filter.is_empty() {
+                self.filter.clear();
+                continue;
+            }
+            let Some(action) = self.keymap.action(&key) else {
+                // Digits that aren't bound to anything start a jump.
+                if let KeyCode::Char(c) = key.code
+                    && c.is_ascii_digit()
+                    && key.modifiers.is_empty()
+                {
+                    self.prompt = Some(Prompt::Jump(c.to_string()));
+                }
+                continue;
+            };
+            match action {
+                Action::Quit => return Ok(Vec::new()),
+                Action::Filter => self.typing = true,
+                Action::Up => self.cursor = self.cursor.saturating_sub(1),
+                Action::Down => self.cursor += 1,
+                Action::PageUp => self.cursor = self.cursor.saturating_sub(page),
+                Action::PageDown => self.cursor += page,
+                Action::Top => self.cursor = 0,
+                Action::Bottom => self.cursor = visible.len(),
+                Action::Expand => {
+                    if let Some(i) = selected {
+                        if self.nodes[i].expanded {
+                            // Step into an expanded directory.
+                            if self.nodes[i]
+                                .children
+                                .as_ref()
+                                .is_some_and(|c| !c.is_empty())
+                            {
+                                self.cursor += 1;
+                            }
+                        } else {
+                            self.toggle(i)?;
+                        }
+                    }
+                }
+                Action::Collapse => {
+                    if let Some(i) = selected {
+                        if self.nodes[i].expanded {
+                            self.toggle(i)?;
+                        } else {
+                            // Step out to the parent directory.
+                            let depth = self.nodes[i].item.depth();
+                            if let Some(parent) = visible[..self.cursor]
+                                .iter()
+                                .rposition(|&j| self.nodes[j].item.depth() < depth)
+                            {
+                                self.cursor = parent;
+                            }
+                        }
+                    }
+                }
+                Action::Preview => self.preview = !self.preview,
+                Action::GoTo => self.prompt = Some(Prompt::Jump(String::new())),
+                Action::Delete | Action::Rename | Action::NewDir if self.data => {
+                    self.message = Some("Only files can be changed".into());
+                }
+                Action::Delete => self.prompt = selected.map(Prompt::Delete),
+                Action::Rename => {
+                    self.prompt = selected.map(|i| Prompt::Rename {
+                        node: i,
+                        name: self.nodes[i].item.name().to_string(),
+                    });
+                }
+                Action::NewDir => {
+                    // Directories are created in the selected directory, or
+                    // next to the selected file.
+                    let dir = match selected {
+                        Some(i) if self.nodes[i].item.is_dir() => Some(i),
+                        Some(i) => self.parent(i),
+                        None => None,
+                    };
+                    self.prompt = Some(Prompt::NewDir {
+                        dir,
+                        name: String::new(),
+                    });
+                }
+                Action::Copy => {
+                    if let Some(i) = selected {
+                        self.copy(&mut out, i)?;
+                    }
+                }
+                Action::Edit => {
+                    if let Some(i) = selected {
+                        self.edit(&screen, &mut out, i)?;
+                    }
+                }
+                Action::Mark => {
+                    if let Some(i) = selected {
+                        self.mark(i);
+                        self.cursor += 1;
+                    }
+                }
+                Action::Pick => {
+                    if !self.marked.is_empty() {
+                        return Ok(self.marked.clone());
+                    }
+                    if let Some(i) = selected {
+                        if self.nodes[i].item.is_dir() {
+                            self.toggle(i)?;
+                        } else {
+                            return Ok(vec![i]);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    fn draw(&self, out: &mut impl Write, visible: &[usize], page: usize) -> io::Result<()> {
+        let styled = |style: Style| self.args.color.style(style);
+        let mut rows: Vec<view::Row> = visible
+            .iter()
+            .map(|&i| {
+                let node = &self.nodes[i];
+                let entry = match &node.item {
+                    Item::Path(entry) => entry,
+                    Item::Data { depth, key, value } => {
+                        let summary = summary(value, &self.config);
+                        let summary = if node.item.is_dir() {
+                            styled(Style::new().dimmed()).paint(summary).to_string()
+                        } else {
+                            summary
+                        };
+                        return view::Row {
+                            depth: *depth,
+                            leading: " ".to_string(),
+                            label: format!("{}: {summary}", styled(Style::new().bold()).paint(key)),
+                        };
+                    }
+                };
+                let mut row = view::render_entry(entry, &self.args, &self.ls_colors, 0);
+                if entry.is_dir && !node.expanded && self.filter.is_empty() {
+                    let placeholder = match node.count.flatten() {
+                        Some(0) => " (empty)".to_string(),
+                        Some(1) => " (1 entry)".to_string(),
+                        Some(count) => format!(" ({count} entries)"),
+                        None => " …".to_string(),
+                    };
+                    row.label
+                        .push_str(&styled(Style::new().dimmed()).paint(placeholder).to_string());
+                }
+                row
+            })
+            .collect();
+        // Entries are numbered while a jump is typed, even without --numbered.
+        if self.args.numbered || matches!(self.prompt, Some(Prompt::Jump(_))) {
+            let width = visible.len().to_string().len();
+            for (i, row) in rows.iter_mut().enumerate() {
+                let number = styled(Style::new().dimmed()).paint(format!("{:>width$} ", i + 1));
+                row.leading = format!("{number}{}", row.leading);
+            }
+        }
+        if let Some(columns) = &self.args.columns {
+            let entries: Vec<&view::Entry> = visible
+                .iter()
+                .filter_map(|&i| match &self.nodes[i].item {
+                    Item::Path(entry) => Some(entry),
+                    Item::Data { .. } => None,
+                })
+                .collect();
+            view::column_cells(&self.args, columns, &entries, &mut rows);
+        }
+        let lines = view::row_lines(&self.args, &rows);
+
+        queue!(
+            out,
+            terminal::Clear(terminal::ClearType::All),
+            cursor::MoveTo(0, 0)
+        )?;
+        queue!(
+            out,
+            style::Print(styled(Style::new().bold()).paint(&self.title)),
+        )?;
+        for (row, line) in lines.iter().enumerate().skip(self.scroll).take(page) {
+            let marked = self.marked.contains(&visible[row]);
+            let marker = match (row == self.cursor, marked) {
+                (true, false) => styled(Style::new().reverse()).paint(">").to_string(),
+                (true, true) => styled(Style::new().reverse()).paint("*").to_string(),
+                (false, true) => styled(Style::new().bold()).paint("*").to_string(),
+                (false, false) => " ".to_string(),
+            };
+            queue!(
+                out,
+                cursor::MoveToNextLine(1),
+                style::Print(format!("{marker}{line}"))
+            )?;
+        }
+        let (width, height) = terminal::size()?;
+        if self.preview
+            && width >= MIN_PREVIEW_WIDTH
+            && let Some(&i) = visible.get(self.cursor)
+        {
+            // The pane is drawn over the end of the tree lines, which can't wrap.
+            let left = width * 11 / 20;
+            let room = (width - left) as usize - 2;
+            let item = &self.nodes[i].item;
+            let border = styled(Style::new().dimmed()).paint("│ ").to_string();
+            let title =
+                styled(Style::new().bold()).paint(clip(&utils::escape_control(item.name()), room));
+            queue!(
+                out,
+                cursor::MoveTo(left, 0),
+                style::Print(format!("{border}{title}")),
+                terminal::Clear(terminal::ClearType::UntilNewLine)
+            )?;
+            let lines = match item {
+                Item::Path(entry) => preview(entry, self.args.all),
+                Item::Data { value, .. } => preview_value(value, &self.config),
+            };
+            for row in 0..page {
+                let line = lines
+                    .get(row)
+                    .map_or(String::new(), |line| clip(line, room));
+                queue!(
+                    out,
+                    cursor::MoveTo(left, row as u16 + 1),
+                    style::Print(format!("{border}{line}")),
+                    terminal::Clear(terminal::ClearType::UntilNewLine)
+                )?;
+            }
+        }
+        let status = if let Some(prompt) = &self.prompt {
+            let question = match prompt {
+                Prompt::Delete(i) => {
+                    let item = &self.nodes[*i].item;
+                    let contents = if item.is_dir() {
+                        " and everything in it"
+                    } else {
+                        ""
+                    };
+                    format!("Delete {}{contents}? (y/n)", item.name())
+                }
+                Prompt::Rename { name, .. } => format!("Rename to: {name}"),
+                Prompt::NewDir { dir, name } => {
+                    let parent = dir.map_or(self.title.as_str(), |d| self.nodes[d].item.name());
+                    format!("New directory in {parent}: {name}")
+                }
+                Prompt::Jump(number) => format!("Jump to entry: {number}"),
+            };
+            styled(Style::new().bold()).paint(question).to_string()
+        } else if let Some(message) = &self.message {
+            styled(Style::new().bold()).paint(message).to_string()
+        } else if self.typing || !self.filter.is_empty() {
+            let hint = if self.typing {
+                "  (enter keep, esc clear)"
+            } else {
+                "  (esc clear)"
+            };
+            let reading = match self.reading {
+                Some(_) if !self.filter.is_empty() => {
+                    format!("  reading… {} entries", self.nodes.len())
+                }
+                _ => String::new(),
+            };
+            format!(
+                "/{}{}",
+                self.filter,
+                styled(Style::new().dimmed()).paint(format!("{hint}{reading}"))
+            )
+        } else {
+            let key = |action| self.keymap.label(action);
+            let help = if self.marked.is_empty() {
+                format!(
+                    "{}{} move  {}{} expand/collapse  {} jump  {} filter  {} preview  {} edit  {} copy  {} mark  {} pick  {} quit",
+                    key(Action::Up),
+                    key(Action::Down),
+                    key(Action::Expand),
+                    key(Action::Collapse),
+                    key(Action::GoTo),
+                    key(Action::Filter),
+                    key(Action::Preview),
+                    key(Action::Edit),
+                    key(Action::Copy),
+                    key(Action::Mark),
+                    key(Action::Pick),
+                    key(Action::Quit),
+                )
+            } else {
+                format!(
+                    "{} marked  {} unmark  {} accept  {} quit",
+                    self.marked.len(),
+                    key(Action::Mark),
+                    key(Action::Pick),
+                    key(Action::Quit),
+                )
+            };
+            styled(Style::new().dimmed()).paint(help).to_string()
+        };
+        queue!(
+            out,
+            cursor::MoveTo(0, height.saturating_sub(1)),
+            style::Print(status)
+        )?;
+        out.flush()
+    }
+}
+
+/// The command that edits files: `$env.config.buffer_editor`, like Nushell's
+/// own editor commands, or else `$env.VISUAL` or `$env.EDITOR`.
+fn editor(
+    engine: &EngineInterface,
+    buffer_editor: &Value,
+) -> Result<Option<Vec<String>>, LabeledError> {
+    let words = match buffer_editor {
+        Value::String { val, .. } => vec![val.clone()],
+        Value::List { vals, .. } => {
+            let mut words = Vec::with_capacity(vals.len());
+            for val in vals {
+                words.push(val.coerce_string()?);
+            }
+            words
+        }
+        _ => {
+            let mut words = Vec::new();
+            for name in ["VISUAL", "EDITOR"] {
+                if let Some(value) = engine.get_env_var(name)? {
+                    words = value
+                        .coerce_string()?
+                        .split_whitespace()
+                        .map(String::from)
+                        .collect();
+                    break;
+                }
+            }
+            words
+        }
+    };
+    Ok((!words.is_empty() && !words[0].is_empty()).then_some(words))
+}
+
+/// The lines shown in the preview pane: the head of a text file, the contents
+/// of a directory without its hidden entries unless `--all` is given, or a
+/// summary of anything else.
+fn preview(entry: &view::Entry, all: bool) -> Vec<String> {
+    if entry.is_dir {
+        return match fs::read_dir(&entry.path) {
+            Ok(dir) => {
+                let mut names: Vec<String> = dir
+                    .filter_map(Result::ok)
+                    .filter(|child| all || !utils::is_hidden(child))
+                    .map(|child| {
+                        let mut name =
+                            utils::escape_control(&utils::escape_invalid(&child.file_name()));
+                        if child.file_type().is_ok_and(|ft| ft.is_dir()) {
+                            name.push('/');
+                        }
+                        name
+                    })
+                    .collect();
+                names.sort();
+                names
+            }
+            Err(err) => vec![err.to_string()],
+        };
+    }
+
+    let details = |label: &str| {
+        let mut summary = vec![label.to_string(), String::new()];
+        for column in [
+            view::Column::Size,
+            view::Column::Modified,
+            view::Column::Permissions,
+            view::Column::Owner,
+        ] {
+            let cell = column.cell(entry);
+            if !cell.is_empty() {
+                summary.push(format!("{}: {}", column.record_key(), cell));
+            }
+        }
+        summary
+    };
+    // Opening a named pipe, a socket or a terminal could block, so only
+    // regular files are read.
+    match fs::metadata(&entry.path) {
+        Ok(metadata) if !metadata.is_file() => return details(special_kind(metadata.file_type())),
+        Ok(_) => {}
+        Err(err) => return vec![err.to_string()],
+    }
+    let mut head = Vec::new();
+    if let Err(err) =
+        File::open(&entry.path).and_then(|file| file.take(PREVIEW_BYTES).read_to_end(&mut head))
+    {
+        return vec![err.to_string()];
+    }
+    text_lines(&head).unwrap_or_else(|| details("binary file"))
+}
+
+/// What kind of file something that is neither a regular file nor a directory is.
+fn special_kind(file_type: fs::FileType) -> &'static str {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::FileTypeExt;
+        if file_type.is_fifo() {
+            return "named pipe";
+        }
+        if file_type.is_socket() {
+            return "socket";
+        }
+        if file_type.is_char_device() {
+            return "character device";
+        }
+        if file_type.is_block_device() {
+            return "block device";
+        }
+    }
+    let _ = file_type;
+    "special file"
+}
+
+/// Splits the start of a file into lines, unless it looks binary.
+fn text_lines(head: &[u8]) -> Option<Vec<String>> {
+    if head.contains(&0) {
+        return None;
+    }
+    Some(
+        String::from_utf8_lossy(head)
+            .lines()
+            .map(|line| {
+                line.replace('\t', "    ")
+                    .chars()
+                    .filter(|c| !c.is_control())
+                    .collect()
+            })
+            .collect(),
+    )
+}
+
+/// A one-line description of a value: its text, or how many items it holds.
+fn summary(value: &Value, config: &Config) -> String {
+    let count = |count: usize, one: &str, many: &str| {
+        format!("{count} {}", if count == 1 { one } else { many })
+    };
+    match value {
+        Value::Record { val, .. } => format!("{{{}}}", count(val.len(), "field", "fields")),
+        Value::List { vals, .. } => format!("[{}]", count(vals.len(), "item", "items")),
+        value => value
+            .to_abbreviated_string(config)
+            .lines()
+            .next()
+            .unwrap_or_default()
+            .to_string(),
+    }
+}
+
+/// The preview of a value: the summaries of its fields or items, or its
+/// whole text.
+fn preview_value(value: &Value, config: &Config) -> Vec<String> {
+    match value {
+        Value::Record { val, .. } => val
+            .iter()
+            .map(|(key, value)| format!("{key}: {}", summary(value, config)))
+            .collect(),
+        Value::List { vals, .. } => vals
+            .iter()
+            .enumerate()
+            .map(|(i, value)| format!("{i}: {}", summary(value, config)))
+            .collect(),
+        value => value
+            .to_expanded_string("\n", config)
+            .lines()
+            .map(String::from)
+            .collect(),
+    }
+}
+
+/// The position among `visible` entries of the entry numbered `number`,
+/// counting from 1.
+fn jump_target(number: &str, visible: usize) -> Option<usize> {
+    number
+        .parse::<usize>()
+        .ok()
+        .filter(|n| (1..=visible).contains(n))
+        .map(|n| n - 1)
+}
+
+/// Checks a name typed for a new or renamed entry.
+fn check_name(name: &str) -> anyhow::Result<()> {
+    if name.is_empty() || name == "." || name == ".." || name.contains(std::path::is_separator) {
+        anyhow::bail!("'{name}' is not a valid name");
+    }
+    Ok(())
+}
+
+/// Whether the characters of `query` appear in `name` in order, like fzf.
+/// The match ignores case unless the query has an uppercase letter.
+fn fuzzy_match(query: &str, name: &str) -> bool {
+    let ignore_case = !query.chars().any(char::is_uppercase);
+    let mut name = name.chars().map(|c| {
+        if ignore_case {
+            c.to_ascii_lowercase()
+        } else {
+            c
+        }
+    });
+    query.chars().all(|q| name.any(|c| c == q))
+}
+
+/// Cuts plain text to fit in `width` columns.
+fn clip(text: &str, width: usize) -> String {
+    text.chars().take(width).collect()
+}
+
+/// Switches the terminal to a raw alternate screen, restoring it when dropped.
+struct Screen;
+
+impl Screen {
+    fn enter(out: &mut impl Write) -> io::Result<Self> {
+        Self::setup(out)?;
+        Ok(Screen)
+    }
+
+    /// Gives the terminal back while `f` runs, for programs like editors.
+    fn suspend<T>(&self, out: &mut impl Write, f: impl FnOnce() -> T) -> io::Result<T> {
+        Self::restore(out)?;
+        let result = f();
+        Self::setup(out)?;
+        Ok(result)
+    }
+
+    fn setup(out: &mut impl Write) -> io::Result<()> {
+        terminal::enable_raw_mode()?;
+        execute!(
+            out,
+            terminal::EnterAlternateScreen,
+            terminal::DisableLineWrap,
+            cursor::Hide
+        )
+    }
+
+    fn restore(out: &mut impl Write) -> io::Result<()> {
+        execute!(
+            out,
+            cursor::Show,
+            terminal::EnableLineWrap,
+            terminal::LeaveAlternateScreen
+        )?;
+        terminal::disable_raw_mode()
+    }
+}
+
+impl Drop for Screen {
+    fn drop(&mut self) {
+        let _ = Self::restore(&mut io::stdout());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_text_lines() {
+        assert_eq!(
+            text_lines(b"fn main() {\n\tprintln!();\r\n}"),
+            Some(vec![
+                "fn main() {".to_string(),
+                "    println!();".to_string(),
+                "}".to_string()
+            ])
+        );
+        assert_eq!(text_lines(b"\x7fELF\0\0"), None);
+    }
+
+    #[test]
+    fn test_summary() {
+        let config = Config::default();
+        let record = Value::test_record(nu_protocol::record! {
+            "tags" => Value::test_list(vec![Value::test_string("a")]),
+            "name" => Value::test_string("x\ny"),
+        });
+
+        assert_eq!(summary(&record, &config), "{2 fields}");
+        assert_eq!(
+            preview_value(&record, &config),
+            vec!["tags: [1 item]".to_string(), "name: x".to_string()]
+        );
+    }
+
+    #[test]
+    fn test_check_name() {
+        assert!(check_name("notes").is_ok());
+        assert!(check_name(".config").is_ok());
+        assert!(check_name("").is_err());
+        assert!(check_name("..").is_err());
+        assert!(check_name("a/b").is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_special_files_are_not_opened() {
+        let entry = view::Entry {
+            path: PathBuf::from("/dev/null"),
+            name: "null".to_string(),
+            depth: 1,
+            ..Default::default()
+        };
+        assert_eq!(preview(&entry, false)[0], "character device");
+    }
+
+    #[test]
+    fn test_jump_target() {
+        assert_eq!(jump_target("1", 3), Some(0));
+        assert_eq!(jump_target("03", 3), Some(2));
+        assert_eq!(jump_target("4", 3), None);
+        assert_eq!(jump_target("0", 3), None);
+        assert_eq!(jump_target("", 3), None);
+    }
+
+    #[test]
+    fn test_fuzzy_match() {
+        assert!(fuzzy_match("mnrs", "main.rs"));
+        assert!(fuzzy_match("readme", "README.md"));
+        assert!(!fuzzy_match("Readme", "README.md"));
+        assert!(!fuzzy_match("srm", "main.rs"));
+        assert!(fuzzy_match("", "anything"));
+    }
+
+    fn explorer_in(dir: &Path) -> Explorer {
+        let args = view::ViewArgs {
+            path: dir.to_path_buf(),
+            ..Default::default()
+        };
+        Explorer::new(
+            args,
+            Arc::new(LsColors::default()),
+            None,
+            Keymap::default(),
+            Arc::new(Config::default()),
+        )
+        .unwrap()
+    }
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!(
+            "nu_plugin_tree_explore_{name}_{}",
+            std::process::id()
+        ));
+        _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_file_operations() {
+        let dir = temp_dir("ops");
+        fs::write(dir.join("a.txt"), "a").unwrap();
+        fs::create_dir(dir.join("old")).unwrap();
+        fs::write(dir.join("old/b.txt"), "b").unwrap();
+        let mut explorer = explorer_in(&dir);
+
+        let old = explorer.find(None, &dir.join("old")).unwrap();
+        explorer.rename(old, "new").unwrap();
+        assert!(dir.join("new/b.txt").is_file());
+        assert!(!dir.join("old").exists());
+        let new = explorer.find(None, &dir.join("new")).unwrap();
+        assert!(explorer.rename(new, "a.txt").is_err());
+
+        let file = explorer.find(None, &dir.join("a.txt")).unwrap();
+        explorer.delete(file).unwrap();
+        assert!(!dir.join("a.txt").exists());
+        let new = explorer.find(None, &dir.join("new")).unwrap();
+        explorer.delete(new).unwrap();
+        assert!(!dir.join("new").exists());
+
+        explorer.new_dir(None, "made").unwrap();
+        assert!(dir.join("made").is_dir());
+        assert!(explorer.find(None, &dir.join("made")).is_some());
+        assert!(explorer.new_dir(None, "made").is_err());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_data_leaves_files_alone() {
+        let dir = temp_dir("data");
+        let args = view::ViewArgs {
+            path: dir.clone(),
+            ..Default::default()
+        };
+        let mut explorer = Explorer::from_value(
+            args,
+            Arc::new(LsColors::default()),
+            Keymap::default(),
+            Arc::new(Config::default()),
+            Value::test_record(nu_protocol::record! { "a" => Value::test_int(1) }),
+        );
+
+        assert!(explorer.new_dir(None, "made").is_err());
+        assert!(explorer.delete(0).is_err());
+        assert!(explorer.rename(0, "b").is_err());
+        assert!(!dir.join("made").exists());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,360 @@
+//! Writes path mode walks in the export formats of GNU `tree`, so tooling that
+//! already consumes `tree -J` or `tree -X` can consume this plugin's output
+//! directly, and `tree -H` style HTML pages can be published as directory indexes.
+
+use crate::view::{self, Entry, Node, Row, Totals, ViewArgs, nest};
+use std::fmt::Write;
+use std::fs;
+
+/// Renders the walk as the JSON array printed by `tree -J`: the root directory
+/// followed by a report object with the directory and file counts.
+pub fn to_json(args: &ViewArgs, entries: &[Entry]) -> String {
+    let mut out = String::from("[\n");
+    let _ = write!(
+        out,
+        "  {{\"type\":\"directory\",\"name\":{}",
+        json_string(&args.path.to_string_lossy())
+    );
+    write_json_contents(&mut out, args, &nest(entries), 1);
+    out.push_str("}\n,\n");
+
+    let directories = entries.iter().filter(|e| e.is_dir).count();
+    let _ = writeln!(
+        out,
+        "  {{\"type\":\"report\",\"directories\":{},\"files\":{}}}",
+        directories,
+        entries.len() - directories
+    );
+    out.push(']');
+    out
+}
+
+fn write_json_contents(out: &mut String, args: &ViewArgs, nodes: &[Node], level: usize) {
+    if nodes.is_empty() {
+        return;
+    }
+    out.push_str(",\"contents\":[\n");
+    for (i, node) in nodes.iter().enumerate() {
+        let entry = node.entry;
+        let _ = write!(
+            out,
+            "{}{{\"type\":\"{}\",\"name\":{}",
+            "  ".repeat(level + 1),
+            entry_type(entry),
+            json_string(&entry.name)
+        );
+        if entry.is_symlink
+            && let Ok(target) = fs::read_link(&entry.path)
+        {
+            let _ = write!(
+                out,
+                ",\"target\":{}",
+                json_string(&target.to_string_lossy())
+            );
+        }
+        if args.permissions
+            && let Some(permissions) = &entry.permissions
+        {
+            let _ = write!(out, ",\"prot\":{}", json_string(permissions));
+        }
+        if args.size
+            && let Some(size) = entry.size
+        {
+            let _ = write!(out, ",\"size\":{size}");
+        }
+        write_json_contents(out, args, &node.children, level + 1);
+        out.push('}');
+        if i + 1 < nodes.len() {
+            out.push(',');
+        }
+        out.push('\n');
+    }
+    let _ = write!(out, "{}]", "  ".repeat(level));
+}
+
+/// Renders the walk as the XML document printed by `tree -X`.
+pub fn to_xml(args: &ViewArgs, entries: &[Entry]) -> String {
+    let mut out = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<tree>\n");
+    let _ = write!(
+        out,
+        "  <directory name=\"{}\">",
+        xml_escape(&args.path.to_string_lossy())
+    );
+    write_xml_contents(&mut out, args, &nest(entries), 1);
+    out.push_str("</directory>\n");
+
+    let directories = entries.iter().filter(|e| e.is_dir).count();
+    let _ = write!(
+        out,
+        "  <report>\n    <directories>{}</directories>\n    <files>{}</files>\n  </report>\n</tree>",
+        directories,
+        entries.len() - directories
+    );
+    out
+}
+
+fn write_xml_contents(out: &mut String, args: &ViewArgs, nodes: &[Node], level: usize) {
+    if nodes.is_empty() {
+        return;
+    }
+    out.push('\n');
+    for node in nodes {
+        let entry = node.entry;
+        let element = match entry_type(entry) {
+            "link" => "link",
+            "directory" => "directory",
+            _ => "file",
+        };
+        let _ = write!(
+            out,
+            "{}<{} name=\"{}\"",
+            "  ".repeat(level + 1),
+            element,
+            xml_escape(&entry.name)
+        );
+        if entry.is_symlink
+            && let Ok(target) = fs::read_link(&entry.path)
+        {
+            let _ = write!(out, " target=\"{}\"", xml_escape(&target.to_string_lossy()));
+        }
+        if args.permissions
+            && let Some(permissions) = &entry.permissions
+        {
+            let _ = write!(out, " prot=\"{}\"", xml_escape(permissions));
+        }
+        if args.size
+            && let Some(size) = entry.size
+        {
+            let _ = write!(out, " size=\"{size}\"");
+        }
+        out.push('>');
+        write_xml_contents(out, args, &node.children, level + 1);
+        let _ = writeln!(out, "</{element}>");
+    }
+    out.push_str(&"  ".repeat(level));
+}
+
+/// Renders the walk as an HTML page where every entry links to `base_url`
+/// followed by its path below the root, like `tree -H`.
+pub fn to_html(args: &ViewArgs, entries: &[Entry], base_url: &str) -> String {
+    let base_url = base_url.trim_end_matches('/');
+    let rows: Vec<Row> = entries
+        .iter()
+        .map(|entry| {
+            let relative = entry.path.strip_prefix(&args.path).unwrap_or(&entry.path);
+            let mut href = format!("{}/{}", base_url, url_encode(&relative.to_string_lossy()));
+            if entry.is_dir {
+                href.push('/');
+            }
+            Row {
+                depth: entry.depth,
+                leading: String::new(),
+                label: format!(
+                    "<a href=\"{}\">{}</a>",
+                    xml_escape(&href),
+                    xml_escape(&entry.name)
+                ),
+            }
+        })
+        .collect();
+
+    let mut body = Vec::new();
+    view::write_rows(args, &rows, Totals::of(entries), &mut body);
+
+    let title = xml_escape(&args.path.to_string_lossy());
+    format!(
+        concat!(
+            "<!DOCTYPE html>\n",
+            "<html>\n",
+            "<head>\n",
+            "  <meta charset=\"utf-8\">\n",
+            "  <title>{title}</title>\n",
+            "  <style>a {{ text-decoration: none; }} a:hover {{ text-decoration: underline; }}</style>\n",
+            "</head>\n",
+            "<body>\n",
+            "<h1>{title}</h1>\n",
+            "<pre>\n",
+            "<a href=\"{root}/\">{title}</a>\n",
+            "{body}",
+            "</pre>\n",
+            "</body>\n",
+            "</html>"
+        ),
+        title = title,
+        root = xml_escape(base_url),
+        body = String::from_utf8_lossy(&body),
+    )
+}
+
+/// Percent-encodes a `/`-separated path for use in a URL.
+fn url_encode(path: &str) -> String {
+    let mut encoded = String::with_capacity(path.len());
+    for segment in path.split(['/', '\\']) {
+        if !encoded.is_empty() {
+            encoded.push('/');
+        }
+        for byte in segment.bytes() {
+            if byte.is_ascii_alphanumeric() || b"-._~".contains(&byte) {
+                encoded.push(byte as char);
+            } else {
+                let _ = write!(encoded, "%{byte:02X}");
+            }
+        }
+    }
+    encoded
+}
+
+/// The `type` used by GNU `tree` for an entry.
+fn entry_type(entry: &Entry) -> &'static str {
+    if entry.is_symlink {
+        "link"
+    } else if entry.is_dir {
+        "directory"
+    } else {
+        "file"
+    }
+}
+
+/// Quotes a string as JSON, the way `to json` does.
+fn json_string(s: &str) -> String {
+    serde_json::to_string(s).unwrap_or_else(|_| "\"\"".to_string())
+}
+
+/// Escapes text for an XML attribute or element. Whitespace other than spaces
+/// is written as character references so attributes keep it, and characters
+/// XML 1.0 can't hold at all, like most control characters, become U+FFFD.
+fn xml_escape(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            '\t' | '\n' | '\r' => {
+                let _ = write!(escaped, "&#{};", c as u32);
+            }
+            '\u{0}'..='\u{1f}' | '\u{fffe}' | '\u{ffff}' => escaped.push('\u{fffd}'),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn entry(name: &str, depth: usize, is_dir: bool) -> Entry {
+        Entry {
+            path: PathBuf::from(name),
+            name: name.to_string(),
+            depth,
+            is_dir,
+            size: (!is_dir).then_some(3),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_to_json_matches_tree_layout() {
+        let args = ViewArgs {
+            path: PathBuf::from("root"),
+            size: true,
+            ..Default::default()
+        };
+        let entries = vec![
+            entry("src", 1, true),
+            entry("main.rs", 2, false),
+            entry("README \"x\".md", 1, false),
+        ];
+
+        assert_eq!(
+            to_json(&args, &entries),
+            concat!(
+                "[\n",
+                "  {\"type\":\"directory\",\"name\":\"root\",\"contents\":[\n",
+                "    {\"type\":\"directory\",\"name\":\"src\",\"contents\":[\n",
+                "      {\"type\":\"file\",\"name\":\"main.rs\",\"size\":3}\n",
+                "    ]},\n",
+                "    {\"type\":\"file\",\"name\":\"README \\\"x\\\".md\",\"size\":3}\n",
+                "  ]}\n",
+                ",\n",
+                "  {\"type\":\"report\",\"directories\":1,\"files\":2}\n",
+                "]"
+            )
+        );
+    }
+
+    #[test]
+    fn test_to_xml_matches_tree_layout() {
+        let args = ViewArgs {
+            path: PathBuf::from("root"),
+            ..Default::default()
+        };
+        let entries = vec![
+            entry("src", 1, true),
+            entry("main.rs", 2, false),
+            entry("a&b.md", 1, false),
+        ];
+
+        assert_eq!(
+            to_xml(&args, &entries),
+            concat!(
+                "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n",
+                "<tree>\n",
+                "  <directory name=\"root\">\n",
+                "    <directory name=\"src\">\n",
+                "      <file name=\"main.rs\"></file>\n",
+                "    </directory>\n",
+                "    <file name=\"a&amp;b.md\"></file>\n",
+                "  </directory>\n",
+                "  <report>\n",
+                "    <directories>1</directories>\n",
+                "    <files>2</files>\n",
+                "  </report>\n",
+                "</tree>"
+            )
+        );
+    }
+
+    #[test]
+    fn test_names_are_escaped() {
+        assert_eq!(json_string("a\"b\\c\u{1}"), r#""a\"b\\c\u0001""#);
+        let name = "line\u{2028}break";
+        assert_eq!(
+            serde_json::from_str::<String>(&json_string(name)).unwrap(),
+            name
+        );
+        assert_eq!(xml_escape("a&b\t<c>\u{1}"), "a&amp;b&#9;&lt;c&gt;\u{fffd}");
+    }
+
+    #[test]
+    fn test_to_html_links_entries() {
+        let args = ViewArgs {
+            path: PathBuf::from("root"),
+            ..Default::default()
+        };
+        let entries = vec![
+            Entry {
+                path: PathBuf::from("root/my docs"),
+                ..entry("my docs", 1, true)
+            },
+            Entry {
+                path: PathBuf::from("root/my docs/a&b.md"),
+                ..entry("a&b.md", 2, false)
+            },
+        ];
+        let html = to_html(&args, &entries, "https://example.com/files/");
+
+        assert!(html.contains("<a href=\"https://example.com/files/my%20docs/\">my docs</a>"));
+        assert!(
+            html.contains(
+                "<a href=\"https://example.com/files/my%20docs/a%26b.md\">a&amp;b.md</a>"
+            )
+        );
+        assert!(html.contains("1 directories, 1 files"));
+    }
+}
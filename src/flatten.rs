@@ -0,0 +1,138 @@
+//! Implements `tree flatten`, the inverse of drawing a data tree: it turns a
+//! nested value into one row per leaf, so deeply nested data can be filtered,
+//! grepped or compared row by row.
+
+use crate::TreePlugin;
+use nu_plugin::{EngineInterface, EvaluatedCall, PluginCommand};
+use nu_protocol::ast::{CellPath, PathMember};
+use nu_protocol::casing::Casing;
+use nu_protocol::{
+    Category, Example, LabeledError, PipelineData, Signature, Span, Type, Value, record,
+};
+
+pub struct TreeFlatten;
+
+impl PluginCommand for TreeFlatten {
+    type Plugin = TreePlugin;
+
+    fn name(&self) -> &str {
+        "tree flatten"
+    }
+
+    fn signature(&self) -> Signature {
+        Signature::build(self.name())
+            .input_output_types(vec![(Type::Any, Type::table())])
+            .category(Category::Experimental)
+    }
+
+    fn description(&self) -> &str {
+        "List every leaf of a nested value with the cell path that reaches it."
+    }
+
+    fn examples(&self) -> Vec<Example<'_>> {
+        vec![
+            Example {
+                example: "{a: {b: 1, c: [x y]}} | tree flatten",
+                description: "Flatten a nested record",
+                result: Some(Value::test_list(vec![
+                    leaf(&["a", "b"], Value::test_int(1)),
+                    leaf(&["a", "c", "0"], Value::test_string("x")),
+                    leaf(&["a", "c", "1"], Value::test_string("y")),
+                ])),
+            },
+            Example {
+                example: "open config.json | tree flatten | where value == localhost",
+                description: "Find the settings set to localhost, wherever they are nested",
+                result: None,
+            },
+        ]
+    }
+
+    fn run(
+        &self,
+        _plugin: &TreePlugin,
+        _engine: &EngineInterface,
+        call: &EvaluatedCall,
+        input: PipelineData,
+    ) -> Result<PipelineData, LabeledError> {
+        let value = input.into_value(call.head)?;
+        let mut rows = Vec::new();
+        flatten(value, &mut Vec::new(), &mut rows, call.head);
+        Ok(PipelineData::Value(Value::list(rows, call.head), None))
+    }
+}
+
+/// Adds a row for every leaf below `value`. Empty records and lists are kept
+/// as leaves, so no part of the value disappears.
+fn flatten(value: Value, path: &mut Vec<PathMember>, rows: &mut Vec<Value>, span: Span) {
+    match value {
+        Value::Record { val, .. } if !val.is_empty() => {
+            for (key, value) in val.into_owned() {
+                path.push(PathMember::string(key, false, Casing::Sensitive, span));
+                flatten(value, path, rows, span);
+                path.pop();
+            }
+        }
+        Value::List { vals, .. } if !vals.is_empty() => {
+            for (i, value) in vals.into_iter().enumerate() {
+                path.push(PathMember::int(i, false, span));
+                flatten(value, path, rows, span);
+                path.pop();
+            }
+        }
+        value => rows.push(Value::record(
+            record! {
+                "cell_path" => Value::cell_path(CellPath { members: path.clone() }, span),
+                "value" => value,
+            },
+            span,
+        )),
+    }
+}
+
+/// The row `tree flatten` returns for a leaf, for the examples.
+fn leaf(path: &[&str], value: Value) -> Value {
+    let members = path
+        .iter()
+        .map(|member| match member.parse() {
+            Ok(i) => PathMember::test_int(i, false),
+            Err(_) => PathMember::test_string(member.to_string(), false, Casing::Sensitive),
+        })
+        .collect();
+    Value::test_record(record! {
+        "cell_path" => Value::test_cell_path(CellPath { members }),
+        "value" => value,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_flatten_keeps_empty_containers_and_scalars() {
+        let value = Value::test_record(record! {
+            "tags" => Value::test_list(vec![]),
+            "name" => Value::test_string("x"),
+        });
+        let mut rows = Vec::new();
+        flatten(value, &mut Vec::new(), &mut rows, Span::test_data());
+
+        assert_eq!(
+            rows,
+            vec![
+                leaf(&["tags"], Value::test_list(vec![])),
+                leaf(&["name"], Value::test_string("x")),
+            ]
+        );
+
+        let mut rows = Vec::new();
+        flatten(
+            Value::test_int(3),
+            &mut Vec::new(),
+            &mut rows,
+            Span::test_data(),
+        );
+        assert_eq!(rows, vec![leaf(&[], Value::test_int(3))]);
+    }
+}
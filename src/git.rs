@@ -3,10 +3,15 @@
 //!
 //! This module uses the `git2` crate to discover repositories, read file statuses,
 //! and provide a simplified representation of those statuses for display.
+//! With the `gitoxide` feature, file statuses are read with `gix` instead, which
+//! is much faster on large repositories.
 
 use git2::Repository;
-use std::collections::HashMap;
+use nu_protocol::Signals;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
 use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
 /// A simplified representation of a file's Git status.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
@@ -18,6 +23,8 @@ pub enum FileStatus {
     Typechange,
     Untracked,
     Conflicted,
+    /// Matched by a `.gitignore`, only reported when asked for.
+    Ignored,
 }
 
 impl FileStatus {
@@ -31,6 +38,36 @@ impl FileStatus {
             Self::Typechange => 'T',
             Self::Untracked => '?',
             Self::Conflicted => 'C',
+            Self::Ignored => '!',
+        }
+    }
+
+    /// How much a status stands out when the statuses below a directory are
+    /// summed up in one.
+    fn weight(&self) -> u8 {
+        match self {
+            Self::Conflicted => 7,
+            Self::Modified => 6,
+            Self::Deleted => 5,
+            Self::Renamed => 4,
+            Self::Typechange => 3,
+            Self::New => 2,
+            Self::Untracked => 1,
+            Self::Ignored => 0,
+        }
+    }
+
+    /// Returns the name of the status, as used in structured output.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Modified => "modified",
+            Self::New => "added",
+            Self::Deleted => "deleted",
+            Self::Renamed => "renamed",
+            Self::Typechange => "typechange",
+            Self::Untracked => "untracked",
+            Self::Conflicted => "conflicted",
+            Self::Ignored => "ignored",
         }
     }
 }
@@ -38,19 +75,83 @@ impl FileStatus {
 /// A cache mapping file paths to their Git status.
 pub type StatusCache = HashMap<PathBuf, FileStatus>;
 
+/// How many lines a change adds and removes, as in `git diff --stat`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DiffStat {
+    pub added: usize,
+    pub removed: usize,
+}
+
+impl fmt::Display for DiffStat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "+{} -{}", self.added, self.removed)
+    }
+}
+
+/// What `load_status` reads besides the status of each changed file.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct StatusOptions {
+    /// Report files matched by `.gitignore`.
+    pub ignored: bool,
+    /// Read the files inside submodules from their own repositories.
+    pub submodules: bool,
+    /// Count the lines added and removed in each file.
+    pub diff_stats: bool,
+}
+
 /// Contains the status cache and the root path of the repository.
 #[derive(Clone)]
 pub struct GitRepoStatus {
     pub cache: StatusCache,
     pub root: PathBuf,
+    /// The roots of the submodules, relative to the repository root, including
+    /// nested ones when their statuses were read.
+    pub submodules: HashSet<PathBuf>,
+    /// The lines changed in each file since the last commit, summed up for
+    /// directories. Only read when asked for.
+    pub diff_stats: HashMap<PathBuf, DiffStat>,
+}
+
+impl GitRepoStatus {
+    /// The status of a path relative to the repository root. Git reports an
+    /// ignored directory without its contents, which are ignored too.
+    pub fn status(&self, relative: &Path) -> Option<FileStatus> {
+        self.cache.get(relative).copied().or_else(|| {
+            relative
+                .ancestors()
+                .skip(1)
+                .any(|dir| self.cache.get(dir) == Some(&FileStatus::Ignored))
+                .then_some(FileStatus::Ignored)
+        })
+    }
+
+    /// The most notable status in the whole repository, ignored files aside.
+    pub fn summary(&self) -> Option<FileStatus> {
+        self.cache
+            .values()
+            .filter(|status| **status != FileStatus::Ignored)
+            .max_by_key(|status| status.weight())
+            .copied()
+    }
+
+    /// Whether a path relative to the repository root is a submodule root.
+    pub fn is_submodule(&self, relative: &Path) -> bool {
+        self.submodules.contains(relative)
+    }
 }
 
 /// Discovers a Git repository from a starting path, scans for file statuses,
 /// and returns them in a `GitRepoStatus` object.
 ///
-/// The cache will contain paths relative to the repository root.
+/// The cache will contain paths relative to the repository root. Ignored
+/// files are only included when asked for. Git only reports whether a
+/// submodule changed as a whole; with `submodules` set, the statuses of the
+/// files inside are read from the submodule's own repository.
 /// If no Git repository is found, it returns `Ok(None)`.
-pub fn load_status(start_path: &Path) -> anyhow::Result<Option<GitRepoStatus>> {
+pub fn load_status(
+    start_path: &Path,
+    options: StatusOptions,
+) -> anyhow::Result<Option<GitRepoStatus>> {
     let Ok(repo) = Repository::discover(start_path) else {
         return Ok(None);
     };
@@ -59,37 +160,423 @@ pub fn load_status(start_path: &Path) -> anyhow::Result<Option<GitRepoStatus>> {
         return Ok(None);
     };
 
-    let mut cache = StatusCache::new();
+    let mut status = GitRepoStatus {
+        cache: StatusCache::new(),
+        // Return the CANONICALIZED workdir path as the root.
+        root: dunce::canonicalize(workdir)?,
+        submodules: HashSet::new(),
+        diff_stats: HashMap::new(),
+    };
+    read_statuses(&repo, Path::new(""), options, &mut status)?;
+    propagate_to_dirs(&mut status.cache);
+    sum_to_dirs(&mut status.diff_stats);
+    Ok(Some(status))
+}
+
+/// Adds the statuses and submodules of `repo`, whose root is at `prefix` in
+/// the outermost repository.
+fn read_statuses(
+    repo: &Repository,
+    prefix: &Path,
+    options: StatusOptions,
+    status: &mut GitRepoStatus,
+) -> anyhow::Result<()> {
+    #[cfg(feature = "gitoxide")]
+    if let Some(workdir) = repo.workdir() {
+        gitoxide::read_file_statuses(workdir, prefix, options.ignored, &mut status.cache)?;
+    }
+    #[cfg(not(feature = "gitoxide"))]
+    read_file_statuses(repo, prefix, options.ignored, &mut status.cache)?;
+
+    for submodule in repo.submodules()? {
+        let path = prefix.join(submodule.path());
+        // A submodule that isn't checked out has no repository to read.
+        if options.submodules
+            && let Ok(sub_repo) = submodule.open()
+        {
+            read_statuses(&sub_repo, &path, options, status)?;
+        }
+        status.submodules.insert(path);
+    }
+
+    if options.diff_stats {
+        read_diff_stats(repo, prefix, &mut status.diff_stats)?;
+    }
+    Ok(())
+}
+
+/// Adds the status of every changed file in `repo`, read with `git2`.
+#[cfg(not(feature = "gitoxide"))]
+fn read_file_statuses(
+    repo: &Repository,
+    prefix: &Path,
+    ignored: bool,
+    cache: &mut StatusCache,
+) -> anyhow::Result<()> {
     let mut opts = git2::StatusOptions::new();
     opts.include_untracked(true)
-        .include_ignored(false)
+        .include_ignored(ignored)
         .recurse_untracked_dirs(true);
 
     let statuses = repo.statuses(Some(&mut opts))?;
 
     for entry in statuses.iter() {
-        let Some(status) = git_to_file_status(entry.status()) else {
+        let Some(file_status) = git_to_file_status(entry.status()) else {
             continue;
         };
 
         if let Some(path_str) = entry.path() {
             // Use the relative path directly as the key.
-            cache.insert(PathBuf::from(path_str), status);
+            cache.insert(prefix.join(path_str), file_status);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(feature = "gitoxide")]
+mod gitoxide {
+    use super::{FileStatus, StatusCache};
+    use gix::diff::index::ChangeRef;
+    use gix::dir::entry::Status as DirStatus;
+    use gix::dir::walk::EmissionMode;
+    use gix::status::index_worktree::{self, iter::Summary};
+    use gix::status::{Item, UntrackedFiles};
+    use std::collections::HashMap;
+    use std::path::Path;
+
+    /// Adds the status of every changed file in the repository at `workdir`,
+    /// read with `gix`. The statuses match the ones `git2` reports, except that
+    /// staged renames are detected, as `git status` does.
+    pub fn read_file_statuses(
+        workdir: &Path,
+        prefix: &Path,
+        ignored: bool,
+        cache: &mut StatusCache,
+    ) -> anyhow::Result<()> {
+        let repo = gix::open(workdir)?;
+        let items = repo
+            .status(gix::progress::Discard)?
+            .untracked_files(UntrackedFiles::Files)
+            .dirwalk_options(|opts| {
+                // Like git2, ignored directories are reported without their contents.
+                opts.emit_ignored(ignored.then_some(EmissionMode::CollapseDirectory))
+            })
+            .into_iter(None)?;
+
+        // Staged changes take precedence over changes in the worktree, unless
+        // the worktree has a conflict, as with git2.
+        let mut staged = HashMap::new();
+        let mut worktree = HashMap::new();
+        for item in items {
+            let item = item?;
+            let path = gix::path::from_bstr(item.location()).into_owned();
+            match item {
+                Item::TreeIndex(change) => {
+                    staged.insert(path, staged_status(&change));
+                }
+                Item::IndexWorktree(item) => {
+                    if let Some(status) = worktree_status(&item) {
+                        worktree.insert(path, status);
+                    }
+                }
+            }
+        }
+
+        for (path, status) in worktree {
+            if status == FileStatus::Conflicted || !staged.contains_key(&path) {
+                cache.insert(prefix.join(path), status);
+            }
+        }
+        for (path, status) in staged {
+            if cache.get(&prefix.join(&path)) != Some(&FileStatus::Conflicted) {
+                cache.insert(prefix.join(path), status);
+            }
+        }
+        Ok(())
+    }
+
+    fn staged_status(change: &ChangeRef<'_, '_>) -> FileStatus {
+        match change {
+            ChangeRef::Addition { .. } => FileStatus::New,
+            ChangeRef::Deletion { .. } => FileStatus::Deleted,
+            ChangeRef::Modification { .. } => FileStatus::Modified,
+            ChangeRef::Rewrite { .. } => FileStatus::Renamed,
         }
     }
 
-    // Return the CANONICALIZED workdir path as the root.
-    Ok(Some(GitRepoStatus {
-        cache,
-        root: workdir.canonicalize()?,
+    fn worktree_status(item: &index_worktree::Item) -> Option<FileStatus> {
+        if let index_worktree::Item::DirectoryContents { entry, .. } = item
+            && matches!(entry.status, DirStatus::Ignored(_))
+        {
+            return Some(FileStatus::Ignored);
+        }
+        Some(match item.summary()? {
+            Summary::Removed => FileStatus::Deleted,
+            Summary::Added => FileStatus::Untracked,
+            Summary::Modified => FileStatus::Modified,
+            Summary::TypeChange => FileStatus::Typechange,
+            Summary::Renamed | Summary::Copied => FileStatus::Renamed,
+            Summary::IntentToAdd => FileStatus::New,
+            Summary::Conflict => FileStatus::Conflicted,
+        })
+    }
+}
+
+/// Counts the lines each file changed between the last commit and the
+/// worktree, new files included.
+fn read_diff_stats(
+    repo: &Repository,
+    prefix: &Path,
+    stats: &mut HashMap<PathBuf, DiffStat>,
+) -> anyhow::Result<()> {
+    // A repository without commits diffs against nothing.
+    let head = repo.head().ok().and_then(|head| head.peel_to_tree().ok());
+    let mut opts = git2::DiffOptions::new();
+    opts.include_untracked(true)
+        .recurse_untracked_dirs(true)
+        .show_untracked_content(true)
+        .ignore_submodules(true);
+    let diff = repo.diff_tree_to_workdir_with_index(head.as_ref(), Some(&mut opts))?;
+
+    for i in 0..diff.deltas().len() {
+        let Some(patch) = git2::Patch::from_diff(&diff, i)? else {
+            continue;
+        };
+        let (_, added, removed) = patch.line_stats()?;
+        // Binary files have no lines to count.
+        if added + removed == 0 {
+            continue;
+        }
+        let delta = patch.delta();
+        if let Some(path) = delta.new_file().path().or(delta.old_file().path()) {
+            stats.insert(prefix.join(path), DiffStat { added, removed });
+        }
+    }
+    Ok(())
+}
+
+/// Gives every directory the sum of the lines changed below it.
+fn sum_to_dirs(stats: &mut HashMap<PathBuf, DiffStat>) {
+    let files: Vec<(PathBuf, DiffStat)> = stats
+        .iter()
+        .map(|(path, stat)| (path.clone(), *stat))
+        .collect();
+    roll_up_to_ancestors(stats, files, |stats, dir, stat| {
+        let sum = stats.entry(dir.to_path_buf()).or_default();
+        sum.added += stat.added;
+        sum.removed += stat.removed;
+        true
+    });
+}
+
+/// Rolls values up to the directories above their paths. For each
+/// `(path, value)`, `merge` folds the value into `map` at every ancestor of
+/// `path`, nearest first, until it returns false or the top of a relative
+/// path is reached.
+pub fn roll_up_to_ancestors<M, P: AsRef<Path>, T>(
+    map: &mut M,
+    values: impl IntoIterator<Item = (P, T)>,
+    mut merge: impl FnMut(&mut M, &Path, &T) -> bool,
+) {
+    for (path, value) in values {
+        for dir in path.as_ref().ancestors().skip(1) {
+            if dir.as_os_str().is_empty() || !merge(map, dir, &value) {
+                break;
+            }
+        }
+    }
+}
+
+/// Where the repository stands, as shown by shell prompts.
+#[derive(Debug, PartialEq)]
+pub struct HeadInfo {
+    /// The checked out branch, or the short commit id of a detached head.
+    pub head: String,
+    pub detached: bool,
+    /// How many commits the branch is ahead of and behind its upstream, if it
+    /// has one.
+    pub ahead_behind: Option<(usize, usize)>,
+    /// Whether the tree drawn has uncommitted changes.
+    pub dirty: bool,
+}
+
+impl fmt::Display for HeadInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.detached {
+            write!(f, "HEAD detached at {}", self.head)?;
+        } else {
+            write!(f, "on {}", self.head)?;
+        }
+        match self.ahead_behind {
+            Some((0, 0)) => write!(f, ", up to date")?,
+            Some((ahead, behind)) => {
+                if ahead > 0 {
+                    write!(f, " ↑{ahead}")?;
+                }
+                if behind > 0 {
+                    write!(f, " ↓{behind}")?;
+                }
+            }
+            None => {}
+        }
+        write!(f, ", {}", if self.dirty { "dirty" } else { "clean" })
+    }
+}
+
+/// Reads the branch and upstream distance of the repository containing
+/// `start_path`, if there is one. Whether it is `dirty` comes from the
+/// statuses already read for the tree, rather than from another scan.
+pub fn head_info(start_path: &Path, dirty: bool) -> anyhow::Result<Option<HeadInfo>> {
+    let Ok(repo) = Repository::discover(start_path) else {
+        return Ok(None);
+    };
+    if repo.is_bare() {
+        return Ok(None);
+    }
+
+    let (head, detached, ahead_behind) = match repo.head() {
+        Ok(head) if head.is_branch() => {
+            let name = head.shorthand().unwrap_or("HEAD").to_string();
+            let branch = git2::Branch::wrap(head);
+            let ahead_behind = match (branch.get().target(), branch.upstream()) {
+                (Some(local), Ok(upstream)) => match upstream.get().target() {
+                    Some(upstream) => Some(repo.graph_ahead_behind(local, upstream)?),
+                    None => None,
+                },
+                _ => None,
+            };
+            (name, false, ahead_behind)
+        }
+        Ok(head) => {
+            let id = head.target().map(|id| id.to_string()).unwrap_or_default();
+            (id.chars().take(7).collect(), true, None)
+        }
+        // A new repository has no commits yet, but HEAD still names a branch.
+        Err(_) => {
+            let head = repo.find_reference("HEAD")?;
+            let target = head.symbolic_target().unwrap_or("HEAD");
+            let name = target.strip_prefix("refs/heads/").unwrap_or(target);
+            (name.to_string(), false, None)
+        }
+    };
+
+    Ok(Some(HeadInfo {
+        head,
+        detached,
+        ahead_behind,
+        dirty,
+    }))
+}
+
+/// When each tracked file was last committed, relative to the repository root.
+/// Directories take the time of the newest commit below them.
+pub struct CommitTimes {
+    pub times: HashMap<PathBuf, SystemTime>,
+    pub root: PathBuf,
+}
+
+/// Walks the history from `HEAD`, newest first, until the last commit of every
+/// file in `HEAD` is found. Like `git log -1 -- <file>`, a merge counts as
+/// changing what it brought in from its other parents. An interruption stops
+/// the walk, keeping the times found so far.
+/// If no Git repository is found, it returns `Ok(None)`.
+pub fn commit_times(
+    start_path: &Path,
+    signals: Option<&Signals>,
+) -> anyhow::Result<Option<CommitTimes>> {
+    let Ok(repo) = Repository::discover(start_path) else {
+        return Ok(None);
+    };
+    let Some(workdir) = repo.workdir() else {
+        return Ok(None);
+    };
+
+    let mut times = HashMap::new();
+    // A repository without commits has nothing to look up. Files that are
+    // only staged have no commit, so only the files in `HEAD` are looked for.
+    if let Ok(head) = repo.head().and_then(|head| head.peel_to_tree()) {
+        let mut remaining = HashSet::new();
+        head.walk(git2::TreeWalkMode::PreOrder, |dir, entry| {
+            if entry.kind() == Some(git2::ObjectType::Blob) {
+                remaining.insert(
+                    Path::new(dir).join(String::from_utf8_lossy(entry.name_bytes()).as_ref()),
+                );
+            }
+            git2::TreeWalkResult::Ok
+        })?;
+        let mut revwalk = repo.revwalk()?;
+        revwalk.push_head()?;
+        revwalk.set_sorting(git2::Sort::TIME)?;
+        for id in revwalk {
+            if remaining.is_empty() || signals.is_some_and(Signals::interrupted) {
+                break;
+            }
+            let commit = repo.find_commit(id?)?;
+            let tree = commit.tree()?;
+            let parent_tree = match commit.parents().next() {
+                Some(parent) => Some(parent.tree()?),
+                None => None,
+            };
+            let diff = repo.diff_tree_to_tree(parent_tree.as_ref(), Some(&tree), None)?;
+            let time = UNIX_EPOCH + Duration::from_secs(commit.time().seconds().max(0) as u64);
+            for delta in diff.deltas() {
+                if let Some(path) = delta.new_file().path()
+                    && remaining.remove(path)
+                {
+                    times.insert(path.to_path_buf(), time);
+                }
+            }
+        }
+    }
+
+    newest_to_dirs(&mut times);
+    Ok(Some(CommitTimes {
+        times,
+        root: dunce::canonicalize(workdir)?,
     }))
 }
 
+/// Gives every directory the time of the newest commit below it.
+fn newest_to_dirs(times: &mut HashMap<PathBuf, SystemTime>) {
+    let files: Vec<(PathBuf, SystemTime)> = times
+        .iter()
+        .map(|(path, time)| (path.clone(), *time))
+        .collect();
+    roll_up_to_ancestors(times, files, |times, dir, time| {
+        let newest = times.entry(dir.to_path_buf()).or_insert(*time);
+        *newest = (*newest).max(*time);
+        true
+    });
+}
+
+/// Gives every directory above a changed file the most notable status below
+/// it, so a change deep in the tree shows on its top-level directory, like in
+/// IDE file trees.
+fn propagate_to_dirs(cache: &mut StatusCache) {
+    // Ignored files don't make their directory look changed.
+    let files: Vec<(PathBuf, FileStatus)> = cache
+        .iter()
+        .filter(|(_, status)| **status != FileStatus::Ignored)
+        .map(|(path, status)| (path.clone(), *status))
+        .collect();
+    roll_up_to_ancestors(cache, files, |cache, dir, status| {
+        let summary = cache.entry(dir.to_path_buf()).or_insert(*status);
+        if status.weight() > summary.weight() {
+            *summary = *status;
+        }
+        true
+    });
+}
+
 /// Converts a `git2::Status` bitflag into our simplified `FileStatus` enum.
+#[cfg(not(feature = "gitoxide"))]
 fn git_to_file_status(s: git2::Status) -> Option<FileStatus> {
     if s.is_conflicted() {
         return Some(FileStatus::Conflicted);
     }
+    if s.is_ignored() {
+        return Some(FileStatus::Ignored);
+    }
     if s.is_index_new() {
         return Some(FileStatus::New);
     }
@@ -122,3 +609,110 @@ fn git_to_file_status(s: git2::Status) -> Option<FileStatus> {
     }
     None
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_directories_take_the_most_notable_status() {
+        let mut cache = StatusCache::from([
+            (PathBuf::from("src/view.rs"), FileStatus::Modified),
+            (PathBuf::from("src/git/new.rs"), FileStatus::Untracked),
+            (PathBuf::from("docs/guide.md"), FileStatus::Untracked),
+            (PathBuf::from("target/debug"), FileStatus::Ignored),
+        ]);
+        propagate_to_dirs(&mut cache);
+
+        assert_eq!(cache.get(Path::new("src")), Some(&FileStatus::Modified));
+        assert_eq!(
+            cache.get(Path::new("src/git")),
+            Some(&FileStatus::Untracked)
+        );
+        assert_eq!(cache.get(Path::new("docs")), Some(&FileStatus::Untracked));
+        assert_eq!(cache.get(Path::new("target")), None);
+        assert_eq!(cache.len(), 7);
+    }
+
+    #[test]
+    fn test_directories_sum_the_lines_changed_below_them() {
+        let mut stats = HashMap::from([
+            (
+                PathBuf::from("src/view.rs"),
+                DiffStat {
+                    added: 12,
+                    removed: 3,
+                },
+            ),
+            (
+                PathBuf::from("src/git.rs"),
+                DiffStat {
+                    added: 1,
+                    removed: 0,
+                },
+            ),
+        ]);
+        sum_to_dirs(&mut stats);
+
+        let src = stats[Path::new("src")];
+        assert_eq!(src.to_string(), "+13 -3");
+        assert_eq!(stats.len(), 3);
+    }
+
+    #[test]
+    fn test_directories_take_the_newest_commit_time() {
+        let day = |days: u64| UNIX_EPOCH + Duration::from_secs(days * 86_400);
+        let mut times = HashMap::from([
+            (PathBuf::from("src/view.rs"), day(3)),
+            (PathBuf::from("src/git/mod.rs"), day(5)),
+            (PathBuf::from("README.md"), day(1)),
+        ]);
+        newest_to_dirs(&mut times);
+
+        assert_eq!(times[Path::new("src")], day(5));
+        assert_eq!(times[Path::new("src/git")], day(5));
+        assert_eq!(times.len(), 5);
+    }
+
+    #[test]
+    fn test_head_info_reads_like_a_prompt() {
+        let info = |head: &str, detached, ahead_behind, dirty| {
+            HeadInfo {
+                head: head.to_string(),
+                detached,
+                ahead_behind,
+                dirty,
+            }
+            .to_string()
+        };
+
+        assert_eq!(
+            info("main", false, Some((2, 1)), true),
+            "on main ↑2 ↓1, dirty"
+        );
+        assert_eq!(
+            info("main", false, Some((0, 0)), false),
+            "on main, up to date, clean"
+        );
+        assert_eq!(
+            info("1a2b3c4", true, None, false),
+            "HEAD detached at 1a2b3c4, clean"
+        );
+    }
+
+    #[test]
+    fn test_paths_below_ignored_directories_are_ignored() {
+        let status = GitRepoStatus {
+            cache: StatusCache::from([(PathBuf::from("target"), FileStatus::Ignored)]),
+            root: PathBuf::from("/repo"),
+            submodules: HashSet::new(),
+            diff_stats: HashMap::new(),
+        };
+
+        assert_eq!(
+            status.status(Path::new("target/debug/app")),
+            Some(FileStatus::Ignored)
+        );
+        assert_eq!(status.status(Path::new("src/main.rs")), None);
+    }
+}
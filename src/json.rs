@@ -0,0 +1,239 @@
+//! Parses JSON byte streams as they are read, as in `open --raw big.json | tree`,
+//! without holding the whole text, or the parts of the value that a tree with
+//! `--depth` or `--max-nodes` won't draw. With `--types`, the whole value is
+//! kept, since the type of a node describes everything below it.
+
+use crate::data::DataArgs;
+use nu_protocol::{Record, Span, Value};
+use serde::de::{DeserializeSeed, Deserializer, IgnoredAny, MapAccess, SeqAccess, Visitor};
+use std::cell::Cell;
+use std::fmt;
+use std::io::Read;
+
+/// Reads the JSON value of `reader`. Values that won't be drawn are read past
+/// and kept as `null`, so lists and records still hold as many items as the
+/// tree counts.
+pub fn read(reader: impl Read, args: &DataArgs, span: Span) -> Result<Value, serde_json::Error> {
+    // Node counts only match the tree's when every field and item up to the
+    // limit is drawn, in the order it was read.
+    let counted = args.collapse.is_none() && !args.sort_keys && !args.compact && !args.dedup;
+    let pruned = !args.types;
+    let limits = Limits {
+        depth: args.depth.filter(|_| pruned),
+        max_items: args.max_items.filter(|_| pruned),
+        max_nodes: args.max_nodes.filter(|_| pruned && counted),
+        nodes: Cell::new(0),
+        span,
+    };
+    let mut deserializer = serde_json::Deserializer::from_reader(reader);
+    let value = Seed {
+        limits: &limits,
+        depth: 1,
+    }
+    .deserialize(&mut deserializer)?;
+    deserializer.end()?;
+    Ok(value)
+}
+
+struct Limits {
+    depth: Option<usize>,
+    max_items: Option<usize>,
+    max_nodes: Option<usize>,
+    /// How many fields and list items were kept so far.
+    nodes: Cell<usize>,
+    span: Span,
+}
+
+impl Limits {
+    /// Counts a field or list item about to be kept, returning false once
+    /// `max_nodes` of them were.
+    fn take_node(&self) -> bool {
+        if self.max_nodes.is_some_and(|max| self.nodes.get() >= max) {
+            return false;
+        }
+        self.nodes.set(self.nodes.get() + 1);
+        true
+    }
+}
+
+/// Reads a value whose nodes are drawn `depth` levels below the root, counting
+/// depth the way the tree does: fields are a level deeper than their record,
+/// and list items are drawn among the nodes of their list.
+#[derive(Clone, Copy)]
+struct Seed<'a> {
+    limits: &'a Limits,
+    depth: usize,
+}
+
+impl Seed<'_> {
+    /// Past `--depth`, a value is drawn as `…`, so only how many items it holds matters.
+    fn counted_only(&self) -> bool {
+        self.limits.depth.is_some_and(|max| self.depth > max)
+    }
+}
+
+impl<'de> DeserializeSeed<'de> for Seed<'_> {
+    type Value = Value;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<Value, D::Error> {
+        deserializer.deserialize_any(self)
+    }
+}
+
+impl<'de> Visitor<'de> for Seed<'_> {
+    type Value = Value;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "a JSON value")
+    }
+
+    fn visit_bool<E>(self, val: bool) -> Result<Value, E> {
+        Ok(Value::bool(val, self.limits.span))
+    }
+
+    fn visit_i64<E>(self, val: i64) -> Result<Value, E> {
+        Ok(Value::int(val, self.limits.span))
+    }
+
+    fn visit_u64<E>(self, val: u64) -> Result<Value, E> {
+        Ok(match i64::try_from(val) {
+            Ok(val) => Value::int(val, self.limits.span),
+            Err(_) => Value::float(val as f64, self.limits.span),
+        })
+    }
+
+    fn visit_f64<E>(self, val: f64) -> Result<Value, E> {
+        Ok(Value::float(val, self.limits.span))
+    }
+
+    fn visit_str<E>(self, val: &str) -> Result<Value, E> {
+        Ok(Value::string(val, self.limits.span))
+    }
+
+    fn visit_string<E>(self, val: String) -> Result<Value, E> {
+        Ok(Value::string(val, self.limits.span))
+    }
+
+    fn visit_unit<E>(self) -> Result<Value, E> {
+        Ok(Value::nothing(self.limits.span))
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Value, A::Error> {
+        let span = self.limits.span;
+        let mut vals = Vec::new();
+        loop {
+            let kept = !self.counted_only()
+                && self.limits.max_items.is_none_or(|max| vals.len() < max)
+                && self.limits.take_node();
+            if kept {
+                match seq.next_element_seed(self)? {
+                    Some(value) => vals.push(value),
+                    None => break,
+                }
+            } else {
+                match seq.next_element::<IgnoredAny>()? {
+                    Some(_) => vals.push(Value::nothing(span)),
+                    None => break,
+                }
+            }
+        }
+        Ok(Value::list(vals, span))
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Value, A::Error> {
+        let span = self.limits.span;
+        let field = Seed {
+            limits: self.limits,
+            depth: self.depth + 1,
+        };
+        let mut record = Record::new();
+        while let Some(key) = map.next_key::<String>()? {
+            let value = if !self.counted_only() && self.limits.take_node() {
+                map.next_value_seed(field)?
+            } else {
+                map.next_value::<IgnoredAny>()?;
+                Value::nothing(span)
+            };
+            record.insert(key, value);
+        }
+        Ok(Value::record(record, span))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nu_protocol::record;
+
+    fn parse(json: &str, args: &DataArgs) -> Value {
+        read(json.as_bytes(), args, Span::test_data()).unwrap()
+    }
+
+    #[test]
+    fn test_read_whole_value() {
+        let value = parse(
+            r#"{"a": [1, 2.5, "x"], "b": {"c": null, "d": true}}"#,
+            &DataArgs::default(),
+        );
+        assert_eq!(
+            value,
+            Value::test_record(record! {
+                "a" => Value::test_list(vec![
+                    Value::test_int(1),
+                    Value::test_float(2.5),
+                    Value::test_string("x"),
+                ]),
+                "b" => Value::test_record(record! {
+                    "c" => Value::test_nothing(),
+                    "d" => Value::test_bool(true),
+                }),
+            })
+        );
+    }
+
+    #[test]
+    fn test_read_keeps_what_is_drawn() {
+        let json = r#"{"a": {"b": {"c": 1}}, "d": [1, 2, 3]}"#;
+        let args = DataArgs {
+            depth: Some(2),
+            max_items: Some(2),
+            ..Default::default()
+        };
+        let value = parse(json, &args);
+        assert_eq!(
+            value,
+            Value::test_record(record! {
+                "a" => Value::test_record(record! {
+                    "b" => Value::test_record(record! { "c" => Value::test_nothing() }),
+                }),
+                "d" => Value::test_list(vec![
+                    Value::test_int(1),
+                    Value::test_int(2),
+                    Value::test_nothing(),
+                ]),
+            })
+        );
+
+        let args = DataArgs {
+            max_nodes: Some(2),
+            ..Default::default()
+        };
+        let value = parse(json, &args);
+        assert_eq!(
+            value,
+            Value::test_record(record! {
+                "a" => Value::test_record(record! {
+                    "b" => Value::test_record(record! { "c" => Value::test_nothing() }),
+                }),
+                "d" => Value::test_nothing(),
+            })
+        );
+        assert!(read(&b"{\"a\": 1} 2"[..], &args, Span::test_data()).is_err());
+
+        let args = DataArgs {
+            types: true,
+            ..args
+        };
+        assert_eq!(parse(json, &args), parse(json, &DataArgs::default()));
+    }
+}
@@ -0,0 +1,312 @@
+//! The key bindings of `tree explore`, which can be remapped in the plugin
+//! configuration:
+//!
+//! ```nushell
+//! $env.config.plugins.tree.explore_keys = {
+//!     down: [j ctrl-n]
+//!     up: [k ctrl-p]
+//!     quit: q
+//! }
+//! ```
+//!
+//! Keys are single characters or one of `up`, `down`, `left`, `right`, `enter`,
+//! `esc`, `space`, `tab`, `backspace`, `pageup`, `pagedown`, `home` and `end`,
+//! optionally prefixed with `ctrl-` or `alt-`. A remapped action replaces all
+//! of its default keys, and the keys it takes are removed from other actions.
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use std::fmt;
+use std::str::FromStr;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Action {
+    Up,
+    Down,
+    PageUp,
+    PageDown,
+    Top,
+    Bottom,
+    Expand,
+    Collapse,
+    Mark,
+    Pick,
+    Quit,
+    Filter,
+    Preview,
+    Edit,
+    Copy,
+    Delete,
+    Rename,
+    NewDir,
+    GoTo,
+}
+
+impl Action {
+    const ALL: [(Action, &'static str); 19] = [
+        (Action::Up, "up"),
+        (Action::Down, "down"),
+        (Action::PageUp, "page_up"),
+        (Action::PageDown, "page_down"),
+        (Action::Top, "top"),
+        (Action::Bottom, "bottom"),
+        (Action::Expand, "expand"),
+        (Action::Collapse, "collapse"),
+        (Action::Mark, "mark"),
+        (Action::Pick, "pick"),
+        (Action::Quit, "quit"),
+        (Action::Filter, "filter"),
+        (Action::Preview, "preview"),
+        (Action::Edit, "edit"),
+        (Action::Copy, "copy"),
+        (Action::Delete, "delete"),
+        (Action::Rename, "rename"),
+        (Action::NewDir, "new_dir"),
+        (Action::GoTo, "go_to"),
+    ];
+}
+
+impl FromStr for Action {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Action::ALL
+            .iter()
+            .find(|(_, name)| *name == s)
+            .map(|(action, _)| *action)
+            .ok_or_else(|| {
+                let names: Vec<&str> = Action::ALL.iter().map(|(_, name)| *name).collect();
+                format!("unknown action '{s}', expected one of {}", names.join(", "))
+            })
+    }
+}
+
+/// A key with its modifiers, like `ctrl-n`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Key {
+    code: KeyCode,
+    modifiers: KeyModifiers,
+}
+
+impl Key {
+    fn matches(&self, event: &KeyEvent) -> bool {
+        // Shift is already part of the character, as in `G`.
+        self.code == event.code && self.modifiers == event.modifiers - KeyModifiers::SHIFT
+    }
+}
+
+const NAMED_KEYS: [(&str, KeyCode); 13] = [
+    ("up", KeyCode::Up),
+    ("down", KeyCode::Down),
+    ("left", KeyCode::Left),
+    ("right", KeyCode::Right),
+    ("enter", KeyCode::Enter),
+    ("esc", KeyCode::Esc),
+    ("space", KeyCode::Char(' ')),
+    ("tab", KeyCode::Tab),
+    ("backspace", KeyCode::Backspace),
+    ("pageup", KeyCode::PageUp),
+    ("pagedown", KeyCode::PageDown),
+    ("home", KeyCode::Home),
+    ("end", KeyCode::End),
+];
+
+impl FromStr for Key {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut modifiers = KeyModifiers::NONE;
+        let mut rest = s;
+        loop {
+            if let Some(key) = rest.strip_prefix("ctrl-").filter(|key| !key.is_empty()) {
+                modifiers |= KeyModifiers::CONTROL;
+                rest = key;
+            } else if let Some(key) = rest.strip_prefix("alt-").filter(|key| !key.is_empty()) {
+                modifiers |= KeyModifiers::ALT;
+                rest = key;
+            } else {
+                break;
+            }
+        }
+
+        let mut chars = rest.chars();
+        let code = match (chars.next(), chars.next()) {
+            (Some(c), None) => KeyCode::Char(c),
+            _ => NAMED_KEYS
+                .iter()
+                .find(|(name, _)| name.eq_ignore_ascii_case(rest))
+                .map(|(_, code)| *code)
+                .ok_or_else(|| format!("unknown key '{s}'"))?,
+        };
+        Ok(Key { code, modifiers })
+    }
+}
+
+impl fmt::Display for Key {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.modifiers.contains(KeyModifiers::CONTROL) {
+            write!(f, "ctrl-")?;
+        }
+        if self.modifiers.contains(KeyModifiers::ALT) {
+            write!(f, "alt-")?;
+        }
+        match self.code {
+            KeyCode::Up => write!(f, "↑"),
+            KeyCode::Down => write!(f, "↓"),
+            KeyCode::Left => write!(f, "←"),
+            KeyCode::Right => write!(f, "→"),
+            KeyCode::Char(c) if c != ' ' => write!(f, "{c}"),
+            code => {
+                let name = NAMED_KEYS.iter().find(|(_, named)| *named == code);
+                write!(f, "{}", name.map_or("?", |(name, _)| name))
+            }
+        }
+    }
+}
+
+/// The keys bound to each action.
+#[derive(Clone, Debug)]
+pub struct Keymap {
+    bindings: Vec<(Action, Vec<Key>)>,
+}
+
+impl Default for Keymap {
+    fn default() -> Self {
+        let defaults: [(Action, &[&str]); 19] = [
+            (Action::Up, &["up", "k"]),
+            (Action::Down, &["down", "j"]),
+            (Action::PageUp, &["pageup"]),
+            (Action::PageDown, &["pagedown"]),
+            (Action::Top, &["home", "g"]),
+            (Action::Bottom, &["end", "G"]),
+            (Action::Expand, &["right", "l"]),
+            (Action::Collapse, &["left", "h"]),
+            (Action::Mark, &["space"]),
+            (Action::Pick, &["enter"]),
+            (Action::Quit, &["q", "esc"]),
+            (Action::Filter, &["/"]),
+            (Action::Preview, &["p"]),
+            (Action::Edit, &["e"]),
+            (Action::Copy, &["y"]),
+            (Action::Delete, &["d"]),
+            (Action::Rename, &["r"]),
+            (Action::NewDir, &["n"]),
+            (Action::GoTo, &[":"]),
+        ];
+        let bindings = defaults
+            .iter()
+            .map(|(action, keys)| {
+                let keys = keys.iter().map(|key| key.parse().unwrap()).collect();
+                (*action, keys)
+            })
+            .collect();
+        Keymap { bindings }
+    }
+}
+
+impl Keymap {
+    /// Binds `keys` to `action`, replacing its keys and unbinding them from
+    /// other actions.
+    pub fn bind(&mut self, action: Action, keys: Vec<Key>) {
+        for (bound, bound_keys) in &mut self.bindings {
+            if *bound == action {
+                *bound_keys = keys.clone();
+            } else {
+                bound_keys.retain(|key| !keys.contains(key));
+            }
+        }
+    }
+
+    /// The action bound to a key press.
+    pub fn action(&self, event: &KeyEvent) -> Option<Action> {
+        self.bindings
+            .iter()
+            .find(|(_, keys)| keys.iter().any(|key| key.matches(event)))
+            .map(|(action, _)| *action)
+    }
+
+    /// The first key bound to an action, for the help line.
+    pub fn label(&self, action: Action) -> String {
+        self.bindings
+            .iter()
+            .find(|(bound, _)| *bound == action)
+            .and_then(|(_, keys)| keys.first())
+            .map_or(String::new(), |key| key.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn press(code: KeyCode, modifiers: KeyModifiers) -> KeyEvent {
+        KeyEvent::new(code, modifiers)
+    }
+
+    #[test]
+    fn test_default_keys() {
+        let keymap = Keymap::default();
+
+        assert_eq!(
+            keymap.action(&press(KeyCode::Char('j'), KeyModifiers::NONE)),
+            Some(Action::Down)
+        );
+        assert_eq!(
+            keymap.action(&press(KeyCode::Char('G'), KeyModifiers::SHIFT)),
+            Some(Action::Bottom)
+        );
+        assert_eq!(
+            keymap.action(&press(KeyCode::Char('j'), KeyModifiers::CONTROL)),
+            None
+        );
+        assert_eq!(
+            keymap.action(&press(KeyCode::Char(':'), KeyModifiers::SHIFT)),
+            Some(Action::GoTo)
+        );
+        assert_eq!(
+            keymap.action(&press(KeyCode::Char('4'), KeyModifiers::NONE)),
+            None
+        );
+        assert_eq!(keymap.label(Action::Up), "↑");
+        assert_eq!(keymap.label(Action::Mark), "space");
+    }
+
+    #[test]
+    fn test_rebound_keys_move_between_actions() {
+        let mut keymap = Keymap::default();
+        keymap.bind(
+            Action::Collapse,
+            vec!["j".parse().unwrap(), "ctrl-b".parse().unwrap()],
+        );
+
+        assert_eq!(
+            keymap.action(&press(KeyCode::Char('j'), KeyModifiers::NONE)),
+            Some(Action::Collapse)
+        );
+        assert_eq!(
+            keymap.action(&press(KeyCode::Char('b'), KeyModifiers::CONTROL)),
+            Some(Action::Collapse)
+        );
+        assert_eq!(
+            keymap.action(&press(KeyCode::Left, KeyModifiers::NONE)),
+            None
+        );
+        assert_eq!(
+            keymap.action(&press(KeyCode::Down, KeyModifiers::NONE)),
+            Some(Action::Down)
+        );
+        assert_eq!(keymap.label(Action::Collapse), "j");
+    }
+
+    #[test]
+    fn test_invalid_keys_and_actions() {
+        assert_eq!(
+            "ctrl--".parse::<Key>(),
+            Ok(Key {
+                code: KeyCode::Char('-'),
+                modifiers: KeyModifiers::CONTROL
+            })
+        );
+        assert!("f13".parse::<Key>().is_err());
+        assert!("jump".parse::<Action>().is_err());
+    }
+}
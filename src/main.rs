@@ -1,20 +1,63 @@
+use lscolors::LsColors;
 use nu_plugin::{EngineInterface, EvaluatedCall};
 use nu_plugin::{MsgPackSerializer, Plugin, PluginCommand, serve_plugin};
-use nu_protocol::{Category, Config, Example, LabeledError, PipelineData, Signature, Value};
-use ptree::TreeBuilder;
+use nu_protocol::{
+    ByteStream, ByteStreamType, Category, DataSource, Example, IntoSpanned, LabeledError,
+    ListStream, PipelineData, ShellError, Signature, Span, SyntaxShape, Value,
+};
 use ptree::item::StringItem;
-use ptree::output::print_tree_with;
-use ptree::print_config::PrintConfig;
+use ptree::output::write_tree_with;
+use ptree::print_config::{PrintConfig, StyleWhen};
 use ptree::style::{Color, Style};
+use std::io::{BufRead, BufReader, Read, Write};
+use std::path::PathBuf;
 use std::sync::Arc;
+use std::sync::mpsc::SyncSender;
 
 use crate::view::ColorChoice;
 
+mod cache;
+mod cargo;
+mod config;
+mod data;
+mod diff;
+mod du;
+mod edges;
+mod explore;
+mod export;
+mod flatten;
 mod git;
+mod json;
+mod keymap;
+mod markup;
+mod paths;
+mod patterns;
+mod progress;
+mod provider;
+mod ps;
+mod records;
+mod stats;
+mod theme;
 mod utils;
 mod view;
+mod watch;
 
-pub struct TreePlugin;
+#[derive(Default)]
+pub struct TreePlugin {
+    /// The last path mode walk, for `--cached`.
+    walk_cache: cache::WalkCache,
+    ls_colors: cache::LsColorsCache,
+}
+
+impl TreePlugin {
+    /// The colors of the caller's `LS_COLORS`, or the default colors.
+    fn ls_colors(&self, engine: &EngineInterface) -> Result<Arc<LsColors>, LabeledError> {
+        let env = engine
+            .get_env_var("LS_COLORS")?
+            .and_then(|v| v.coerce_into_string().ok());
+        Ok(self.ls_colors.get(env))
+    }
+}
 
 impl Plugin for TreePlugin {
     fn version(&self) -> String {
@@ -22,7 +65,18 @@ impl Plugin for TreePlugin {
     }
 
     fn commands(&self) -> Vec<Box<dyn PluginCommand<Plugin = Self>>> {
-        vec![Box::new(TreeView)]
+        vec![
+            Box::new(TreeView),
+            Box::new(watch::TreeWatch),
+            Box::new(ps::TreePs),
+            Box::new(cargo::TreeCargo),
+            Box::new(du::TreeDu),
+            Box::new(diff::TreeDiff),
+            Box::new(paths::TreeFromPaths),
+            Box::new(flatten::TreeFlatten),
+            Box::new(stats::TreeStats),
+            Box::new(explore::TreeExplore),
+        ]
     }
 }
 
@@ -48,9 +102,255 @@ impl PluginCommand for TreeView {
         Signature::build(self.name())
             .switch(
                 "path",
-                "tell the tree command that the parameter is a path",
+                "tell the tree command that the parameter is a path (the current directory without input)",
                 Some('p'),
             )
+            .named(
+                "charset",
+                SyntaxShape::String,
+                "the characters used to draw the branches: utf8 (default) or ascii",
+                None,
+            )
+            .named(
+                "root",
+                SyntaxShape::String,
+                "the label of the top node of a data tree (default: the name of the opened file, or value)",
+                None,
+            )
+            .named(
+                "by",
+                SyntaxShape::List(Box::new(SyntaxShape::String)),
+                "build the tree from a table whose rows reference their parent: [id-column parent-column]",
+                None,
+            )
+            .named(
+                "indent",
+                SyntaxShape::Int,
+                "the width of each level of the tree (default 4)",
+                None,
+            )
+            .switch(
+                "numbered",
+                "number the entries of the tree in the order they are drawn",
+                Some('n'),
+            )
+            .switch(
+                "cached",
+                "reuse the previous walk of the same path, if any, instead of reading the tree again",
+                None,
+            )
+            .switch(
+                "refresh",
+                "read the tree again and keep it for later calls with --cached",
+                None,
+            )
+            .named(
+                "threads",
+                SyntaxShape::Int,
+                "walk the tree on this many threads (default 1)",
+                None,
+            )
+            .named(
+                "pattern",
+                SyntaxShape::OneOf(vec![
+                    SyntaxShape::List(Box::new(SyntaxShape::String)),
+                    SyntaxShape::String,
+                ]),
+                "only show files matching these patterns; patterns with a / are anchored to the root",
+                Some('P'),
+            )
+            .named(
+                "ignore",
+                SyntaxShape::OneOf(vec![
+                    SyntaxShape::List(Box::new(SyntaxShape::String)),
+                    SyntaxShape::String,
+                ]),
+                "hide entries matching these patterns; patterns with a / are anchored to the root",
+                Some('I'),
+            )
+            .switch(
+                "from-paths",
+                "build the tree from a list of paths, drawing it while the paths stream in",
+                None,
+            )
+            .switch(
+                "parse",
+                "parse string input as JSON, NUON, XML or HTML and draw the value it holds",
+                None,
+            )
+            .switch(
+                "types",
+                "annotate each node with the type of its value, like int, filesize or record<…>",
+                Some('t'),
+            )
+            .named(
+                "depth",
+                SyntaxShape::Int,
+                "stop expanding data nested deeper than this, showing … in its place",
+                Some('d'),
+            )
+            .switch(
+                "counts",
+                "show how many items each list [n] and record {n} holds",
+                Some('c'),
+            )
+            .switch(
+                "sort-keys",
+                "draw the fields of records in alphabetical order",
+                None,
+            )
+            .switch(
+                "cell-paths",
+                "follow each value with the cell path that gets it, like (users.0.name)",
+                None,
+            )
+            .switch(
+                "compact",
+                "fold chains of single fields into one node, like a.b.c → 1",
+                None,
+            )
+            .named(
+                "max-width",
+                SyntaxShape::Int,
+                "cut values that would make their line wider than this, with … (default: the terminal width, 0 for no limit)",
+                None,
+            )
+            .switch(
+                "debug",
+                "follow each node with the span of its value, and the root with the pipeline metadata",
+                None,
+            )
+            .switch(
+                "dedup",
+                "draw repeated records and lists once, replacing later copies with the cell path of the first",
+                None,
+            )
+            .named(
+                "max-nodes",
+                SyntaxShape::Int,
+                "stop after this many fields and list items (default 100000, 0 for no limit)",
+                None,
+            )
+            .named(
+                "binary-preview",
+                SyntaxShape::Int,
+                "show this many bytes of binary values in hex (default 8)",
+                None,
+            )
+            .switch(
+                "raw-durations",
+                "write durations as their number of nanoseconds",
+                None,
+            )
+            .named(
+                "datefmt",
+                SyntaxShape::String,
+                "write dates with this strftime format, like '%Y-%m-%d' (default: as tables write them)",
+                None,
+            )
+            .named(
+                "collapse",
+                SyntaxShape::Int,
+                "show lists of plain values longer than this as a count",
+                None,
+            )
+            .switch(
+                "json",
+                "with --path, output the tree as JSON in the format of `tree -J`",
+                None,
+            )
+            .switch(
+                "xml",
+                "with --path, output the tree as XML in the format of `tree -X`",
+                None,
+            )
+            .switch(
+                "git-ignored",
+                "with --path, list files ignored by git dimmed and marked with !",
+                None,
+            )
+            .switch(
+                "git-dirty-only",
+                "with --path, only show files with uncommitted changes and the directories above them",
+                None,
+            )
+            .switch(
+                "git-submodules",
+                "with --path, show the git status of files inside submodules instead of only the submodule",
+                None,
+            )
+            .switch(
+                "git-diff-stats",
+                "with --path, show how many lines each changed file adds and removes, like M +12 -3",
+                None,
+            )
+            .switch(
+                "git-age",
+                "with --path, color names by how recently they were last committed, from red for today to grey for over a year",
+                None,
+            )
+            .named(
+                "filelimit",
+                SyntaxShape::Int,
+                "with --path, don't descend into directories holding more than this many entries, like tree --filelimit",
+                None,
+            )
+            .named(
+                "max-entries",
+                SyntaxShape::Int,
+                "with --path, keep at most this many entries (default 100000 when drawing the tree, 0 for no limit)",
+                None,
+            )
+            .switch(
+                "follow-symlinks",
+                "with --path, descend into symlinked directories, showing links back to a directory above them without following them",
+                Some('l'),
+            )
+            .switch(
+                "one-file-system",
+                "with --path, stay on the filesystem of the root path, like find -xdev",
+                Some('x'),
+            )
+            .switch(
+                "dereference",
+                "with --path, show the metadata of the targets of symlinks instead of the links, like ls -L",
+                None,
+            )
+            .switch(
+                "escape",
+                "with --path, also escape backslashes in names, so every name reads back unambiguously",
+                None,
+            )
+            .switch("quote", "wrap names in double quotes", Some('Q'))
+            .named(
+                "columns",
+                SyntaxShape::List(Box::new(SyntaxShape::String)),
+                "with --path, the metadata columns to show, in order: perms, size, modified, owner, git",
+                None,
+            )
+            .named(
+                "format",
+                SyntaxShape::String,
+                "with --path, serialize the tree instead of drawing it: json, xml, yaml, nuon, or record for nested records",
+                None,
+            )
+            .named(
+                "html",
+                SyntaxShape::String,
+                "with --path, output an HTML page linking each entry below the given base URL",
+                None,
+            )
+            .named(
+                "preset",
+                SyntaxShape::String,
+                "a bundle of data mode settings: json (types, counts, and truncated lists and strings)",
+                None,
+            )
+            .switch(
+                "as-records",
+                "with --path, return a table of the entries instead of the drawn tree",
+                None,
+            )
             .category(Category::Experimental)
     }
 
@@ -75,12 +375,82 @@ impl PluginCommand for TreeView {
                 description: "Transform the folder path into a typical tree display",
                 result: None,
             },
+            Example {
+                example: "tree --path",
+                description: "Draw the current directory",
+                result: None,
+            },
+            Example {
+                example: "'some/folder' | tree --path --charset ascii",
+                description: "Draw the tree using only ASCII characters",
+                result: None,
+            },
+            Example {
+                example: "ps | tree --by [pid ppid]",
+                description: "Nest each row under the row whose id matches its parent id",
+                result: None,
+            },
+            Example {
+                example: "'some/folder' | tree --path --as-records | where type == file | sort-by size",
+                description: "List the entries of a folder as a table",
+                result: None,
+            },
+            Example {
+                example: "'some/folder' | tree --path --columns [size modified]",
+                description: "Show only the size and modification time of each entry",
+                result: None,
+            },
+            Example {
+                example: "'some/folder' | tree --path --json | from json",
+                description: "Export the folder in the JSON format of GNU tree",
+                result: None,
+            },
+            Example {
+                example: "'some/folder' | tree --path --format yaml | save snapshot.yaml",
+                description: "Archive a snapshot of a folder as YAML",
+                result: None,
+            },
+            Example {
+                example: "'some/folder' | tree --path --format record | get children.name",
+                description: "Get the folder as nested records and list its top-level entries",
+                result: None,
+            },
+            Example {
+                example: "'some/folder' | tree --path --html https://example.com/files | save index.html",
+                description: "Publish a browsable index of a folder",
+                result: None,
+            },
+            Example {
+                example: "glob **/*.rs | path relative-to (pwd) | tree --from-paths",
+                description: "Draw the tree of the paths in a list",
+                result: None,
+            },
+            Example {
+                example: "scope modules | tree",
+                description: "Show each module with its commands, aliases and submodules",
+                result: None,
+            },
+            Example {
+                example: "$env | tree --types --collapse 5",
+                description: "Show the environment with the type of each variable, counting long lists like PATH",
+                result: None,
+            },
+            Example {
+                example: "http get https://api.github.com/repos/nushell/nushell | tree --preset json",
+                description: "Explore an API payload with types, counts and truncated values",
+                result: None,
+            },
+            Example {
+                example: "ls | tree --indent 2",
+                description: "Draw a compact tree with two columns per level",
+                result: None,
+            },
         ]
     }
 
     fn run(
         &self,
-        _plugin: &TreePlugin,
+        plugin: &TreePlugin,
         engine: &EngineInterface,
         call: &EvaluatedCall,
         input: PipelineData,
@@ -88,6 +458,9 @@ impl PluginCommand for TreeView {
         let _span = call.head;
         let config = engine.get_config()?;
         let path_param = call.has_flag("path")?;
+        let numbered = call.has_flag("numbered")?;
+        let branches = config::branch_style(engine, call)?;
+        let color = config::color_choice(engine, &config)?;
         // engine.get_config()?.use_ansi_coloring = true;
 
         if path_param {
@@ -95,158 +468,804 @@ impl PluginCommand for TreeView {
             #[cfg(windows)]
             nu_ansi_term::enable_ansi_support().unwrap();
 
-            // If the path flag is set, we assume the input is a path and handle it accordingly
-            if let PipelineData::Value(Value::String { val, .. }, _) = &input {
-                // Create a tree from the path string
-                let mut view_args = view::ViewArgs::default();
-                view_args.path = val.into();
-                view_args.color = ColorChoice::Always;
-                view_args.git_status = true;
-                view_args.size = true;
-                view_args.icons = true;
-                view_args.all = true;
-                view_args.permissions = true;
-                let ls_colors_str = engine
-                    .get_env_var("LS_COLORS")?
-                    .and_then(|v| Some(v.coerce_into_string().ok()?));
-                let ls_colors = utils::get_ls_colors(ls_colors_str);
-                view::run(&view_args, &ls_colors).map_err(|err| {
+            // If the path flag is set, we assume the input is a path, a glob or
+            // a record of options for a path, or a list of them drawn as a
+            // forest, and handle it accordingly
+            let roots = match input {
+                PipelineData::Value(
+                    root @ (Value::String { .. } | Value::Glob { .. } | Value::Record { .. }),
+                    _,
+                ) => vec![root],
+                PipelineData::Value(Value::List { vals, .. }, _) if !vals.is_empty() => vals,
+                // Without input, the current directory is drawn.
+                PipelineData::Empty | PipelineData::Value(Value::Nothing { .. }, _) => {
+                    vec![Value::string(".", call.head)]
+                }
+                _ => {
+                    return Err(LabeledError::new(
+                        "Expected a folder path to be provided when using --path flag",
+                    ));
+                }
+            };
+            // Create a tree for each path string
+            let columns = config::columns(call)?;
+            // Explicit columns replace the default decorations.
+            let shows = |column| columns.as_ref().is_none_or(|c| c.contains(&column));
+            let include = config::patterns(call, "pattern")?;
+            let exclude = config::patterns(call, "ignore")?;
+            // Only the drawn tree is capped unless --max-entries says otherwise,
+            // so records and exports hold the whole tree.
+            let drawn = !call.has_flag("as-records")?
+                && call.get_flag_span("html").is_none()
+                && export_format(call)?.is_none();
+            let view_args_at =
+                |path: PathBuf, glob: Option<&str>| -> Result<view::ViewArgs, LabeledError> {
+                    let mut view_args = view::ViewArgs {
+                        path: path.clone(),
+                        color,
+                        git_status: shows(view::Column::Git),
+                        size: shows(view::Column::Size),
+                        icons: true,
+                        all: true,
+                        permissions: shows(view::Column::Permissions),
+                        branches: branches.clone(),
+                        theme: Some(theme::load()?),
+                        columns: columns.clone(),
+                        numbered,
+                        git_ignored: call.has_flag("git-ignored")?,
+                        git_dirty_only: call.has_flag("git-dirty-only")?,
+                        git_submodules: call.has_flag("git-submodules")?,
+                        git_diff_stats: call.has_flag("git-diff-stats")?,
+                        git_age: call.has_flag("git-age")?,
+                        dereference: call.has_flag("dereference")?,
+                        follow_symlinks: call.has_flag("follow-symlinks")?,
+                        one_file_system: call.has_flag("one-file-system")?,
+                        file_limit: call
+                            .get_flag::<i64>("filelimit")?
+                            .map(|limit| limit.max(0) as usize),
+                        max_entries: match call.get_flag::<i64>("max-entries")? {
+                            Some(max) if max <= 0 => None,
+                            Some(max) => Some(max as usize),
+                            None => drawn.then_some(view::DEFAULT_MAX_ENTRIES),
+                        },
+                        escape: call.has_flag("escape")?,
+                        quote: call.has_flag("quote")?,
+                        summary: config::summary(engine)?,
+                        patterns: patterns::Patterns::new(&path, &include, &exclude)
+                            .and_then(|patterns| match glob {
+                                Some(glob) => patterns.with_glob(&path, glob),
+                                None => Ok(patterns),
+                            })
+                            .map_err(|err| {
+                                LabeledError::new("Invalid pattern").with_label(err, call.head)
+                            })?,
+                        ..Default::default()
+                    };
+                    config::walk_tuning(engine, call, &mut view_args)?;
+                    Ok(view_args)
+                };
+            // Returns the arguments for drawing a path, and the glob that picks
+            // its entries when it has wildcards.
+            let view_args_of = |val: &str| -> Result<_, LabeledError> {
+                if provider::is_url(val) {
+                    return Ok((view_args_at(val.into(), None)?, None));
+                }
+                // The plugin runs in its own directory, so relative paths are
+                // resolved against Nushell's. `.` components are dropped, so
+                // `.` draws as the directory itself.
+                let cwd = PathBuf::from(engine.get_current_dir()?);
+                let resolve = |val: &str| -> PathBuf { cwd.join(val).components().collect() };
+                // Paths with wildcards that don't name an existing path are globs,
+                // drawn from the directory before their first wildcard.
+                match patterns::split_glob(val) {
+                    Some((base, glob)) if !resolve(val).exists() => {
+                        Ok((view_args_at(resolve(&base), Some(&glob))?, Some(glob)))
+                    }
+                    _ => Ok((view_args_at(resolve(val), None)?, None)),
+                }
+            };
+            // Records also set options for their path, over the flags.
+            let view_args_for = |root: &Value| -> Result<_, LabeledError> {
+                let val = &config::root_path(root)?;
+                let (mut view_args, glob) = view_args_of(val)?;
+                config::path_options(root, &mut view_args)?;
+                Ok((view_args, glob))
+            };
+            if roots.len() > 1 {
+                let flag = [
+                    "as-records",
+                    "html",
+                    "format",
+                    "json",
+                    "xml",
+                    "cached",
+                    "refresh",
+                ]
+                .into_iter()
+                .find_map(|flag| call.get_flag_span(flag));
+                if let Some(span) = flag {
+                    return Err(
+                        LabeledError::new("Can't combine a list of paths with this flag")
+                            .with_label("only a single path can be exported or cached", span),
+                    );
+                }
+                let roots = roots
+                    .iter()
+                    .map(|root| view_args_for(root).map(|(view_args, _)| view_args))
+                    .collect::<Result<Vec<_>, _>>()?;
+                let ls_colors = plugin.ls_colors(engine)?;
+                let mut out = Vec::new();
+                view::run_forest(roots, &ls_colors, &mut out).map_err(|err| {
                     LabeledError::new(format!("Error trying to create a tree view: {}", err))
                 })?;
-                return Ok(PipelineData::Empty);
+                return Ok(string_output(out, call.head));
+            }
+            let (view_args, glob) = view_args_for(&roots[0])?;
+            let path = view_args.path.clone();
+            let cache_mode = if call.has_flag("refresh")? {
+                cache::CacheMode::Refresh
+            } else if call.has_flag("cached")? {
+                cache::CacheMode::Cached
             } else {
-                return Err(LabeledError::new(
-                    "Expected a folder path to be provided when using --path flag",
+                cache::CacheMode::Off
+            };
+            let key = cache::WalkKey {
+                path: view_args.path.clone(),
+                level: view_args.level,
+                dirs_only: view_args.dirs_only,
+                all: view_args.all,
+                gitignore: view_args.gitignore,
+                git_status: view_args.git_status,
+                git_ignored: view_args.git_ignored,
+                git_dirty_only: view_args.git_dirty_only,
+                git_submodules: view_args.git_submodules,
+                git_diff_stats: view_args.git_diff_stats,
+                git_age: view_args.git_age,
+                dereference: view_args.dereference,
+                follow_symlinks: view_args.follow_symlinks,
+                one_file_system: view_args.one_file_system,
+                file_limit: view_args.file_limit,
+                max_entries: view_args.max_entries,
+                include,
+                exclude,
+                glob,
+            };
+            let walk = || {
+                plugin
+                    .walk_cache
+                    .walk(key.clone(), cache_mode, || walk_entries(&view_args))
+            };
+            // Records and exports have no room for a notice, so they need
+            // the whole tree.
+            let complete_walk = || {
+                let walk = walk()?;
+                if walk.interrupted {
+                    return Err(LabeledError::new(
+                        "Error trying to walk the path: the walk was interrupted",
+                    ));
+                }
+                Ok::<_, LabeledError>(walk)
+            };
+            if call.has_flag("as-records")? {
+                let walk = complete_walk()?;
+                return Ok(PipelineData::Value(
+                    records::walk_to_value(&view_args.path, &walk, columns.as_deref(), call.head),
+                    None,
                 ));
             }
+            if let Some(base_url) = call.get_flag::<String>("html")? {
+                let walk = complete_walk()?;
+                return Ok(PipelineData::Value(
+                    Value::string(
+                        export::to_html(&view_args, &walk.entries, &base_url),
+                        call.head,
+                    ),
+                    None,
+                ));
+            }
+            if let Some(format) = export_format(call)? {
+                let walk = complete_walk()?;
+                let root = path.to_string_lossy();
+                let nested =
+                    || records::walk_to_nested(&root, &walk, columns.as_deref(), call.head);
+                let text = match format {
+                    ExportFormat::Json => export::to_json(&view_args, &walk.entries),
+                    ExportFormat::Xml => export::to_xml(&view_args, &walk.entries),
+                    ExportFormat::Yaml => {
+                        call_command(engine, "to yaml", &[], nested(), call.head)?
+                            .coerce_into_string()?
+                    }
+                    ExportFormat::Nuon => {
+                        call_command(engine, "to nuon", &[], nested(), call.head)?
+                            .coerce_into_string()?
+                    }
+                    ExportFormat::Record => {
+                        return Ok(PipelineData::Value(nested(), None));
+                    }
+                };
+                return Ok(PipelineData::Value(Value::string(text, call.head), None));
+            }
+            let ls_colors = plugin.ls_colors(engine)?;
+            let mut out = Vec::new();
+            if cache_mode == cache::CacheMode::Off {
+                view::run(&view_args, &ls_colors, &mut out).map_err(|err| {
+                    LabeledError::new(format!("Error trying to create a tree view: {}", err))
+                })?;
+            } else {
+                view::draw(&view_args, &*walk()?, &ls_colors, &mut out);
+            }
+            return Ok(string_output(out, call.head));
+        }
+        // JSON payloads, like `open data.json`, and HTML pages are parsed
+        // without --parse. The json preset cuts values short, so it is only
+        // used when asked for.
+        let content_type = input.metadata().and_then(|metadata| metadata.content_type);
+        let is_json = content_type.as_deref() == Some("application/json");
+        let is_html =
+            content_type.is_some_and(|content_type| content_type.starts_with("text/html"));
+        let parse = call.has_flag("parse")? || is_json || is_html;
+        // Text streams, like the output of `^fd`, are read as lines of paths.
+        // The output of external commands is taken for text when it starts
+        // with text; other byte streams need --from-paths.
+        let input = if parse {
+            input
+        } else {
+            sniff_text(input, engine)
+        };
+        let text_stream = matches!(
+            &input,
+            PipelineData::ByteStream(stream, _) if stream.type_() == ByteStreamType::String
+        ) && !parse;
+        if call.has_flag("from-paths")? || text_stream {
+            return paths::stream(engine, call, input, branches);
         }
         // eprintln!("Running in tree mode");
-        // Process different types of input
-        let tree = match input {
-            PipelineData::ListStream(list_stream, _) => {
-                // For list streams, consume the stream directly
-                let values = list_stream.into_value()?;
-                let mut tree_builder = TreeBuilder::new("root".to_string());
-                from_value_helper(&values, &mut tree_builder, config);
-                tree_builder.build()
+        let root = root_label(call, &input)?;
+        let input = parse_input(engine, input, parse)?;
+        if let Some(by) = call.get_flag::<Vec<String>>("by")? {
+            let [id, parent] = by.as_slice() else {
+                return Err(LabeledError::new("Invalid --by value").with_label(
+                    "expected an id column and a parent id column, like [pid ppid]",
+                    call.get_flag_span("by").unwrap_or(call.head),
+                ));
+            };
+            let columns = edges::EdgeColumns { id, parent };
+            let rows = input.into_value(call.head)?.into_list()?;
+            let mut tree = edges::build(
+                &rows,
+                &columns,
+                root.as_deref().unwrap_or("root"),
+                edges::default_label(&columns),
+                call.head,
+            )?;
+            if numbered {
+                data::number_items(&mut tree);
             }
-            _ => from_value(&input, config),
+            return Ok(stream_tree(tree, branches, color, engine, call.head));
+        }
+
+        let mut data_args = match call.get_flag_value("preset") {
+            Some(preset) => data::DataArgs::preset(&preset.coerce_string()?).map_err(|err| {
+                LabeledError::new("Invalid preset").with_label(err, preset.span())
+            })?,
+            None => data::DataArgs::default(),
         };
+        data_args.types |= call.has_flag("types")?;
+        data_args.signals = Some(engine.signals().clone());
+        data_args.engine = Some(engine.clone());
+        if let Some(limit) = call.get_flag::<i64>("collapse")? {
+            data_args.collapse = Some(limit.max(0) as usize);
+        }
+        data_args.counts |= call.has_flag("counts")?;
+        data_args.sort_keys |= call.has_flag("sort-keys")?;
+        data_args.cell_paths |= call.has_flag("cell-paths")?;
+        data_args.compact |= call.has_flag("compact")?;
+        data_args.dedup |= call.has_flag("dedup")?;
+        data_args.debug |= call.has_flag("debug")?;
+        // With --debug, the root also tells where the input came from.
+        let metadata = data_args
+            .debug
+            .then(|| data::metadata_text(input.metadata().as_ref()));
+        data_args.raw_durations |= call.has_flag("raw-durations")?;
+        data_args.max_nodes = match call.get_flag::<i64>("max-nodes")? {
+            Some(max) if max <= 0 => None,
+            Some(max) => Some(max as usize),
+            None => Some(data::DEFAULT_MAX_NODES),
+        };
+        data_args.binary_preview = call
+            .get_flag::<i64>("binary-preview")?
+            .map_or(data::BINARY_PREVIEW, |bytes| bytes.max(0) as usize);
+        data_args.indent = branches.indent;
+        data_args.max_width = match call.get_flag::<i64>("max-width")? {
+            Some(width) if width > 0 => Some(width as usize),
+            Some(_) => None,
+            None => crossterm::terminal::size()
+                .ok()
+                .map(|(columns, _)| columns as usize),
+        };
+        if let Some(depth) = call.get_flag::<i64>("depth")? {
+            data_args.depth = Some(depth.max(1) as usize);
+        }
+        if let Some(format) = call.get_flag_value("datefmt") {
+            let span = format.span();
+            let format = format.coerce_into_string()?;
+            chrono::format::StrftimeItems::new(&format)
+                .parse()
+                .map_err(|err| {
+                    LabeledError::new("Invalid date format").with_label(err.to_string(), span)
+                })?;
+            data_args.date_format = Some(format);
+        }
+        // JSON byte streams, like `open --raw big.json`, are parsed as they are
+        // read, keeping only what the tree draws. XML is read whole.
+        let input = match input {
+            PipelineData::ByteStream(stream, metadata) if parse => {
+                let span = stream.span();
+                let value = match stream.reader().map(BufReader::new) {
+                    Some(mut reader) => {
+                        if starts_with_tag(&mut reader) {
+                            let mut text = String::new();
+                            reader.read_to_string(&mut text).map_err(|err| {
+                                LabeledError::new("Couldn't read the input")
+                                    .with_label(err.to_string(), span)
+                            })?;
+                            parse_markup(engine, &text, is_html, span)?
+                        } else {
+                            json::read(reader, &data_args, span).map_err(|err| {
+                                LabeledError::new("Couldn't parse the input as JSON")
+                                    .with_label(err.to_string(), span)
+                            })?
+                        }
+                    }
+                    None => Value::nothing(span),
+                };
+                PipelineData::Value(value, metadata)
+            }
+            input => input,
+        };
+        // XML documents, parsed here or by `open`, are drawn as trees of elements.
+        if let PipelineData::Value(value, _) = &input
+            && markup::is_xml(value)
+        {
+            let mut tree = markup::xml_tree(value, root.as_deref().unwrap_or("document"));
+            if numbered {
+                data::number_items(&mut tree);
+            }
+            return Ok(stream_tree(tree, branches, color, engine, call.head));
+        }
+        let mut tree_config = print_config(branches, color);
+        if color != ColorChoice::Never {
+            data_args.styles = nu_color_config::get_color_map(&config.color_config);
+        }
+        // Values colored by type replace the bold of the plugin's leaf style.
+        if !data_args.styles.is_empty() {
+            tree_config.leaf = Style::default();
+        }
 
-        // Set up the print configuration
-        let tree_config = {
-            let mut tree_config = PrintConfig::from_env();
-            tree_config.branch = Style {
-                foreground: Some(Color::Green),
-                dimmed: true,
-                ..Style::default()
-            };
-            tree_config.leaf = Style {
-                bold: true,
-                ..Style::default()
-            };
-            tree_config.indent = 4;
-            tree_config
+        // List streams are drawn item by item as they arrive, unless --dedup
+        // has to look back at earlier items.
+        if !data_args.dedup
+            && let PipelineData::ListStream(list_stream, _) = input
+        {
+            let list = data::ListTree::new(data_args, config);
+            let root = list.root_label(root.as_deref().unwrap_or(DEFAULT_ROOT));
+            return Ok(stream_list(
+                list_stream,
+                list,
+                match metadata {
+                    Some(metadata) => format!("{root} {metadata}"),
+                    None => root,
+                },
+                numbered,
+                tree_config,
+                engine,
+                call.head,
+            ));
+        }
+        // Byte streams that aren't read as paths are drawn as their bytes.
+        let value = match input {
+            PipelineData::Empty => None,
+            input => Some(input.into_value(call.head)?),
         };
+        let mut tree = data::from_value(
+            value.as_ref(),
+            root.as_deref().unwrap_or(DEFAULT_ROOT),
+            &data_args,
+            config,
+        );
+        if let Some(metadata) = metadata
+            && let Some(top) = tree.children.first_mut()
+        {
+            top.text = format!("{} {metadata}", top.text);
+        }
+        if numbered {
+            data::number_items(&mut tree);
+        }
 
-        // Print out the tree using custom formatting
-        print_tree_with(&tree, &tree_config)
-            .map_err(|err| LabeledError::new(format!("Error calculating tree: {}", err)))?;
+        Ok(stream_tree_with(tree, tree_config, engine, call.head))
+    }
+}
 
-        Ok(PipelineData::Empty)
+/// The label of the top node of a data tree when neither `--root` nor a file
+/// name gives one.
+const DEFAULT_ROOT: &str = "value";
+
+/// The label of the top node of a data tree: `--root`, or the name of the file
+/// the data was opened from.
+fn root_label(call: &EvaluatedCall, input: &PipelineData) -> Result<Option<String>, LabeledError> {
+    if let Some(root) = call.get_flag::<String>("root")? {
+        return Ok(Some(root));
     }
+    Ok(input
+        .metadata()
+        .and_then(|metadata| match metadata.data_source {
+            DataSource::FilePath(path) => path
+                .file_name()
+                .map(|name| name.to_string_lossy().into_owned()),
+            _ => None,
+        }))
 }
 
-fn from_value(input: &PipelineData, config: Arc<Config>) -> StringItem {
-    let mut tree = TreeBuilder::new("".to_string());
-    let builder = &mut tree;
+/// Parses a JSON or NUON string, like the body of an HTTP response, into the
+/// value it holds when `parse` is set; other input is kept as it is.
+fn parse_input(
+    engine: &EngineInterface,
+    input: PipelineData,
+    parse: bool,
+) -> Result<PipelineData, LabeledError> {
+    let PipelineData::Value(value @ Value::String { .. }, metadata) = input else {
+        return Ok(input);
+    };
+    if !parse {
+        return Ok(PipelineData::Value(value, metadata));
+    }
+    let span = value.span();
+    if let Ok(text) = value.as_str()
+        && text.trim_start().starts_with('<')
+    {
+        let html = metadata
+            .as_ref()
+            .and_then(|metadata| metadata.content_type.as_deref())
+            .is_some_and(|content_type| content_type.starts_with("text/html"));
+        let parsed = parse_markup(engine, text, html, span)?;
+        return Ok(PipelineData::Value(parsed, metadata));
+    }
+    // Without --strict, `from json` takes most text as a bare string.
+    let parsed = call_command(engine, "from json", &["strict"], value.clone(), span)
+        .or_else(|_| call_command(engine, "from nuon", &[], value, span))
+        .map_err(|_| {
+            LabeledError::new("Couldn't parse the input")
+                .with_label("expected a JSON or NUON string", span)
+        })?;
+    Ok(PipelineData::Value(parsed, metadata))
+}
 
-    match input {
-        PipelineData::Empty => {
-            builder.add_empty_child("empty".to_string());
-        }
-        PipelineData::Value(value, _pipeline_metadata) => {
-            builder.begin_child("value".to_string());
-            from_value_helper(value, builder, config);
-            builder.end_child();
-        }
-        PipelineData::ListStream(_, _) => {
-            // For ListStreams, just add a placeholder node since we can't easily iterate over a reference
-            builder.begin_child("list stream".to_string());
-            builder.add_empty_child("<contains stream data>".to_string());
-            builder.end_child();
-        }
-        PipelineData::ByteStream(_byte_stream, _pipeline_metadata) => {
-            builder.add_empty_child("binary stream".to_string());
-        }
+/// Reads markup: HTML pages, told by their doctype or by `html` being set for
+/// a `text/html` response, with the forgiving parser of [`markup`], and XML
+/// with `from xml`, keeping its comments and processing instructions.
+fn parse_markup(
+    engine: &EngineInterface,
+    text: &str,
+    html: bool,
+    span: Span,
+) -> Result<Value, LabeledError> {
+    if html || markup::is_html(text) {
+        return Ok(markup::parse_html(text, span));
     }
+    let xml = Value::string(text, span);
+    call_command(engine, "from xml", &["keep-comments", "keep-pi"], xml, span).map_err(|_| {
+        LabeledError::new("Couldn't parse the input").with_label("expected an XML document", span)
+    })
+}
 
-    builder.build()
+/// Gives a byte stream of unknown type, like the output of an external
+/// command, the string type when its first chunk is text.
+fn sniff_text(input: PipelineData, engine: &EngineInterface) -> PipelineData {
+    let PipelineData::ByteStream(stream, metadata) = input else {
+        return input;
+    };
+    if stream.type_() != ByteStreamType::Unknown {
+        return PipelineData::ByteStream(stream, metadata);
+    }
+    let span = stream.span();
+    let Some(mut reader) = stream.reader() else {
+        return PipelineData::Empty;
+    };
+    let type_ = match reader.fill_buf() {
+        Ok(buf) if is_text(buf) => ByteStreamType::String,
+        _ => ByteStreamType::Unknown,
+    };
+    let stream = ByteStream::read(reader, span, engine.signals().clone(), type_);
+    PipelineData::ByteStream(stream, metadata)
 }
 
-fn from_value_helper(value: &Value, builder: &mut TreeBuilder, config: Arc<Config>) {
-    match value {
-        Value::Bool { val, .. } => {
-            builder.add_empty_child(val.to_string());
-        }
-        Value::Int { val, .. } => {
-            builder.add_empty_child(val.to_string());
-        }
-        Value::Float { val, .. } => {
-            builder.add_empty_child(val.to_string());
-        }
-        Value::String { val, .. } => {
-            builder.add_empty_child(val.clone());
-        }
-        Value::Glob { val, .. } => {
-            builder.add_empty_child(val.to_string());
-        }
-        Value::Filesize { val, .. } => {
-            builder.add_empty_child(val.to_string());
-        }
-        Value::Duration { val, .. } => {
-            builder.add_empty_child(val.to_string());
+/// Whether the start of a stream is UTF-8 text. A character cut off at the
+/// end of the chunk still counts.
+fn is_text(buf: &[u8]) -> bool {
+    !buf.contains(&0)
+        && match std::str::from_utf8(buf) {
+            Ok(_) => true,
+            Err(err) => err.error_len().is_none(),
         }
-        Value::Date { val, .. } => {
-            builder.add_empty_child(val.to_string());
-        }
-        Value::Range { val, .. } => {
-            builder.add_empty_child(val.to_string());
-        }
-        Value::Record { val, .. } => {
-            for (k, v) in val.iter() {
-                builder.begin_child(k.clone());
-                from_value_helper(v, builder, config.clone());
-                builder.end_child();
+}
+
+/// Whether buffered text starts with a tag, once its leading whitespace is skipped.
+fn starts_with_tag(reader: &mut impl BufRead) -> bool {
+    reader
+        .fill_buf()
+        .is_ok_and(|buf| buf.iter().find(|byte| !byte.is_ascii_whitespace()) == Some(&b'<'))
+}
+
+/// The serialized outputs of path mode.
+enum ExportFormat {
+    Json,
+    Xml,
+    Yaml,
+    Nuon,
+    /// The nested records themselves, rather than serialized text.
+    Record,
+}
+
+/// Reads `--format`, or its `--json` and `--xml` shorthands.
+fn export_format(call: &EvaluatedCall) -> Result<Option<ExportFormat>, LabeledError> {
+    if let Some(format) = call.get_flag_value("format") {
+        return match format.coerce_str()?.as_ref() {
+            "json" => Ok(Some(ExportFormat::Json)),
+            "xml" => Ok(Some(ExportFormat::Xml)),
+            "yaml" => Ok(Some(ExportFormat::Yaml)),
+            "nuon" => Ok(Some(ExportFormat::Nuon)),
+            "record" => Ok(Some(ExportFormat::Record)),
+            _ => Err(LabeledError::new("Invalid format")
+                .with_label("expected json, xml, yaml, nuon or record", format.span())),
+        };
+    }
+    if call.has_flag("json")? {
+        Ok(Some(ExportFormat::Json))
+    } else if call.has_flag("xml")? {
+        Ok(Some(ExportFormat::Xml))
+    } else {
+        Ok(None)
+    }
+}
+
+/// Walks a path for the structured and exported outputs of path mode.
+fn walk_entries(view_args: &view::ViewArgs) -> Result<view::Walk, LabeledError> {
+    view::walk(view_args, true)
+        .map_err(|err| LabeledError::new(format!("Error trying to walk the path: {}", err)))
+}
+
+/// Runs one of Nushell's own commands on a value, with the given switches.
+fn call_command(
+    engine: &EngineInterface,
+    name: &str,
+    switches: &[&str],
+    input: Value,
+    span: Span,
+) -> Result<Value, LabeledError> {
+    let Some(decl) = engine.find_decl(name)? else {
+        return Err(LabeledError::new(format!(
+            "The {name} command is not available"
+        )));
+    };
+    Ok(engine
+        .call_decl(
+            decl,
+            switches
+                .iter()
+                .fold(EvaluatedCall::new(span), |call, switch| {
+                    call.with_flag(switch.to_string().into_spanned(span))
+                }),
+            PipelineData::Value(input, None),
+            true,
+            false,
+        )?
+        .into_value(span)?)
+}
+
+/// How many bytes of a drawn data tree are sent to Nushell at a time.
+const STREAM_CHUNK: usize = 8 * 1024;
+
+/// Draws a data tree with the plugin's branch and leaf styles, streaming the
+/// drawing as it is written instead of holding all of it in memory.
+fn stream_tree(
+    tree: StringItem,
+    branches: view::BranchStyle,
+    color: ColorChoice,
+    engine: &EngineInterface,
+    span: Span,
+) -> PipelineData {
+    stream_tree_with(tree, print_config(branches, color), engine, span)
+}
+
+/// Draws a data tree like `stream_tree`, with the given print config.
+fn stream_tree_with(
+    tree: StringItem,
+    tree_config: PrintConfig,
+    engine: &EngineInterface,
+    span: Span,
+) -> PipelineData {
+    stream_drawing(engine, span, move |out| {
+        write_tree_with(&tree, out, &tree_config)
+    })
+}
+
+/// Draws the tree of a list stream under a node labeled `root`, drawing each item
+/// as soon as the next one shows it isn't the last.
+fn stream_list(
+    stream: ListStream,
+    mut list: data::ListTree,
+    root: String,
+    numbered: bool,
+    tree_config: PrintConfig,
+    engine: &EngineInterface,
+    span: Span,
+) -> PipelineData {
+    stream_drawing(engine, span, move |out| {
+        let root = StringItem {
+            text: root,
+            children: Vec::new(),
+        };
+        write_tree_with(&root, &mut *out, &tree_config)?;
+        let mut next_number = 1;
+        // The last node drawn is told apart by its connector, so each node
+        // waits for the one after it.
+        let mut pending: Option<StringItem> = None;
+        let mut draw = |mut nodes: StringItem, out: &mut ChunkSender| {
+            if numbered {
+                data::number_below(&mut nodes, &mut next_number);
             }
-        }
-        Value::List { vals, .. } => {
-            for value in vals {
-                from_value_helper(value, builder, config.clone());
+            for node in nodes.children {
+                if let Some(previous) = pending.replace(node) {
+                    write_child(previous, false, out, &tree_config)?;
+                }
             }
+            Ok::<(), std::io::Error>(())
+        };
+        for value in stream {
+            draw(list.push(value), out)?;
         }
-        Value::Closure { val, .. } => {
-            builder.add_empty_child(val.block_id.get().to_string());
-        }
-        Value::Error { error, .. } => {
-            builder.add_empty_child(error.to_string());
+        draw(list.finish(), out)?;
+        match pending {
+            Some(last) => write_child(last, true, out, &tree_config),
+            None => Ok(()),
         }
-        Value::Binary { .. } => {
-            builder.add_empty_child("binary".to_string());
+    })
+}
+
+/// Draws `node` as a child of the root, without the root's own line.
+fn write_child(
+    node: StringItem,
+    last: bool,
+    out: &mut ChunkSender,
+    tree_config: &PrintConfig,
+) -> std::io::Result<()> {
+    let mut root = StringItem {
+        text: String::new(),
+        children: vec![node],
+    };
+    // An empty sibling after a node that isn't last gives it the right connectors.
+    if !last {
+        root.children.push(StringItem {
+            text: String::new(),
+            children: Vec::new(),
+        });
+    }
+    let mut drawing = Vec::new();
+    write_tree_with(&root, &mut drawing, tree_config)?;
+    let mut lines: Vec<&[u8]> = drawing.split_inclusive(|byte| *byte == b'\n').collect();
+    if !last {
+        lines.pop();
+    }
+    for line in lines.into_iter().skip(1) {
+        out.write_all(line)?;
+    }
+    Ok(())
+}
+
+/// The branch and leaf styles of data trees.
+fn print_config(branches: view::BranchStyle, color: ColorChoice) -> PrintConfig {
+    let mut tree_config = PrintConfig::from_env();
+    tree_config.branch = Style {
+        foreground: Some(Color::Green),
+        dimmed: true,
+        ..Style::default()
+    };
+    tree_config.leaf = Style {
+        bold: true,
+        ..Style::default()
+    };
+    tree_config.indent = branches.indent;
+    tree_config.characters = branches.characters;
+    tree_config.styled = match color {
+        ColorChoice::Never => StyleWhen::Never,
+        _ => StyleWhen::Always,
+    };
+    tree_config
+}
+
+/// Streams what `write` draws. The drawing is written on its own thread,
+/// which waits while Nushell catches up.
+fn stream_drawing(
+    engine: &EngineInterface,
+    span: Span,
+    write: impl FnOnce(&mut ChunkSender) -> std::io::Result<()> + Send + 'static,
+) -> PipelineData {
+    let (tx, rx) = std::sync::mpsc::sync_channel(4);
+    std::thread::spawn(move || {
+        let mut out = ChunkSender {
+            tx: tx.clone(),
+            chunk: Vec::with_capacity(STREAM_CHUNK),
+        };
+        // Writing only fails once Nushell stops reading, and then there is no
+        // one left to tell.
+        if let Err(err) = write(&mut out).and_then(|()| out.flush())
+            && err.kind() != std::io::ErrorKind::BrokenPipe
+        {
+            _ = tx.send(Err(ShellError::GenericError {
+                error: format!("Error calculating tree: {err}"),
+                msg: String::new(),
+                span: Some(span),
+                help: None,
+                inner: Vec::new(),
+            }));
         }
-        Value::CellPath { val, .. } => {
-            builder.add_empty_child(val.to_string());
+    });
+
+    // Like the other outputs, the drawing doesn't end with a newline.
+    let mut chunks = rx.into_iter().peekable();
+    let chunks = std::iter::from_fn(move || {
+        let mut chunk: Result<Vec<u8>, ShellError> = chunks.next()?;
+        if chunks.peek().is_none()
+            && let Ok(bytes) = &mut chunk
+        {
+            bytes.truncate(bytes.trim_ascii_end().len());
         }
-        Value::Custom { .. } => {
-            builder.add_empty_child("custom".to_string());
+        Some(chunk)
+    });
+    let stream = ByteStream::from_result_iter(
+        chunks,
+        span,
+        engine.signals().clone(),
+        ByteStreamType::String,
+    );
+    PipelineData::ByteStream(stream, None)
+}
+
+/// Sends what is written in chunks of `STREAM_CHUNK` bytes.
+struct ChunkSender {
+    tx: SyncSender<Result<Vec<u8>, ShellError>>,
+    chunk: Vec<u8>,
+}
+
+impl Write for ChunkSender {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.chunk.extend_from_slice(buf);
+        if self.chunk.len() >= STREAM_CHUNK {
+            self.flush()?;
         }
-        Value::Nothing { .. } => {
-            builder.add_empty_child("null".to_string());
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        if self.chunk.is_empty() {
+            return Ok(());
         }
+        let chunk = std::mem::replace(&mut self.chunk, Vec::with_capacity(STREAM_CHUNK));
+        self.tx
+            .send(Ok(chunk))
+            .map_err(|_| std::io::ErrorKind::BrokenPipe.into())
     }
 }
 
+/// Wraps rendered output in a string value, without the final newline.
+fn string_output(out: Vec<u8>, span: Span) -> PipelineData {
+    let mut text = String::from_utf8_lossy(&out).into_owned();
+    text.truncate(text.trim_end_matches('\n').len());
+    PipelineData::Value(Value::string(text, span), None)
+}
+
 #[test]
+#[allow(clippy::result_large_err)]
 fn test_examples() -> Result<(), nu_protocol::ShellError> {
     use nu_plugin_test_support::PluginTest;
 
@@ -254,9 +1273,21 @@ fn test_examples() -> Result<(), nu_protocol::ShellError> {
     // output against what was specified in the example. You can remove this test if the examples
     // can't be tested this way, but we recommend including it if possible.
 
-    PluginTest::new("tree", TreePlugin.into())?.test_command_examples(&TreeView)
+    let mut plugin_test = PluginTest::new("tree", TreePlugin::default().into())?;
+    plugin_test.test_command_examples(&TreeView)?;
+    plugin_test.test_command_examples(&flatten::TreeFlatten)
+}
+
+#[test]
+fn test_is_text() {
+    assert!(is_text(b"src/main.rs\nCargo.toml\n"));
+    assert!(is_text("caf\u{e9}".as_bytes()));
+    // A character cut off by the end of the chunk.
+    assert!(is_text(&"caf\u{e9}".as_bytes()[..4]));
+    assert!(!is_text(b"\x7fELF\0\x02"));
+    assert!(!is_text(b"caf\xe9.txt"));
 }
 
 fn main() {
-    serve_plugin(&TreePlugin, MsgPackSerializer);
+    serve_plugin(&TreePlugin::default(), MsgPackSerializer);
 }
@@ -0,0 +1,449 @@
+//! Draws XML documents as trees of elements, as in `open pom.xml | tree` or
+//! `open --raw icon.svg | tree --parse`. Elements are branches, with their
+//! attributes and text as leaves.
+//!
+//! HTML pages, as in `http get https://example.com | tree`, are read with a
+//! forgiving parser into the same records `from xml` returns, and drawn as
+//! their DOM: elements are labeled with their tag, id and classes, like
+//! `div#main.content`, and their other attributes are left out.
+
+use nu_protocol::{Record, Span, Value};
+use ptree::TreeBuilder;
+use ptree::item::StringItem;
+
+/// How many characters of a text node are shown.
+pub const TEXT_PREVIEW: usize = 60;
+
+/// Elements that never have content or an end tag.
+const VOID_ELEMENTS: &[&str] = &[
+    "area", "base", "br", "col", "embed", "hr", "img", "input", "link", "meta", "param", "source",
+    "track", "wbr",
+];
+/// Elements whose content is text up to their end tag, even if it looks like tags.
+const RAW_TEXT_ELEMENTS: &[&str] = &["script", "style", "textarea", "title"];
+/// Elements whose end tag may be left out before a sibling of the same kind.
+const SIBLING_CLOSED: &[&str] = &["li", "p", "option", "tr", "td", "th", "dt", "dd"];
+
+/// Whether a value is a document read by `from xml`: a record with the `tag`,
+/// `attributes` and `content` of its root element.
+pub fn is_xml(value: &Value) -> bool {
+    value.as_record().is_ok_and(|record| {
+        record.len() == 3
+            && ["tag", "attributes", "content"]
+                .iter()
+                .all(|column| record.contains(column))
+    })
+}
+
+/// Whether text is an HTML page rather than XML, from its doctype or root tag.
+pub fn is_html(text: &str) -> bool {
+    let start: String = text
+        .trim_start()
+        .chars()
+        .take(14)
+        .collect::<String>()
+        .to_ascii_lowercase();
+    start.starts_with("<!doctype html") || start.starts_with("<html")
+}
+
+/// Builds the tree of a document read by `from xml` or [`parse_html`], below a
+/// `root` node. A document whose root element is `html` is drawn as a DOM.
+pub fn xml_tree(value: &Value, root: &str) -> StringItem {
+    let html = value.get_data_by_key("tag").is_some_and(|tag| {
+        tag.as_str()
+            .is_ok_and(|tag| tag.eq_ignore_ascii_case("html"))
+    });
+    let mut builder = TreeBuilder::new(root.to_string());
+    add_xml(value, html, &mut builder);
+    builder.build()
+}
+
+fn add_xml(value: &Value, html: bool, builder: &mut TreeBuilder) {
+    let Ok(node) = value.as_record() else {
+        return;
+    };
+    let content = node.get("content");
+    let text = content
+        .and_then(|content| content.as_str().ok())
+        .unwrap_or_default();
+    match node.get("tag").and_then(|tag| tag.as_str().ok()) {
+        None => {
+            if let Some(text) = text_preview(text) {
+                builder.add_empty_child(format!("\"{text}\""));
+            }
+        }
+        Some("!") => {
+            builder.add_empty_child(format!(
+                "<!-- {} -->",
+                text_preview(text).unwrap_or_default()
+            ));
+        }
+        Some(instruction) if instruction.starts_with('?') => {
+            builder.add_empty_child(format!("<{instruction} {}?>", text.trim()));
+        }
+        Some(tag) if html => {
+            let attribute = |name: &str| {
+                node.get("attributes")
+                    .and_then(|attributes| attributes.get_data_by_key(name))
+                    .and_then(|value| value.coerce_string().ok())
+                    .unwrap_or_default()
+            };
+            let mut label = tag.to_string();
+            if let Some(id) = attribute("id").split_whitespace().next() {
+                label.push_str(&format!("#{id}"));
+            }
+            for class in attribute("class").split_whitespace() {
+                label.push_str(&format!(".{class}"));
+            }
+            builder.begin_child(label);
+            if let Some(Ok(children)) = content.map(Value::as_list) {
+                for child in children {
+                    add_xml(child, html, builder);
+                }
+            }
+            builder.end_child();
+        }
+        Some(tag) => {
+            builder.begin_child(tag.to_string());
+            if let Some(Ok(attributes)) = node.get("attributes").map(Value::as_record) {
+                for (name, value) in attributes.iter() {
+                    builder.add_empty_child(format!(
+                        "@{name} = {}",
+                        value.coerce_str().unwrap_or_default()
+                    ));
+                }
+            }
+            if let Some(Ok(children)) = content.map(Value::as_list) {
+                for child in children {
+                    add_xml(child, html, builder);
+                }
+            }
+            builder.end_child();
+        }
+    }
+}
+
+/// An element whose end tag wasn't read yet.
+struct OpenElement {
+    tag: String,
+    attributes: Record,
+    content: Vec<Value>,
+}
+
+impl OpenElement {
+    fn new(tag: &str, attributes: Record) -> Self {
+        OpenElement {
+            tag: tag.to_string(),
+            attributes,
+            content: Vec::new(),
+        }
+    }
+
+    fn into_value(self, span: Span) -> Value {
+        node(
+            Value::string(self.tag, span),
+            Value::record(self.attributes, span),
+            Value::list(self.content, span),
+            span,
+        )
+    }
+}
+
+fn node(tag: Value, attributes: Value, content: Value, span: Span) -> Value {
+    let mut record = Record::new();
+    record.push("tag", tag);
+    record.push("attributes", attributes);
+    record.push("content", content);
+    Value::record(record, span)
+}
+
+fn text_node(tag: Option<&str>, text: String, span: Span) -> Value {
+    node(
+        tag.map_or(Value::nothing(span), |tag| Value::string(tag, span)),
+        Value::nothing(span),
+        Value::string(text, span),
+        span,
+    )
+}
+
+/// Reads an HTML page into the records `from xml` returns, the way browsers
+/// forgive it: void elements like `<br>` need no end tag, end tags that close
+/// nothing are ignored, and elements left open are closed by the end tag of
+/// their parent. Content outside of an `html` element is wrapped in one.
+pub fn parse_html(text: &str, span: Span) -> Value {
+    let mut open = vec![OpenElement::new("", Record::new())];
+    let close = |open: &mut Vec<OpenElement>| {
+        if let Some(element) = open.pop()
+            && let Some(parent) = open.last_mut()
+        {
+            parent.content.push(element.into_value(span));
+        }
+    };
+    let mut rest = text;
+    while !rest.is_empty() {
+        if let Some(after) = rest.strip_prefix("<!--") {
+            let (comment, next) = after.split_once("-->").unwrap_or((after, ""));
+            if let Some(parent) = open.last_mut() {
+                parent
+                    .content
+                    .push(text_node(Some("!"), comment.to_string(), span));
+            }
+            rest = next;
+        } else if rest.starts_with("<!") || rest.starts_with("<?") {
+            // Doctypes and processing instructions aren't part of the DOM.
+            rest = rest.split_once('>').map_or("", |(_, next)| next);
+        } else if let Some(after) = rest.strip_prefix("</") {
+            let (name, next) = after.split_once('>').unwrap_or((after, ""));
+            let name = name.trim().to_ascii_lowercase();
+            if let Some(at) = open.iter().skip(1).rposition(|element| element.tag == name) {
+                while open.len() > at + 1 {
+                    close(&mut open);
+                }
+            }
+            rest = next;
+        } else if rest.starts_with('<') && rest[1..].starts_with(|c: char| c.is_ascii_alphabetic())
+        {
+            let (tag, attributes, self_closing, next) = read_tag(&rest[1..], span);
+            rest = next;
+            if SIBLING_CLOSED.contains(&tag.as_str())
+                && open.last().is_some_and(|element| element.tag == tag)
+            {
+                close(&mut open);
+            }
+            let mut element = OpenElement::new(&tag, attributes);
+            if RAW_TEXT_ELEMENTS.contains(&tag.as_str()) {
+                let end = rest.to_ascii_lowercase().find(&format!("</{tag}"));
+                let (raw, next) = rest.split_at(end.unwrap_or(rest.len()));
+                element
+                    .content
+                    .push(text_node(None, decode_entities(raw), span));
+                rest = next.split_once('>').map_or("", |(_, next)| next);
+            }
+            open.push(element);
+            if self_closing
+                || VOID_ELEMENTS.contains(&tag.as_str())
+                || RAW_TEXT_ELEMENTS.contains(&tag.as_str())
+            {
+                close(&mut open);
+            }
+        } else {
+            let first = rest.chars().next().map_or(0, char::len_utf8);
+            let end = rest[first..].find('<').map_or(rest.len(), |at| at + first);
+            if let Some(parent) = open.last_mut() {
+                parent
+                    .content
+                    .push(text_node(None, decode_entities(&rest[..end]), span));
+            }
+            rest = &rest[end..];
+        }
+    }
+    while open.len() > 1 {
+        close(&mut open);
+    }
+    let document = open
+        .pop()
+        .map(|document| document.content)
+        .unwrap_or_default();
+    let elements: Vec<&Value> = document
+        .iter()
+        .filter(|node| {
+            node.get_data_by_key("tag")
+                .is_some_and(|tag| !tag.is_nothing())
+        })
+        .collect();
+    match elements.as_slice() {
+        [root]
+            if root
+                .get_data_by_key("tag")
+                .is_some_and(|tag| tag.as_str().is_ok_and(|tag| tag == "html")) =>
+        {
+            (*root).clone()
+        }
+        _ => OpenElement {
+            content: document,
+            ..OpenElement::new("html", Record::new())
+        }
+        .into_value(span),
+    }
+}
+
+/// Reads the name and attributes of a start tag, after its `<`, returning
+/// them with whether the tag closes itself and the text after it.
+fn read_tag(text: &str, span: Span) -> (String, Record, bool, &str) {
+    let is_end = |c: char| c.is_whitespace() || c == '/' || c == '>';
+    let name_end = text.find(is_end).unwrap_or(text.len());
+    let tag = text[..name_end].to_ascii_lowercase();
+    let mut attributes = Record::new();
+    let mut rest = &text[name_end..];
+    loop {
+        rest = rest.trim_start();
+        if let Some(next) = rest.strip_prefix("/>") {
+            return (tag, attributes, true, next);
+        }
+        if let Some(next) = rest.strip_prefix('>') {
+            return (tag, attributes, false, next);
+        }
+        if rest.is_empty() {
+            return (tag, attributes, false, rest);
+        }
+        let name_end = rest
+            .find(|c: char| is_end(c) || c == '=')
+            .unwrap_or(rest.len())
+            .max(1);
+        let name = rest[..name_end].to_ascii_lowercase();
+        rest = rest[name_end..].trim_start();
+        let mut value = String::new();
+        if let Some(after) = rest.strip_prefix('=') {
+            let after = after.trim_start();
+            let (raw, next) = match after.chars().next() {
+                Some(quote @ ('"' | '\'')) => {
+                    after[1..].split_once(quote).unwrap_or((&after[1..], ""))
+                }
+                _ => after.split_at(
+                    after
+                        .find(|c: char| c.is_whitespace() || c == '>')
+                        .unwrap_or(after.len()),
+                ),
+            };
+            value = decode_entities(raw);
+            rest = next;
+        }
+        if name != "/" {
+            attributes.insert(name, Value::string(value, span));
+        }
+    }
+}
+
+/// Replaces the character references most pages use with their characters.
+fn decode_entities(text: &str) -> String {
+    if !text.contains('&') {
+        return text.to_string();
+    }
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&apos;", "'")
+        .replace("&nbsp;", "\u{a0}")
+        .replace("&amp;", "&")
+}
+
+/// The text of a text node with its whitespace collapsed, cut at
+/// [`TEXT_PREVIEW`] characters. Blank text, like the indentation between
+/// elements, has none.
+pub fn text_preview(text: &str) -> Option<String> {
+    let words: Vec<&str> = text.split_whitespace().collect();
+    if words.is_empty() {
+        return None;
+    }
+    let text = words.join(" ");
+    if text.chars().count() <= TEXT_PREVIEW {
+        return Some(text);
+    }
+    let cut: String = text.chars().take(TEXT_PREVIEW - 1).collect();
+    Some(format!("{}…", cut.trim_end()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nu_protocol::record;
+
+    fn element(tag: Option<&str>, attributes: &[(&str, &str)], content: Value) -> Value {
+        let mut record = record! {
+            "tag" => tag.map_or_else(Value::test_nothing, Value::test_string),
+            "attributes" => Value::test_nothing(),
+            "content" => content,
+        };
+        if tag.is_some() {
+            let attributes = attributes
+                .iter()
+                .map(|(name, value)| (name.to_string(), Value::test_string(*value)))
+                .collect();
+            record.insert("attributes", Value::test_record(attributes));
+        }
+        Value::test_record(record)
+    }
+
+    fn texts(item: &StringItem) -> Vec<String> {
+        item.children.iter().map(|c| c.text.clone()).collect()
+    }
+
+    #[test]
+    fn test_xml_elements_are_branches() {
+        let text = |text: &str| element(None, &[], Value::test_string(text));
+        let document = element(
+            Some("project"),
+            &[("xmlns", "http://maven.apache.org/POM/4.0.0")],
+            Value::test_list(vec![
+                text("\n  "),
+                element(Some("!"), &[], Value::test_string(" build ")),
+                element(Some("version"), &[], Value::test_list(vec![text(" 1.0 ")])),
+                element(Some("modules"), &[], Value::test_list(vec![])),
+            ]),
+        );
+        assert!(is_xml(&document));
+        assert!(!is_xml(&Value::test_record(record! {
+            "tag" => Value::test_string("a"),
+        })));
+
+        let tree = xml_tree(&document, "pom.xml");
+        assert_eq!(texts(&tree), vec!["project"]);
+        assert_eq!(
+            texts(&tree.children[0]),
+            vec![
+                "@xmlns = http://maven.apache.org/POM/4.0.0",
+                "<!-- build -->",
+                "version",
+                "modules"
+            ]
+        );
+        assert_eq!(texts(&tree.children[0].children[2]), vec!["\"1.0\""]);
+    }
+
+    #[test]
+    fn test_html_is_drawn_as_a_dom() {
+        let page = r#"<!DOCTYPE html>
+<html><head><meta charset=utf-8><title>A &amp; B</title></head>
+<body><div id="main" class='content wide'><p>One<br>two<p>Three</i></div>
+<script>if (a < b) {}</script>"#;
+        assert!(is_html(page));
+        assert!(!is_html("<project/>"));
+
+        let tree = xml_tree(&parse_html(page, Span::test_data()), "page");
+        let html = &tree.children[0];
+        assert_eq!(texts(html), vec!["head", "body"]);
+        assert_eq!(texts(&html.children[0]), vec!["meta", "title"]);
+        assert_eq!(texts(&html.children[0].children[1]), vec!["\"A & B\""]);
+        let body = &html.children[1];
+        assert_eq!(texts(body), vec!["div#main.content.wide", "script"]);
+        assert_eq!(texts(&body.children[0]), vec!["p", "p"]);
+        assert_eq!(
+            texts(&body.children[0].children[0]),
+            vec!["\"One\"", "br", "\"two\""]
+        );
+        assert_eq!(texts(&body.children[1]), vec!["\"if (a < b) {}\""]);
+
+        // Fragments are wrapped in an html element.
+        let tree = xml_tree(&parse_html("<b>x</b> y", Span::test_data()), "fragment");
+        assert_eq!(texts(&tree.children[0]), vec!["b", "\"y\""]);
+
+        // Text may start with characters longer than a byte.
+        let tree = xml_tree(
+            &parse_html("<body>été</body>日本", Span::test_data()),
+            "page",
+        );
+        assert_eq!(texts(&tree.children[0]), vec!["body", "\"日本\""]);
+        assert_eq!(texts(&tree.children[0].children[0]), vec!["\"été\""]);
+    }
+
+    #[test]
+    fn test_text_preview_collapses_and_cuts_text() {
+        assert_eq!(text_preview(" \n\t "), None);
+        assert_eq!(text_preview("a\n   b"), Some("a b".to_string()));
+        let long = "word ".repeat(20);
+        let preview = text_preview(&long).unwrap();
+        assert_eq!(preview.chars().count(), TEXT_PREVIEW);
+        assert!(preview.ends_with("word…"));
+    }
+}
@@ -0,0 +1,416 @@
+//! Implements `tree from-paths`, which builds trees from lists of paths, as in
+//! `git ls-files | lines | tree from-paths` or `^fd . | tree --from-paths`,
+//! without touching the filesystem. Rows of `ls`, as in
+//! `ls **/* | tree --from-paths`, also give the type and size of their path.
+//!
+//! The input is consumed as it streams in. While the top-level names arrive
+//! in sorted order, as from `git ls-files`, each top-level entry is drawn as
+//! soon as a path after it arrives, so large listings start printing right
+//! away. Once a top-level name arrives out of order, as from `fd`, the rest
+//! of the paths are merged until the input ends, so no entry is split.
+//! Paths are merged and sorted within a top-level entry.
+
+// The byte stream handed back to Nushell carries `ShellError`s.
+#![allow(clippy::result_large_err)]
+
+use crate::view::{BranchStyle, Connectors, Summary, Totals};
+use crate::{TreePlugin, config, utils};
+use nu_plugin::{EngineInterface, EvaluatedCall, PluginCommand};
+use nu_protocol::{
+    ByteStream, ByteStreamType, Category, Example, LabeledError, PipelineData, ShellError,
+    Signature, SyntaxShape, Type, Value,
+};
+use std::collections::BTreeMap;
+use std::path::{Component, Path};
+
+pub struct TreeFromPaths;
+
+impl PluginCommand for TreeFromPaths {
+    type Plugin = TreePlugin;
+
+    fn name(&self) -> &str {
+        "tree from-paths"
+    }
+
+    fn signature(&self) -> Signature {
+        Signature::build(self.name())
+            .input_output_types(vec![
+                (Type::list(Type::String), Type::String),
+                (Type::table(), Type::String),
+                (Type::String, Type::String),
+            ])
+            .named(
+                "charset",
+                SyntaxShape::String,
+                "the characters used to draw the branches: utf8 (default) or ascii",
+                None,
+            )
+            .named(
+                "indent",
+                SyntaxShape::Int,
+                "the width of each level of the tree (default 4)",
+                None,
+            )
+            .category(Category::Experimental)
+    }
+
+    fn description(&self) -> &str {
+        "Draw the tree of a list of paths without reading the filesystem."
+    }
+
+    fn examples(&self) -> Vec<Example<'_>> {
+        vec![
+            Example {
+                example: "git ls-files | lines | tree from-paths",
+                description: "Draw the files tracked by git",
+                result: None,
+            },
+            Example {
+                example: "^fd --type file | tree from-paths --charset ascii",
+                description: "Draw the output of an external command with ASCII branches",
+                result: None,
+            },
+        ]
+    }
+
+    fn run(
+        &self,
+        _plugin: &TreePlugin,
+        engine: &EngineInterface,
+        call: &EvaluatedCall,
+        input: PipelineData,
+    ) -> Result<PipelineData, LabeledError> {
+        stream(engine, call, input, config::branch_style(engine, call)?)
+    }
+}
+
+/// Draws the tree of the input paths as a string stream; shared with
+/// `tree --from-paths`.
+pub fn stream(
+    engine: &EngineInterface,
+    call: &EvaluatedCall,
+    input: PipelineData,
+    branches: BranchStyle,
+) -> Result<PipelineData, LabeledError> {
+    let stream = ByteStream::from_result_iter(
+        render(input_paths(input)?, branches, config::summary(engine)?),
+        call.head,
+        engine.signals().clone(),
+        ByteStreamType::String,
+    );
+    Ok(PipelineData::ByteStream(stream, None))
+}
+
+/// What a row of `ls` tells about its path.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Info {
+    /// The `type` column: `file`, `dir` or `symlink`.
+    pub kind: Option<String>,
+    pub size: Option<u64>,
+}
+
+/// An entry named by the input paths. Entries with children, or that `ls`
+/// says are directories, are directories.
+#[derive(Default)]
+struct Node {
+    children: BTreeMap<String, Node>,
+    info: Info,
+}
+
+/// Draws a tree from paths pushed one at a time.
+pub struct PathTree {
+    connectors: Connectors,
+    summary: Summary,
+    /// The top-level entry still collecting paths.
+    current: Option<(String, Node)>,
+    /// The top-level entries merged until the input ends, once the input was
+    /// found out of order.
+    unsorted: Option<BTreeMap<String, Node>>,
+    dirs: usize,
+    files: usize,
+    size: u64,
+}
+
+impl PathTree {
+    pub fn new(branches: &BranchStyle, summary: Summary) -> Self {
+        Self {
+            connectors: Connectors::new(&branches.characters, branches.indent),
+            summary,
+            current: None,
+            unsorted: None,
+            dirs: 0,
+            files: 0,
+            size: 0,
+        }
+    }
+
+    /// Adds a path along with what `ls` tells about it, returning the lines of
+    /// the previous top-level entry once the path shows that entry is complete.
+    pub fn push(&mut self, path: &str, info: Info) -> String {
+        let mut components = components(path);
+        let Some(top) = components.next() else {
+            return String::new();
+        };
+
+        let mut out = String::new();
+        if self.unsorted.is_none()
+            && let Some((name, _)) = &self.current
+            && *name != top
+        {
+            let (name, node) = self.current.take().unwrap_or_default();
+            if name < top {
+                self.write_node(&name, &node, "", false, &mut out);
+            } else {
+                self.unsorted = Some(BTreeMap::from([(name, node)]));
+            }
+        }
+        let mut node = match &mut self.unsorted {
+            Some(entries) => entries.entry(top).or_default(),
+            None => &mut self.current.get_or_insert_with(|| (top, Node::default())).1,
+        };
+        for component in components {
+            node = node.children.entry(component).or_default();
+        }
+        if info != Info::default() {
+            node.info = info;
+        }
+        out
+    }
+
+    /// Draws the last top-level entry followed by the summary line.
+    pub fn finish(mut self) -> String {
+        let mut out = String::new();
+        if let Some((name, node)) = self.current.take() {
+            self.write_node(&name, &node, "", true, &mut out);
+        }
+        let entries = self.unsorted.take().unwrap_or_default();
+        let count = entries.len();
+        for (i, (name, node)) in entries.iter().enumerate() {
+            self.write_node(name, node, "", i + 1 == count, &mut out);
+        }
+        // Only rows of `ls` carry sizes; plain paths add up to zero.
+        let totals = Totals {
+            dirs: self.dirs,
+            files: self.files,
+            size: self.size,
+        };
+        if let Some(summary) = self.summary.render(totals) {
+            out.push_str(&format!("\n{summary}"));
+        }
+        out
+    }
+
+    fn write_node(&mut self, name: &str, node: &Node, prefix: &str, last: bool, out: &mut String) {
+        let connector = if last {
+            &self.connectors.last
+        } else {
+            &self.connectors.branch
+        };
+        let is_dir = !node.children.is_empty() || node.info.kind.as_deref() == Some("dir");
+        let decoration = match (&node.info.kind, node.info.size) {
+            (Some(kind), _) if kind != "file" && kind != "dir" => format!(" [{kind}]"),
+            (_, Some(size)) if !is_dir => format!(" ({})", utils::format_size(size)),
+            _ => String::new(),
+        };
+        out.push_str(&format!(
+            "{prefix}{connector}{}{decoration}\n",
+            utils::escape_control(name)
+        ));
+        if !is_dir {
+            self.files += 1;
+            self.size += node.info.size.unwrap_or(0);
+            return;
+        }
+        self.dirs += 1;
+
+        let continuation = if last {
+            &self.connectors.empty
+        } else {
+            &self.connectors.vertical
+        };
+        let child_prefix = format!("{prefix}{continuation}");
+        let count = node.children.len();
+        for (i, (child_name, child)) in node.children.iter().enumerate() {
+            self.write_node(child_name, child, &child_prefix, i + 1 == count, out);
+        }
+    }
+}
+
+/// Splits a path into the names of its components, ignoring `.` components.
+fn components(path: &str) -> impl Iterator<Item = String> + '_ {
+    Path::new(path.trim_end())
+        .components()
+        .filter_map(|component| match component {
+            Component::CurDir => None,
+            Component::RootDir => Some("/".to_string()),
+            other => Some(other.as_os_str().to_string_lossy().to_string()),
+        })
+}
+
+/// A path of the input, with what its `ls` row tells about it.
+type PathEntry = (String, Info);
+
+/// Returns the paths in the input without collecting it: the items of a list,
+/// the lines of a string or byte stream, or the `name` column of a table.
+pub fn input_paths(
+    input: PipelineData,
+) -> Result<Box<dyn Iterator<Item = Result<PathEntry, ShellError>> + Send>, ShellError> {
+    let plain = |line: Result<String, ShellError>| line.map(|line| (line, Info::default()));
+    Ok(match input {
+        PipelineData::Empty => Box::new(std::iter::empty()),
+        PipelineData::ListStream(stream, _) => Box::new(stream.into_iter().map(path_of)),
+        PipelineData::ByteStream(stream, _) => match stream.lines() {
+            Some(lines) => Box::new(lines.map(plain)),
+            None => Box::new(std::iter::empty()),
+        },
+        PipelineData::Value(Value::List { vals, .. }, _) => Box::new(vals.into_iter().map(path_of)),
+        PipelineData::Value(value, _) => {
+            let text = value.coerce_into_string()?;
+            let lines: Vec<String> = text.lines().map(str::to_string).collect();
+            Box::new(lines.into_iter().map(Ok).map(plain))
+        }
+    })
+}
+
+fn path_of(value: Value) -> Result<PathEntry, ShellError> {
+    match value {
+        Value::Record { ref val, .. } if val.contains("name") => {
+            let path = val
+                .get("name")
+                .cloned()
+                .unwrap_or_default()
+                .coerce_into_string()?;
+            let info = Info {
+                kind: val
+                    .get("type")
+                    .and_then(|kind| kind.as_str().ok())
+                    .map(str::to_string),
+                size: val.get("size").and_then(|size| match size {
+                    Value::Filesize { val, .. } => u64::try_from(val.get()).ok(),
+                    Value::Int { val, .. } => u64::try_from(*val).ok(),
+                    _ => None,
+                }),
+            };
+            Ok((path, info))
+        }
+        value => Ok((value.coerce_into_string()?, Info::default())),
+    }
+}
+
+/// Draws the tree for the paths as they arrive, starting with a `.` root line.
+pub fn render(
+    paths: impl Iterator<Item = Result<PathEntry, ShellError>>,
+    branches: BranchStyle,
+    summary: Summary,
+) -> impl Iterator<Item = Result<String, ShellError>> {
+    let mut tree = Some(PathTree::new(&branches, summary));
+    let mut paths = paths.fuse();
+    std::iter::once(Ok(".\n".to_string())).chain(std::iter::from_fn(move || {
+        let tree_ref = tree.as_mut()?;
+        loop {
+            match paths.next() {
+                Some(Ok((path, info))) => {
+                    let out = tree_ref.push(&path, info);
+                    if !out.is_empty() {
+                        return Some(Ok(out));
+                    }
+                }
+                Some(Err(err)) => {
+                    tree = None;
+                    return Some(Err(err));
+                }
+                None => return tree.take().map(|tree| Ok(tree.finish())),
+            }
+        }
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_streams_top_level_entries() {
+        let paths = ["Cargo.toml", "README.md", "src/main.rs", "src/view.rs"];
+        let chunks: Vec<String> = render(
+            paths.iter().map(|p| Ok((p.to_string(), Info::default()))),
+            BranchStyle::default(),
+            Summary::default(),
+        )
+        .collect::<Result<_, _>>()
+        .unwrap();
+
+        assert_eq!(
+            chunks,
+            vec![
+                ".\n",
+                "├── Cargo.toml\n",
+                "├── README.md\n",
+                "└── src\n    ├── main.rs\n    └── view.rs\n\n1 directories, 4 files",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_unsorted_paths_are_merged() {
+        let mut tree = PathTree::new(&BranchStyle::default(), Summary::None);
+        assert_eq!(tree.push("src/a", Info::default()), "");
+        assert_eq!(tree.push("Cargo.toml", Info::default()), "");
+        assert_eq!(tree.push("src/b", Info::default()), "");
+        assert_eq!(
+            tree.finish(),
+            "├── Cargo.toml\n└── src\n    ├── a\n    └── b\n"
+        );
+    }
+
+    #[test]
+    fn test_push_merges_paths_within_an_entry() {
+        let mut tree = PathTree::new(&BranchStyle::default(), Summary::default());
+        assert_eq!(tree.push("./a/z", Info::default()), "");
+        assert_eq!(tree.push("a/b/c", Info::default()), "");
+        assert_eq!(tree.push("a/b", Info::default()), "");
+        assert_eq!(
+            tree.finish(),
+            "└── a\n    ├── b\n    │   └── c\n    └── z\n\n2 directories, 2 files"
+        );
+    }
+
+    #[test]
+    fn test_ls_rows_show_type_and_size() {
+        let info = |kind: &str, size: u64| Info {
+            kind: Some(kind.to_string()),
+            size: Some(size),
+        };
+        let template = "{dirs} dirs / {files} files / {size}".parse().unwrap();
+        let mut tree = PathTree::new(&BranchStyle::default(), template);
+        let mut out = tree.push("empty", info("dir", 4096));
+        out += &tree.push("link", info("symlink", 0));
+        out += &tree.push("src", info("dir", 4096));
+        out += &tree.push("src/main.rs", info("file", 2048));
+        out += &tree.finish();
+
+        assert_eq!(
+            out,
+            "├── empty\n├── link [symlink]\n└── src\n    └── main.rs (2.0 KiB)\n\n2 dirs / 2 files / 2.0 KiB"
+        );
+    }
+
+    #[test]
+    fn test_finish_uses_the_summary() {
+        let mut gnu = PathTree::new(&BranchStyle::default(), Summary::Gnu);
+        gnu.push("a/b", Info::default());
+        assert_eq!(gnu.finish(), "└── a\n    └── b\n\n1 directory, 1 file");
+
+        let template = "{dirs} dirs / {files} files / {size}".parse().unwrap();
+        let mut custom = PathTree::new(&BranchStyle::default(), template);
+        custom.push("a/b", Info::default());
+        assert_eq!(
+            custom.finish(),
+            "└── a\n    └── b\n\n1 dirs / 1 files / 0 B"
+        );
+
+        let mut none = PathTree::new(&BranchStyle::default(), Summary::None);
+        none.push("a", Info::default());
+        assert_eq!(none.finish(), "└── a\n");
+    }
+}
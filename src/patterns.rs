@@ -0,0 +1,182 @@
+//! Matches the `--pattern` (`-P`) and `--ignore` (`-I`) filters of path mode.
+//!
+//! Patterns follow `.gitignore` rules: a pattern without a `/`, like `*.md`,
+//! matches the name of an entry at any depth, while a pattern with a `/`, like
+//! `/src/**` or `docs/*.md`, is anchored to the root of the tree.
+//!
+//! A glob given as the path, like `src/**/*.rs`, is drawn from the directory
+//! before its first wildcard, keeping only its matches and the directories
+//! above them.
+
+use ignore::DirEntry;
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use std::path::Path;
+
+/// The compiled include and ignore patterns.
+#[derive(Clone, Debug, Default)]
+pub struct Patterns {
+    /// Files must match one of these to be shown. Directories are always shown.
+    include: Option<Gitignore>,
+    /// Entries matching one of these are hidden, along with everything below them.
+    exclude: Option<Gitignore>,
+    /// The rest of a glob given as the path, anchored to the root.
+    glob: Option<Gitignore>,
+}
+
+impl Patterns {
+    pub fn new(root: &Path, include: &[String], exclude: &[String]) -> Result<Self, String> {
+        Ok(Patterns {
+            include: compile(root, include)?,
+            exclude: compile(root, exclude)?,
+            glob: None,
+        })
+    }
+
+    /// Adds the glob pattern returned by [`split_glob`], rooted at `root`.
+    pub fn with_glob(self, root: &Path, glob: &str) -> Result<Self, String> {
+        Ok(Patterns {
+            glob: compile(root, &[glob.to_string()])?,
+            ..self
+        })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.include.is_none() && self.exclude.is_none() && self.glob.is_none()
+    }
+
+    pub fn has_glob(&self) -> bool {
+        self.glob.is_some()
+    }
+
+    /// Whether an entry is matched by the glob itself, rather than being a
+    /// directory above its matches.
+    pub fn matches_glob(&self, path: &Path, is_dir: bool) -> bool {
+        self.glob
+            .as_ref()
+            .is_none_or(|glob| glob.matched(path, is_dir).is_ignore())
+    }
+
+    /// Whether the walker should visit an entry.
+    pub fn allows(&self, entry: &DirEntry) -> bool {
+        if entry.depth() == 0 {
+            return true;
+        }
+        let is_dir = entry.file_type().is_some_and(|ft| ft.is_dir());
+        self.allows_path(entry.path(), is_dir)
+    }
+
+    /// Whether the walker should visit the entry at `path`, which isn't the root.
+    pub fn allows_path(&self, path: &Path, is_dir: bool) -> bool {
+        if let Some(exclude) = &self.exclude
+            && exclude.matched(path, is_dir).is_ignore()
+        {
+            return false;
+        }
+        if is_dir {
+            return true;
+        }
+        let included = self.include.as_ref().is_none_or(|include| {
+            include
+                .matched_path_or_any_parents(path, is_dir)
+                .is_ignore()
+        });
+        included && self.matches_glob(path, is_dir)
+    }
+}
+
+/// Splits a path with wildcards, like `src/**/*.rs`, into the directory before
+/// its first wildcard and the rest of it as a pattern anchored to that
+/// directory. Returns `None` for a path without wildcards.
+pub fn split_glob(path: &str) -> Option<(String, String)> {
+    let is_wild = |part: &str| part.contains(['*', '?', '[', '{']);
+    let parts: Vec<&str> = path.split(['/', std::path::MAIN_SEPARATOR]).collect();
+    let wild = parts.iter().position(|part| is_wild(part))?;
+    let base = match parts[..wild].join("/") {
+        base if base.is_empty() && path.starts_with(['/', std::path::MAIN_SEPARATOR]) => {
+            "/".to_string()
+        }
+        base if base.is_empty() => ".".to_string(),
+        base => base,
+    };
+    Some((base, format!("/{}", parts[wild..].join("/"))))
+}
+
+fn compile(root: &Path, patterns: &[String]) -> Result<Option<Gitignore>, String> {
+    if patterns.is_empty() {
+        return Ok(None);
+    }
+    let mut builder = GitignoreBuilder::new(root);
+    for pattern in patterns {
+        builder
+            .add_line(None, pattern)
+            .map_err(|err| format!("invalid pattern '{pattern}': {err}"))?;
+    }
+    builder.build().map(Some).map_err(|err| err.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn patterns(include: &[&str], exclude: &[&str]) -> Patterns {
+        let owned = |patterns: &[&str]| patterns.iter().map(|p| p.to_string()).collect::<Vec<_>>();
+        Patterns::new(Path::new("root"), &owned(include), &owned(exclude)).unwrap()
+    }
+
+    #[test]
+    fn test_unanchored_patterns_match_names_at_any_depth() {
+        let patterns = patterns(&["*.md"], &["target"]);
+
+        assert!(patterns.allows_path(Path::new("root/README.md"), false));
+        assert!(patterns.allows_path(Path::new("root/docs/guide.md"), false));
+        assert!(!patterns.allows_path(Path::new("root/src/main.rs"), false));
+        assert!(patterns.allows_path(Path::new("root/src"), true));
+        assert!(!patterns.allows_path(Path::new("root/target"), true));
+        assert!(!patterns.allows_path(Path::new("root/crates/a/target"), true));
+    }
+
+    #[test]
+    fn test_anchored_patterns_are_relative_to_the_root() {
+        let patterns = patterns(&["/src/**", "docs/*.md"], &["/docs/drafts"]);
+
+        assert!(patterns.allows_path(Path::new("root/src/main.rs"), false));
+        assert!(patterns.allows_path(Path::new("root/src/bin/cli.rs"), false));
+        assert!(!patterns.allows_path(Path::new("root/crates/a/src/lib.rs"), false));
+        assert!(patterns.allows_path(Path::new("root/docs/guide.md"), false));
+        assert!(!patterns.allows_path(Path::new("root/site/docs/guide.md"), false));
+        assert!(!patterns.allows_path(Path::new("root/docs/drafts"), true));
+        assert!(patterns.allows_path(Path::new("root/site/docs/drafts"), true));
+    }
+
+    #[test]
+    fn test_globs_are_split_at_the_first_wildcard() {
+        assert_eq!(
+            split_glob("src/**/*.rs"),
+            Some(("src".to_string(), "/**/*.rs".to_string()))
+        );
+        assert_eq!(
+            split_glob("*.md"),
+            Some((".".to_string(), "/*.md".to_string()))
+        );
+        assert_eq!(
+            split_glob("/srv/*/logs"),
+            Some(("/srv".to_string(), "/*/logs".to_string()))
+        );
+        assert_eq!(split_glob("src/main.rs"), None);
+
+        let patterns = Patterns::new(Path::new("root"), &[], &[])
+            .unwrap()
+            .with_glob(Path::new("root"), "/**/*.rs")
+            .unwrap();
+        assert!(patterns.allows_path(Path::new("root/main.rs"), false));
+        assert!(patterns.allows_path(Path::new("root/src/bin/cli.rs"), false));
+        assert!(!patterns.allows_path(Path::new("root/README.md"), false));
+        assert!(patterns.allows_path(Path::new("root/docs"), true));
+        assert!(!patterns.matches_glob(Path::new("root/docs"), true));
+    }
+
+    #[test]
+    fn test_invalid_patterns_are_reported() {
+        assert!(Patterns::new(Path::new("root"), &["a[".to_string()], &[]).is_err());
+    }
+}
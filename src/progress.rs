@@ -0,0 +1,106 @@
+//! Shows that a long walk is still going, with a line on stderr that is
+//! rewritten as the walk goes and cleared before the tree is printed.
+//!
+//! Nothing is shown for walks that finish quickly, or when stderr isn't a
+//! terminal, so logs and redirected output stay clean.
+
+use std::io::{IsTerminal, Write};
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+/// How long a walk runs before its progress is shown.
+const SHOW_AFTER: Duration = Duration::from_secs(1);
+/// How often the progress line is rewritten.
+const REFRESH: Duration = Duration::from_millis(100);
+/// The clock is only read once per this many entries.
+const CHECK_EVERY: u64 = 64;
+
+pub struct Progress {
+    enabled: bool,
+    started: Instant,
+    last_shown: Option<Instant>,
+    entries: u64,
+}
+
+impl Progress {
+    /// Starts timing a walk. A disabled progress never prints anything.
+    pub fn new(enabled: bool) -> Self {
+        Progress {
+            enabled: enabled && std::io::stderr().is_terminal(),
+            started: Instant::now(),
+            last_shown: None,
+            entries: 0,
+        }
+    }
+
+    /// Counts an entry, showing the directory being walked when it is time to.
+    pub fn tick(&mut self, path: &Path) {
+        self.entries += 1;
+        if !self.enabled || !self.entries.is_multiple_of(CHECK_EVERY) {
+            return;
+        }
+        let now = Instant::now();
+        let due = match self.last_shown {
+            Some(shown) => now.duration_since(shown) >= REFRESH,
+            None => now.duration_since(self.started) >= SHOW_AFTER,
+        };
+        if !due {
+            return;
+        }
+        self.last_shown = Some(now);
+        let dir = path.parent().unwrap_or(path).to_string_lossy();
+        let width = crossterm::terminal::size().map_or(80, |(columns, _)| columns as usize);
+        let mut err = std::io::stderr().lock();
+        _ = write!(err, "\r\x1b[2K{}", line(self.entries, &dir, width));
+        _ = err.flush();
+    }
+
+    /// Removes the progress line, if it was shown.
+    pub fn clear(&mut self) {
+        if self.last_shown.take().is_some() {
+            let mut err = std::io::stderr().lock();
+            _ = write!(err, "\r\x1b[2K");
+            _ = err.flush();
+        }
+    }
+}
+
+impl Drop for Progress {
+    fn drop(&mut self) {
+        self.clear();
+    }
+}
+
+/// The progress line, fitted to `width` columns so it never wraps, which
+/// would leave lines behind when it is cleared. Long paths lose their start.
+fn line(entries: u64, dir: &str, width: usize) -> String {
+    let prefix = format!("Walking… {entries} entries, in ");
+    let room = width.saturating_sub(prefix.chars().count() + 1);
+    let count = dir.chars().count();
+    if count <= room {
+        return format!("{prefix}{dir}");
+    }
+    if room < 2 {
+        return prefix.trim_end_matches(", in ").to_string();
+    }
+    let tail: String = dir.chars().skip(count - (room - 1)).collect();
+    format!("{prefix}…{tail}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_line_fits_the_terminal() {
+        assert_eq!(
+            line(12, "/srv/share", 80),
+            "Walking… 12 entries, in /srv/share"
+        );
+        assert_eq!(
+            line(12, "/srv/share/projects", 36),
+            "Walking… 12 entries, in …e/projects"
+        );
+        assert_eq!(line(12, "/srv/share", 20), "Walking… 12 entries");
+    }
+}
@@ -0,0 +1,435 @@
+//! Lets path mode render trees from sources other than the local filesystem.
+//!
+//! A provider only has to list the direct children of a directory. Walking is
+//! shared by every provider, and the entries it finds are filtered, capped and
+//! drawn like those of the local view, which keeps its own parallel walk.
+//! Remote providers are opt-in cargo features because of the dependencies
+//! they pull in:
+//!
+//! - `s3`: `s3://bucket/prefix`, using the usual `AWS_*` environment variables.
+//! - `sftp`: `sftp://user@host:port/path`, authenticating through the SSH agent.
+//!
+//! `file:///path` lists a local directory the same way, without the git and
+//! ignore file support of the local view.
+
+use std::fs;
+
+/// An entry listed by a provider.
+pub struct ProviderEntry {
+    pub name: String,
+    pub is_dir: bool,
+    pub size: Option<u64>,
+}
+
+/// A source of directory listings.
+pub trait Provider {
+    /// Lists the direct children of `path`, a `/`-separated path inside the provider.
+    fn read_dir(&self, path: &str) -> anyhow::Result<Vec<ProviderEntry>>;
+}
+
+/// Lists directories of the local filesystem, for `file://` paths.
+pub struct LocalProvider;
+
+impl Provider for LocalProvider {
+    fn read_dir(&self, path: &str) -> anyhow::Result<Vec<ProviderEntry>> {
+        fs::read_dir(path)?
+            .map(|entry| {
+                let entry = entry?;
+                let metadata = entry.metadata()?;
+                Ok(ProviderEntry {
+                    name: entry.file_name().to_string_lossy().to_string(),
+                    is_dir: metadata.is_dir(),
+                    size: Some(metadata.len()),
+                })
+            })
+            .collect()
+    }
+}
+
+/// A provider together with the path inside it where the tree starts.
+pub struct Source {
+    pub provider: Box<dyn Provider>,
+    pub root: String,
+}
+
+/// A listed entry and its depth below the root, which is at depth 0.
+pub struct WalkEntry {
+    pub depth: usize,
+    /// The `/`-separated path of the entry below the root.
+    pub path: String,
+    pub entry: ProviderEntry,
+    /// Why the directory couldn't be listed, if it couldn't.
+    pub error: Option<String>,
+}
+
+/// How a source is walked.
+pub struct WalkOptions<'a> {
+    pub max_depth: Option<usize>,
+    /// Whether hidden entries are listed.
+    pub all: bool,
+    /// Whether an entry is kept. The contents of a directory that isn't kept
+    /// aren't listed.
+    pub keep: &'a dyn Fn(&WalkEntry) -> bool,
+    /// Whether to stop walking, such as when Nushell is interrupted.
+    pub stop: &'a dyn Fn() -> bool,
+}
+
+/// Whether a path names a remote source, like `s3://bucket/prefix`, rather
+/// than a local path.
+pub fn is_url(path: &str) -> bool {
+    path.contains("://")
+}
+
+/// Returns the source for a URL such as `s3://bucket/prefix`, or `None` for a local path.
+pub fn from_url(url: &str) -> anyhow::Result<Option<Source>> {
+    let Some((scheme, rest)) = url.split_once("://") else {
+        return Ok(None);
+    };
+    match scheme {
+        "file" => Ok(Some(Source {
+            provider: Box::new(LocalProvider),
+            root: if rest.is_empty() { "." } else { rest }.to_string(),
+        })),
+        "s3" => s3::open(rest).map(Some),
+        "sftp" => sftp::open(rest).map(Some),
+        _ => anyhow::bail!("Unsupported path scheme '{scheme}://'"),
+    }
+}
+
+/// Walks the source depth-first, listing each directory's entries sorted by name.
+///
+/// A directory that can't be listed keeps the error, like in the local walk;
+/// the errors returned along with the entries are about the root.
+pub fn walk(source: &Source, options: &WalkOptions) -> (Vec<WalkEntry>, Vec<String>) {
+    let mut entries = Vec::new();
+    let mut errors = Vec::new();
+    walk_dir(
+        source,
+        options,
+        &source.root,
+        "",
+        1,
+        &mut entries,
+        &mut errors,
+    );
+    (entries, errors)
+}
+
+fn walk_dir(
+    source: &Source,
+    options: &WalkOptions,
+    path: &str,
+    below_root: &str,
+    depth: usize,
+    entries: &mut Vec<WalkEntry>,
+    errors: &mut Vec<String>,
+) {
+    if options.max_depth.is_some_and(|max| depth > max) || (options.stop)() {
+        return;
+    }
+    let mut children = match source.provider.read_dir(path) {
+        Ok(children) => children,
+        Err(err) => {
+            // The directory was pushed just before walking it.
+            match entries.last_mut() {
+                Some(dir) if depth > 1 => dir.error = Some(err.to_string()),
+                _ => errors.push(err.to_string()),
+            }
+            return;
+        }
+    };
+    children.sort_by(|a, b| a.name.cmp(&b.name));
+
+    for child in children {
+        if !options.all && child.name.starts_with('.') {
+            continue;
+        }
+        let child_path = join(path, &child.name);
+        let walk_entry = WalkEntry {
+            depth,
+            path: join(below_root, &child.name),
+            entry: child,
+            error: None,
+        };
+        if !(options.keep)(&walk_entry) {
+            continue;
+        }
+        let is_dir = walk_entry.entry.is_dir;
+        let below = walk_entry.path.clone();
+        entries.push(walk_entry);
+        if is_dir {
+            walk_dir(
+                source,
+                options,
+                &child_path,
+                &below,
+                depth + 1,
+                entries,
+                errors,
+            );
+        }
+    }
+}
+
+fn join(dir: &str, name: &str) -> String {
+    if dir.is_empty() {
+        name.to_string()
+    } else {
+        format!("{}/{}", dir.trim_end_matches('/'), name)
+    }
+}
+
+#[cfg(feature = "s3")]
+mod s3 {
+    use super::{Provider, ProviderEntry, Source};
+    use object_store::{ObjectStore, aws::AmazonS3Builder, path::Path};
+
+    pub struct S3Provider {
+        store: object_store::aws::AmazonS3,
+        runtime: tokio::runtime::Runtime,
+    }
+
+    impl Provider for S3Provider {
+        fn read_dir(&self, path: &str) -> anyhow::Result<Vec<ProviderEntry>> {
+            let prefix = (!path.is_empty()).then(|| Path::from(path));
+            let listing = self
+                .runtime
+                .block_on(self.store.list_with_delimiter(prefix.as_ref()))?;
+
+            let dirs = listing.common_prefixes.into_iter().map(|p| ProviderEntry {
+                name: p.filename().unwrap_or_default().to_string(),
+                is_dir: true,
+                size: None,
+            });
+            let files = listing.objects.into_iter().map(|o| ProviderEntry {
+                name: o.location.filename().unwrap_or_default().to_string(),
+                is_dir: false,
+                size: Some(o.size),
+            });
+            Ok(dirs.chain(files).collect())
+        }
+    }
+
+    /// Opens `bucket/prefix`, taking credentials and region from the environment.
+    pub fn open(rest: &str) -> anyhow::Result<Source> {
+        let (bucket, prefix) = rest.split_once('/').unwrap_or((rest, ""));
+        let store = AmazonS3Builder::from_env()
+            .with_bucket_name(bucket)
+            .build()?;
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()?;
+        Ok(Source {
+            provider: Box::new(S3Provider { store, runtime }),
+            root: prefix.trim_matches('/').to_string(),
+        })
+    }
+}
+
+#[cfg(not(feature = "s3"))]
+mod s3 {
+    pub fn open(_rest: &str) -> anyhow::Result<super::Source> {
+        anyhow::bail!("s3:// paths require nu_plugin_tree to be built with the `s3` feature")
+    }
+}
+
+#[cfg(feature = "sftp")]
+mod sftp {
+    use super::{Provider, ProviderEntry, Source};
+    use ssh2::{Session, Sftp};
+    use std::{net::TcpStream, path::Path};
+
+    pub struct SftpProvider {
+        sftp: Sftp,
+        // The session has to outlive the SFTP channel.
+        _session: Session,
+    }
+
+    impl Provider for SftpProvider {
+        fn read_dir(&self, path: &str) -> anyhow::Result<Vec<ProviderEntry>> {
+            let listing = self.sftp.readdir(Path::new(path))?;
+            Ok(listing
+                .into_iter()
+                .filter_map(|(path, stat)| {
+                    let name = path.file_name()?.to_string_lossy().to_string();
+                    Some(ProviderEntry {
+                        name,
+                        is_dir: stat.is_dir(),
+                        size: stat.size,
+                    })
+                })
+                .collect())
+        }
+    }
+
+    /// Opens `user@host:port/path`, authenticating through the SSH agent.
+    pub fn open(rest: &str) -> anyhow::Result<Source> {
+        let (authority, path) = match rest.split_once('/') {
+            Some((authority, path)) => (authority, format!("/{path}")),
+            None => (rest, ".".to_string()),
+        };
+        let (user, host) = match authority.split_once('@') {
+            Some((user, host)) => (user.to_string(), host),
+            None => (std::env::var("USER").unwrap_or_default(), authority),
+        };
+        let (host, port) = match host.rsplit_once(':') {
+            Some((host, port)) => (host, port.parse()?),
+            None => (host, 22),
+        };
+
+        let mut session = Session::new()?;
+        session.set_tcp_stream(TcpStream::connect((host, port))?);
+        session.handshake()?;
+        session.userauth_agent(&user)?;
+        let sftp = session.sftp()?;
+
+        Ok(Source {
+            provider: Box::new(SftpProvider {
+                sftp,
+                _session: session,
+            }),
+            root: path,
+        })
+    }
+}
+
+#[cfg(not(feature = "sftp"))]
+mod sftp {
+    pub fn open(_rest: &str) -> anyhow::Result<super::Source> {
+        anyhow::bail!("sftp:// paths require nu_plugin_tree to be built with the `sftp` feature")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    /// Lists directories from a map of paths to their entries, where a name
+    /// ending with `/` is a directory.
+    struct MemoryProvider(HashMap<&'static str, Vec<&'static str>>);
+
+    impl Provider for MemoryProvider {
+        fn read_dir(&self, path: &str) -> anyhow::Result<Vec<ProviderEntry>> {
+            let Some(names) = self.0.get(path) else {
+                anyhow::bail!("no such directory: {path}");
+            };
+            Ok(names
+                .iter()
+                .map(|name| ProviderEntry {
+                    name: name.trim_end_matches('/').to_string(),
+                    is_dir: name.ends_with('/'),
+                    size: None,
+                })
+                .collect())
+        }
+    }
+
+    fn source(root: &str) -> Source {
+        Source {
+            provider: Box::new(MemoryProvider(HashMap::from([
+                ("root", vec!["src/", "b.txt", ".hidden", "locked/"]),
+                ("root/src", vec!["main.rs", "lib.rs"]),
+            ]))),
+            root: root.to_string(),
+        }
+    }
+
+    /// The paths and depths walked, with the error of each directory.
+    fn walked(source: &Source, options: &WalkOptions) -> (Vec<String>, Vec<String>) {
+        let (entries, errors) = walk(source, options);
+        let entries = entries
+            .into_iter()
+            .map(|walk_entry| match walk_entry.error {
+                Some(_) => format!("{} {} [error]", walk_entry.depth, walk_entry.path),
+                None => format!("{} {}", walk_entry.depth, walk_entry.path),
+            })
+            .collect();
+        (entries, errors)
+    }
+
+    #[test]
+    fn test_from_url() {
+        assert!(from_url("/home/me").unwrap().is_none());
+        assert!(from_url("src/main.rs").unwrap().is_none());
+        assert!(from_url("ftp://example.com").is_err());
+        assert_eq!(from_url("file:///tmp").unwrap().unwrap().root, "/tmp");
+        assert_eq!(from_url("file://").unwrap().unwrap().root, ".");
+    }
+
+    #[test]
+    fn test_join() {
+        assert_eq!(join("", "a"), "a");
+        assert_eq!(join("root", "a"), "root/a");
+        assert_eq!(join("root/", "a"), "root/a");
+        assert_eq!(join("/", "a"), "/a");
+    }
+
+    #[test]
+    fn test_walk_dir() {
+        let keep_all = |_: &WalkEntry| true;
+        let never = || false;
+        let options = WalkOptions {
+            max_depth: None,
+            all: false,
+            keep: &keep_all,
+            stop: &never,
+        };
+        assert_eq!(
+            walked(&source("root"), &options),
+            (
+                vec![
+                    "1 b.txt".to_string(),
+                    "1 locked [error]".to_string(),
+                    "1 src".to_string(),
+                    "2 src/lib.rs".to_string(),
+                    "2 src/main.rs".to_string(),
+                ],
+                Vec::new()
+            )
+        );
+
+        let no_src = |walk_entry: &WalkEntry| walk_entry.path != "src";
+        let options = WalkOptions {
+            max_depth: Some(1),
+            all: true,
+            keep: &no_src,
+            stop: &never,
+        };
+        assert_eq!(
+            walked(&source("root"), &options).0,
+            ["1 .hidden", "1 b.txt", "1 locked"]
+        );
+
+        let (entries, errors) = walked(&source("missing"), &options);
+        assert!(entries.is_empty());
+        assert_eq!(errors, ["no such directory: missing"]);
+
+        let always = || true;
+        let options = WalkOptions {
+            stop: &always,
+            ..options
+        };
+        assert_eq!(walked(&source("root"), &options), (Vec::new(), Vec::new()));
+    }
+
+    #[test]
+    fn test_local_provider_lists_a_directory() {
+        let dir =
+            std::env::temp_dir().join(format!("nu_plugin_tree_provider_{}", std::process::id()));
+        _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join("sub")).unwrap();
+        fs::write(dir.join("a.txt"), "abc").unwrap();
+
+        let mut entries = LocalProvider.read_dir(dir.to_str().unwrap()).unwrap();
+        entries.sort_by(|a, b| a.name.cmp(&b.name));
+        let listed: Vec<_> = entries
+            .iter()
+            .map(|entry| (entry.name.as_str(), entry.is_dir))
+            .collect();
+        assert_eq!(listed, [("a.txt", false), ("sub", true)]);
+        assert_eq!(entries[0].size, Some(3));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
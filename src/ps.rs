@@ -0,0 +1,123 @@
+//! Implements `tree ps`, which shows the system processes as a hierarchy.
+//!
+//! The process list comes from Nushell's own `ps` command, so the columns match
+//! what users already see there.
+
+use crate::edges::{self, EdgeColumns};
+use crate::{TreePlugin, config, stream_tree, utils};
+use nu_plugin::{EngineInterface, EvaluatedCall, PluginCommand};
+use nu_protocol::{Category, Example, LabeledError, PipelineData, Record, Signature, Value};
+
+pub struct TreePs;
+
+/// Processes are nested under the process that started them.
+const COLUMNS: EdgeColumns = EdgeColumns {
+    id: "pid",
+    parent: "ppid",
+};
+
+impl PluginCommand for TreePs {
+    type Plugin = TreePlugin;
+
+    fn name(&self) -> &str {
+        "tree ps"
+    }
+
+    fn signature(&self) -> Signature {
+        Signature::build(self.name()).category(Category::Experimental)
+    }
+
+    fn description(&self) -> &str {
+        "View the system processes as a tree of parents and children."
+    }
+
+    fn examples(&self) -> Vec<Example<'_>> {
+        vec![Example {
+            example: "tree ps",
+            description: "Show every process under the process that started it",
+            result: None,
+        }]
+    }
+
+    fn run(
+        &self,
+        _plugin: &TreePlugin,
+        engine: &EngineInterface,
+        call: &EvaluatedCall,
+        _input: PipelineData,
+    ) -> Result<PipelineData, LabeledError> {
+        let Some(ps) = engine.find_decl("ps")? else {
+            return Err(LabeledError::new("The ps command is not available")
+                .with_label("tree ps needs Nushell's ps command", call.head));
+        };
+        let processes = engine
+            .call_decl(
+                ps,
+                EvaluatedCall::new(call.head),
+                PipelineData::empty(),
+                true,
+                false,
+            )?
+            .into_value(call.head)?
+            .into_list()?;
+
+        let tree = edges::build(&processes, &COLUMNS, "processes", label, call.head)?;
+        let nu_config = engine.get_config()?;
+        let color = config::color_choice(engine, &nu_config)?;
+        let branches = config::branch_style(engine, call)?;
+
+        Ok(stream_tree(tree, branches, color, engine, call.head))
+    }
+}
+
+/// Labels a process with its name, pid and memory usage.
+fn label(process: &Record) -> String {
+    let name = process
+        .get("name")
+        .and_then(|name| name.coerce_str().ok())
+        .unwrap_or_default();
+    let pid = process
+        .get("pid")
+        .and_then(|pid| pid.as_int().ok())
+        .unwrap_or_default();
+    match process.get("mem") {
+        Some(Value::Filesize { val, .. }) => {
+            let mem = utils::format_size(val.get().max(0) as u64);
+            format!("{name} ({pid}) {mem}")
+        }
+        _ => format!("{name} ({pid})"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nu_protocol::{Span, record};
+
+    fn process(name: &str, pid: i64, ppid: i64, mem: Option<i64>) -> Value {
+        let mut process = record! {
+            "pid" => Value::test_int(pid),
+            "ppid" => Value::test_int(ppid),
+            "name" => Value::test_string(name),
+        };
+        if let Some(mem) = mem {
+            process.push("mem", Value::test_filesize(mem));
+        }
+        Value::test_record(process)
+    }
+
+    #[test]
+    fn test_processes_nest_under_their_parents() {
+        let rows = vec![
+            process("init", 1, 0, Some(2048)),
+            process("shell", 20, 1, Some(512)),
+            process("nu", 300, 20, None),
+        ];
+        let tree = edges::build(&rows, &COLUMNS, "processes", label, Span::test_data()).unwrap();
+
+        let init = &tree.children[0];
+        assert_eq!(init.text, "init (1) 2.0 KiB");
+        assert_eq!(init.children[0].text, "shell (20) 512 B");
+        assert_eq!(init.children[0].children[0].text, "nu (300)");
+    }
+}
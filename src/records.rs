@@ -0,0 +1,430 @@
+//! Turns the entries of a path mode walk into Nushell values: a flat table, so
+//! the tree can be filtered and sorted like any other data, or nested records
+//! that keep the hierarchy for serializing with `to yaml` and friends.
+
+use crate::git;
+use crate::view::{Column, Entry, Node, Walk, nest};
+use chrono::{DateTime, Local};
+use nu_protocol::{Record, Span, Value};
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+
+/// The number of files below each directory with each git status, such as
+/// `{modified: 3, untracked: 1}`.
+type GitSummaries<'a> = HashMap<&'a Path, BTreeMap<&'static str, i64>>;
+
+/// The metadata columns included when `--columns` isn't given.
+const DEFAULT_COLUMNS: [Column; 4] = [
+    Column::Size,
+    Column::Modified,
+    Column::Git,
+    Column::Permissions,
+];
+
+/// Converts a walk into a list of records, one per entry, with the given
+/// metadata columns after the path, name, depth and type. The `raw_path`
+/// column is only added to the default columns.
+///
+/// Errors that belong to no entry come first, as rows for the root at depth 0.
+pub fn walk_to_value(root: &Path, walk: &Walk, columns: Option<&[Column]>, span: Span) -> Value {
+    let raw_path = columns.is_none();
+    let columns = columns.unwrap_or(&DEFAULT_COLUMNS);
+    let summaries = git_summaries(None, &walk.entries, columns);
+    let errors: Vec<Entry> = walk
+        .errors
+        .iter()
+        .map(|message| Entry {
+            path: root.to_path_buf(),
+            name: root.to_string_lossy().to_string(),
+            is_dir: true,
+            error: Some(message.clone()),
+            ..Default::default()
+        })
+        .collect();
+    Value::list(
+        errors
+            .iter()
+            .chain(&walk.entries)
+            .map(|entry| {
+                Value::record(
+                    entry_record(entry, columns, raw_path, &summaries, span),
+                    span,
+                )
+            })
+            .collect(),
+        span,
+    )
+}
+
+/// Converts a walk into a record for the root directory, where every
+/// directory lists the entries below it in a `children` column. Errors that
+/// belong to no entry are listed in an `errors` column, when there are any.
+pub fn walk_to_nested(root: &str, walk: &Walk, columns: Option<&[Column]>, span: Span) -> Value {
+    let entries = &walk.entries;
+    let raw_path = columns.is_none();
+    let columns = columns.unwrap_or(&DEFAULT_COLUMNS);
+    let summaries = git_summaries(Some(Path::new(root)), entries, columns);
+    let mut record = Record::new();
+    record.push("name", Value::string(root, span));
+    record.push("type", Value::string("dir", span));
+    if let Some(summary) = summaries.get(Path::new(root)) {
+        record.push("git_summary", summary_value(summary, span));
+    }
+    if !walk.errors.is_empty() {
+        let errors = walk
+            .errors
+            .iter()
+            .map(|message| Value::string(message, span))
+            .collect();
+        record.push("errors", Value::list(errors, span));
+    }
+    record.push(
+        "children",
+        nested_children(&nest(entries), columns, raw_path, &summaries, span),
+    );
+    Value::record(record, span)
+}
+
+/// Tallies the git status of the files below each directory, and below the
+/// root if given, when the git column is shown.
+fn git_summaries<'a>(
+    root: Option<&'a Path>,
+    entries: &'a [Entry],
+    columns: &[Column],
+) -> GitSummaries<'a> {
+    let mut summaries = GitSummaries::new();
+    if !columns.contains(&Column::Git) {
+        return summaries;
+    }
+    if let Some(root) = root {
+        summaries.insert(root, BTreeMap::new());
+    }
+    for entry in entries.iter().filter(|entry| entry.is_dir) {
+        summaries.insert(&entry.path, BTreeMap::new());
+    }
+    let files = entries
+        .iter()
+        .filter(|entry| !entry.is_dir)
+        .filter_map(|entry| Some((&entry.path, entry.git_status?)));
+    git::roll_up_to_ancestors(
+        &mut summaries,
+        files,
+        |summaries, dir, status| match summaries.get_mut(dir) {
+            Some(summary) => {
+                *summary.entry(status.name()).or_default() += 1;
+                true
+            }
+            None => false,
+        },
+    );
+    summaries
+}
+
+fn nested_children(
+    nodes: &[Node],
+    columns: &[Column],
+    raw_path: bool,
+    summaries: &GitSummaries,
+    span: Span,
+) -> Value {
+    Value::list(
+        nodes
+            .iter()
+            .map(|node| {
+                let entry = node.entry;
+                let mut record = Record::new();
+                record.push("name", Value::string(entry.name.clone(), span));
+                record.push("type", Value::string(entry_type(entry), span));
+                push_columns(&mut record, entry, columns, summaries, span);
+                // Names that aren't valid UTF-8 are escaped, so the original
+                // bytes of their paths are kept too.
+                if raw_path && entry.path.to_str().is_none() {
+                    record.push(
+                        "raw_path",
+                        Value::binary(entry.path.as_os_str().as_encoded_bytes(), span),
+                    );
+                }
+                if entry.error.is_some() {
+                    record.push("error", error_value(entry, span));
+                }
+                if entry.is_dir {
+                    record.push(
+                        "children",
+                        nested_children(&node.children, columns, raw_path, summaries, span),
+                    );
+                }
+                Value::record(record, span)
+            })
+            .collect(),
+        span,
+    )
+}
+
+fn entry_record(
+    entry: &Entry,
+    columns: &[Column],
+    raw_path: bool,
+    summaries: &GitSummaries,
+    span: Span,
+) -> Record {
+    let mut record = Record::new();
+    record.push(
+        "path",
+        Value::string(entry.path.to_string_lossy().to_string(), span),
+    );
+    record.push("name", Value::string(entry.name.clone(), span));
+    record.push("depth", Value::int(entry.depth as i64, span));
+    record.push("type", Value::string(entry_type(entry), span));
+    push_columns(&mut record, entry, columns, summaries, span);
+    // The path above is lossy when it isn't valid UTF-8, so keep the original bytes
+    // to still be able to act on the entry.
+    if raw_path {
+        let raw_path = match entry.path.to_str() {
+            Some(_) => Value::nothing(span),
+            None => Value::binary(entry.path.as_os_str().as_encoded_bytes(), span),
+        };
+        record.push("raw_path", raw_path);
+    }
+    record.push("error", error_value(entry, span));
+    record
+}
+
+/// Why the entry couldn't be read, or nothing.
+fn error_value(entry: &Entry, span: Span) -> Value {
+    match &entry.error {
+        Some(message) => Value::string(message, span),
+        None => Value::nothing(span),
+    }
+}
+
+fn entry_type(entry: &Entry) -> &'static str {
+    if entry.is_symlink {
+        "symlink"
+    } else if entry.is_dir {
+        "dir"
+    } else {
+        "file"
+    }
+}
+
+fn push_columns(
+    record: &mut Record,
+    entry: &Entry,
+    columns: &[Column],
+    summaries: &GitSummaries,
+    span: Span,
+) {
+    let optional = |value: Option<Value>| value.unwrap_or_else(|| Value::nothing(span));
+    for column in columns {
+        let value = match column {
+            Column::Size => entry.size.map(|size| Value::filesize(size as i64, span)),
+            Column::Modified => entry.modified.map(|modified| {
+                Value::date(DateTime::<Local>::from(modified).fixed_offset(), span)
+            }),
+            Column::Git => entry
+                .git_status
+                .map(|status| Value::string(status.name(), span)),
+            Column::Permissions => entry.permissions.clone().map(|p| Value::string(p, span)),
+            Column::Owner => entry.owner.clone().map(|owner| Value::string(owner, span)),
+        };
+        record.push(column.record_key(), optional(value));
+        if *column == Column::Git {
+            // Directories have no status of their own, so they count their files'.
+            let summary = summaries
+                .get(entry.path.as_path())
+                .map(|summary| summary_value(summary, span));
+            record.push("git_summary", optional(summary));
+        }
+    }
+}
+
+fn summary_value(summary: &BTreeMap<&'static str, i64>, span: Span) -> Value {
+    Value::record(
+        summary
+            .iter()
+            .map(|(status, count)| (status.to_string(), Value::int(*count, span)))
+            .collect(),
+        span,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::git::FileStatus;
+    use nu_protocol::record;
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_entry_record_columns() {
+        let entry = Entry {
+            path: PathBuf::from("root/src/main.rs"),
+            name: "main.rs".to_string(),
+            depth: 2,
+            size: Some(42),
+            git_status: Some(FileStatus::Modified),
+            ..Default::default()
+        };
+        let record = entry_record(
+            &entry,
+            &DEFAULT_COLUMNS,
+            true,
+            &GitSummaries::new(),
+            Span::test_data(),
+        );
+
+        assert_eq!(
+            record.columns().map(String::as_str).collect::<Vec<_>>(),
+            vec![
+                "path",
+                "name",
+                "depth",
+                "type",
+                "size",
+                "modified",
+                "git_status",
+                "git_summary",
+                "permissions",
+                "raw_path",
+                "error"
+            ]
+        );
+        assert_eq!(record.get("type").unwrap().as_str().unwrap(), "file");
+        assert_eq!(record.get("depth").unwrap().as_int().unwrap(), 2);
+        assert_eq!(
+            record.get("git_status").unwrap().as_str().unwrap(),
+            "modified"
+        );
+        assert!(record.get("modified").unwrap().is_nothing());
+    }
+
+    #[test]
+    fn test_entry_record_selected_columns() {
+        let entry = Entry {
+            path: PathBuf::from("root/a"),
+            name: "a".to_string(),
+            depth: 1,
+            owner: Some("nu".to_string()),
+            ..Default::default()
+        };
+        let record = entry_record(
+            &entry,
+            &[Column::Owner, Column::Size],
+            false,
+            &GitSummaries::new(),
+            Span::test_data(),
+        );
+
+        assert_eq!(
+            record.columns().map(String::as_str).collect::<Vec<_>>(),
+            vec!["path", "name", "depth", "type", "owner", "size", "error"]
+        );
+        assert_eq!(record.get("owner").unwrap().as_str().unwrap(), "nu");
+    }
+
+    #[test]
+    fn test_entries_to_nested_keeps_hierarchy() {
+        let entry = |name: &str, depth, is_dir| Entry {
+            path: PathBuf::from(name),
+            name: name.to_string(),
+            depth,
+            is_dir,
+            ..Default::default()
+        };
+        let walk = Walk {
+            entries: vec![entry("src", 1, true), entry("main.rs", 2, false)],
+            ..Default::default()
+        };
+        let root = walk_to_nested("root", &walk, Some(&[]), Span::test_data());
+
+        let root = root.as_record().unwrap();
+        assert_eq!(root.get("name").unwrap().as_str().unwrap(), "root");
+        assert!(root.get("errors").is_none());
+        let src = root.get("children").unwrap().as_list().unwrap()[0]
+            .as_record()
+            .unwrap();
+        assert_eq!(src.get("type").unwrap().as_str().unwrap(), "dir");
+        let main = src.get("children").unwrap().as_list().unwrap()[0]
+            .as_record()
+            .unwrap();
+        assert_eq!(
+            main.columns().map(String::as_str).collect::<Vec<_>>(),
+            vec!["name", "type"]
+        );
+    }
+
+    #[test]
+    fn test_walk_errors_are_listed_at_the_root() {
+        let walk = Walk {
+            errors: vec!["Permission denied".to_string()],
+            ..Default::default()
+        };
+        let rows = walk_to_value(Path::new("root"), &walk, None, Span::test_data());
+        let row = rows.as_list().unwrap()[0].as_record().unwrap();
+        assert_eq!(row.get("path").unwrap().as_str().unwrap(), "root");
+        assert_eq!(row.get("depth").unwrap().as_int().unwrap(), 0);
+        assert_eq!(
+            row.get("error").unwrap().as_str().unwrap(),
+            "Permission denied"
+        );
+
+        let root = walk_to_nested("root", &walk, None, Span::test_data());
+        assert_eq!(
+            root.as_record().unwrap().get("errors").unwrap(),
+            &Value::test_list(vec![Value::test_string("Permission denied")])
+        );
+    }
+
+    #[test]
+    fn test_git_summaries_count_files_below_each_directory() {
+        let entry = |path: &str, is_dir, git_status| Entry {
+            path: PathBuf::from(path),
+            name: path.rsplit('/').next().unwrap().to_string(),
+            depth: path.matches('/').count(),
+            is_dir,
+            git_status,
+            ..Default::default()
+        };
+        let entries = vec![
+            entry("root/src", true, None),
+            entry("root/src/bin", true, None),
+            entry("root/src/bin/cli.rs", false, Some(FileStatus::Untracked)),
+            entry("root/src/lib.rs", false, Some(FileStatus::Modified)),
+            entry("root/src/main.rs", false, Some(FileStatus::Modified)),
+            entry("root/docs", true, None),
+            entry("root/docs/guide.md", false, None),
+        ];
+        let walk = Walk {
+            entries,
+            ..Default::default()
+        };
+        let rows = walk_to_value(
+            Path::new("root"),
+            &walk,
+            Some(&[Column::Git]),
+            Span::test_data(),
+        );
+        let summary = |i: usize| {
+            rows.as_list().unwrap()[i]
+                .as_record()
+                .unwrap()
+                .get("git_summary")
+                .unwrap()
+                .clone()
+        };
+
+        assert_eq!(
+            summary(0),
+            Value::test_record(record! {
+                "modified" => Value::test_int(2),
+                "untracked" => Value::test_int(1),
+            })
+        );
+        assert_eq!(
+            summary(1),
+            Value::test_record(record! { "untracked" => Value::test_int(1) })
+        );
+        assert!(summary(3).is_nothing());
+        assert_eq!(summary(5), Value::test_record(record! {}));
+    }
+}
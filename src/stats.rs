@@ -0,0 +1,322 @@
+//! Implements `tree stats`, which summarizes what a directory is made of: how
+//! many files of each extension, type and depth it holds, and its largest files.
+
+use crate::{TreePlugin, config, view};
+use nu_plugin::{EngineInterface, EvaluatedCall, PluginCommand};
+use nu_protocol::{
+    Category, Example, LabeledError, PipelineData, Signature, Span, SyntaxShape, Type, Value,
+    record,
+};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+/// How many of the largest files are listed by default.
+const DEFAULT_TOP: usize = 10;
+
+pub struct TreeStats;
+
+impl PluginCommand for TreeStats {
+    type Plugin = TreePlugin;
+
+    fn name(&self) -> &str {
+        "tree stats"
+    }
+
+    fn signature(&self) -> Signature {
+        Signature::build(self.name())
+            .input_output_types(vec![(Type::Nothing, Type::record())])
+            .optional(
+                "path",
+                SyntaxShape::Filepath,
+                "the directory to summarize (defaults to the current directory)",
+            )
+            .named(
+                "top",
+                SyntaxShape::Int,
+                "how many of the largest files to list (default 10)",
+                Some('n'),
+            )
+            .switch("all", "include hidden files", Some('a'))
+            .switch(
+                "gitignore",
+                "respect .gitignore and other ignore files",
+                Some('g'),
+            )
+            .named(
+                "threads",
+                SyntaxShape::Int,
+                "walk the tree on this many threads (default 1)",
+                None,
+            )
+            .category(Category::Experimental)
+    }
+
+    fn description(&self) -> &str {
+        "Count the files below a path by extension, type and depth, and list the largest ones."
+    }
+
+    fn examples(&self) -> Vec<Example<'_>> {
+        vec![
+            Example {
+                example: "tree stats --gitignore | get by_extension | first 5",
+                description: "Show the five extensions that take the most space in a repository",
+                result: None,
+            },
+            Example {
+                example: "tree stats ~/downloads --top 3 | get largest",
+                description: "List the three largest files in a directory",
+                result: None,
+            },
+        ]
+    }
+
+    fn run(
+        &self,
+        _plugin: &TreePlugin,
+        engine: &EngineInterface,
+        call: &EvaluatedCall,
+        _input: PipelineData,
+    ) -> Result<PipelineData, LabeledError> {
+        let cwd = PathBuf::from(engine.get_current_dir()?);
+        let path = match call.opt::<PathBuf>(0)? {
+            Some(path) => cwd.join(path),
+            None => cwd,
+        };
+        let top = call
+            .get_flag::<i64>("top")?
+            .map_or(DEFAULT_TOP, |top| top.max(0) as usize);
+
+        let mut args = view::ViewArgs {
+            path,
+            all: call.has_flag("all")?,
+            gitignore: call.has_flag("gitignore")?,
+            ..Default::default()
+        };
+        config::walk_tuning(engine, call, &mut args)?;
+        let entries = view::walk(&args, true)
+            .and_then(view::Walk::complete)
+            .map_err(|err| {
+                LabeledError::new(format!("Error trying to walk the path: {}", err))
+                    .with_label("can't summarize this path", call.head)
+            })?;
+
+        let stats = Stats::of(&args.path, &entries, top);
+        Ok(PipelineData::Value(stats.into_value(call.head), None))
+    }
+}
+
+/// The number of entries in a group and the total size of its files.
+#[derive(Debug, Default, PartialEq)]
+struct Tally {
+    count: u64,
+    size: u64,
+}
+
+impl Tally {
+    fn add(&mut self, size: u64) {
+        self.count += 1;
+        self.size += size;
+    }
+}
+
+#[derive(Debug, PartialEq)]
+struct Stats {
+    directories: u64,
+    files: u64,
+    size: u64,
+    by_extension: BTreeMap<String, Tally>,
+    by_type: BTreeMap<&'static str, Tally>,
+    by_depth: BTreeMap<usize, Tally>,
+    /// The largest files, relative to the root, largest first.
+    largest: Vec<(PathBuf, u64)>,
+}
+
+impl Stats {
+    fn of(root: &Path, entries: &[view::Entry], top: usize) -> Self {
+        let mut stats = Stats {
+            directories: 0,
+            files: 0,
+            size: 0,
+            by_extension: BTreeMap::new(),
+            by_type: BTreeMap::new(),
+            by_depth: BTreeMap::new(),
+            largest: Vec::new(),
+        };
+        for entry in entries {
+            // Only files add to the sizes, so every table adds up to `size`.
+            let size = entry.size.filter(|_| !entry.is_dir).unwrap_or(0);
+            let kind = if entry.is_symlink {
+                "symlink"
+            } else if entry.is_dir {
+                "dir"
+            } else {
+                "file"
+            };
+            stats.by_type.entry(kind).or_default().add(size);
+            stats.by_depth.entry(entry.depth).or_default().add(size);
+            if entry.is_dir {
+                stats.directories += 1;
+                continue;
+            }
+
+            stats.files += 1;
+            stats.size += size;
+            let extension = Path::new(&entry.name)
+                .extension()
+                .map(|extension| extension.to_string_lossy().to_string())
+                .unwrap_or_default();
+            stats.by_extension.entry(extension).or_default().add(size);
+            let relative = entry.path.strip_prefix(root).unwrap_or(&entry.path);
+            stats.largest.push((relative.to_path_buf(), size));
+        }
+        stats
+            .largest
+            .sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        stats.largest.truncate(top);
+        stats
+    }
+
+    fn into_value(self, span: Span) -> Value {
+        let size = |size: u64| Value::filesize(size as i64, span);
+        let table = |key: &str, groups: Vec<(Value, Tally)>| {
+            let rows = groups
+                .into_iter()
+                .map(|(group, tally)| {
+                    Value::record(
+                        record! {
+                            key => group,
+                            "count" => Value::int(tally.count as i64, span),
+                            "size" => size(tally.size),
+                        },
+                        span,
+                    )
+                })
+                .collect();
+            Value::list(rows, span)
+        };
+
+        // Extensions are listed by the space they take; types and depths keep their order.
+        let mut by_extension: Vec<(String, Tally)> = self.by_extension.into_iter().collect();
+        by_extension.sort_by(|a, b| b.1.size.cmp(&a.1.size).then_with(|| a.0.cmp(&b.0)));
+
+        Value::record(
+            record! {
+                "directories" => Value::int(self.directories as i64, span),
+                "files" => Value::int(self.files as i64, span),
+                "size" => size(self.size),
+                "by_extension" => table(
+                    "extension",
+                    by_extension
+                        .into_iter()
+                        .map(|(extension, tally)| (Value::string(extension, span), tally))
+                        .collect(),
+                ),
+                "by_type" => table(
+                    "type",
+                    self.by_type
+                        .into_iter()
+                        .map(|(kind, tally)| (Value::string(kind, span), tally))
+                        .collect(),
+                ),
+                "by_depth" => table(
+                    "depth",
+                    self.by_depth
+                        .into_iter()
+                        .map(|(depth, tally)| (Value::int(depth as i64, span), tally))
+                        .collect(),
+                ),
+                "largest" => Value::list(
+                    self.largest
+                        .into_iter()
+                        .map(|(path, file_size)| {
+                            Value::record(
+                                record! {
+                                    "path" => Value::string(path.to_string_lossy(), span),
+                                    "size" => size(file_size),
+                                },
+                                span,
+                            )
+                        })
+                        .collect(),
+                    span,
+                ),
+            },
+            span,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(path: &str, depth: usize, size: Option<u64>) -> view::Entry {
+        view::Entry {
+            path: PathBuf::from(path),
+            name: path.rsplit('/').next().unwrap_or(path).to_string(),
+            depth,
+            is_dir: size.is_none(),
+            size,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_stats_group_files() {
+        let entries = vec![
+            entry("root/src", 1, None),
+            entry("root/src/main.rs", 2, Some(500)),
+            entry("root/src/lib.rs", 2, Some(200)),
+            entry("root/README.md", 1, Some(100)),
+            entry("root/LICENSE", 1, Some(50)),
+        ];
+        let stats = Stats::of(Path::new("root"), &entries, 2);
+
+        assert_eq!((stats.directories, stats.files, stats.size), (1, 4, 850));
+        assert_eq!(
+            stats.by_extension.get("rs"),
+            Some(&Tally {
+                count: 2,
+                size: 700
+            })
+        );
+        assert_eq!(
+            stats.by_extension.get(""),
+            Some(&Tally { count: 1, size: 50 })
+        );
+        assert_eq!(
+            stats.by_depth.get(&1),
+            Some(&Tally {
+                count: 3,
+                size: 150
+            })
+        );
+        assert_eq!(
+            stats.largest,
+            vec![
+                (PathBuf::from("src/main.rs"), 500),
+                (PathBuf::from("src/lib.rs"), 200)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_directories_add_no_size() {
+        let entries = vec![
+            view::Entry {
+                size: Some(4096),
+                ..entry("root/src", 1, None)
+            },
+            entry("root/src/main.rs", 2, Some(500)),
+        ];
+        let stats = Stats::of(Path::new("root"), &entries, 2);
+
+        assert_eq!(stats.size, 500);
+        assert_eq!(stats.by_type.get("dir"), Some(&Tally { count: 1, size: 0 }));
+        assert_eq!(stats.by_depth.get(&1), Some(&Tally { count: 1, size: 0 }));
+        assert_eq!(
+            stats.by_type.values().map(|tally| tally.size).sum::<u64>(),
+            500
+        );
+    }
+}
@@ -0,0 +1,248 @@
+//! Loads the optional theme file, `$XDG_CONFIG_HOME/nu_plugin_tree/theme.toml`
+//! (or `~/.config/nu_plugin_tree/theme.toml`), which lives outside Nushell so it
+//! can be shared with other tree tools.
+//!
+//! ```toml
+//! charset = "utf8"
+//! indent = 4
+//!
+//! [characters]
+//! down_and_right = "├"
+//! turn_right = "╰"
+//!
+//! # Keyed by file name, extension, or `directory`
+//! [icons]
+//! rs = "🦀"
+//! directory = "📁"
+//!
+//! [icon_colors]
+//! rs = "#dea584"
+//!
+//! [colors]
+//! directory = "blue"
+//! "Cargo.toml" = "#ff8700"
+//! ```
+//!
+//! The file is read once per plugin process; the plugin configuration in
+//! `$env.config.plugins.tree` and the command flags take precedence over it.
+
+use crate::config::MIN_INDENT;
+use crate::view::{self, BranchStyle, Charset};
+use nu_ansi_term::{Color, Style};
+use nu_protocol::LabeledError;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+/// The theme file as written by the user.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct ThemeFile {
+    charset: Option<String>,
+    indent: Option<usize>,
+    characters: Characters,
+    icons: HashMap<String, String>,
+    icon_colors: HashMap<String, String>,
+    colors: HashMap<String, String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct Characters {
+    down_and_right: Option<String>,
+    down: Option<String>,
+    turn_right: Option<String>,
+    right: Option<String>,
+    empty: Option<String>,
+}
+
+/// A validated theme.
+#[derive(Debug, Default)]
+pub struct Theme {
+    pub branches: BranchStyle,
+    icons: HashMap<String, String>,
+    icon_colors: HashMap<String, Color>,
+    colors: HashMap<String, Color>,
+}
+
+impl Theme {
+    /// Returns the icon for an entry, if the theme overrides it.
+    pub fn icon(&self, name: &str, is_dir: bool) -> Option<&str> {
+        lookup(&self.icons, name, is_dir).map(String::as_str)
+    }
+
+    /// Returns the color of an entry's icon, if the theme overrides it.
+    pub fn icon_color(&self, name: &str, is_dir: bool) -> Option<Color> {
+        lookup(&self.icon_colors, name, is_dir).copied()
+    }
+
+    /// Returns the style of an entry's name, if the theme overrides it.
+    pub fn name_style(&self, name: &str, is_dir: bool) -> Option<Style> {
+        lookup(&self.colors, name, is_dir).map(|color| color.normal())
+    }
+}
+
+/// Looks up an entry by its exact name, then by `directory` or its extension.
+fn lookup<'a, T>(map: &'a HashMap<String, T>, name: &str, is_dir: bool) -> Option<&'a T> {
+    if map.is_empty() {
+        return None;
+    }
+    map.get(name).or_else(|| {
+        if is_dir {
+            map.get("directory")
+        } else {
+            let extension = Path::new(name).extension()?.to_str()?;
+            map.get(extension)
+        }
+    })
+}
+
+/// Returns the theme, reading the theme file the first time it's needed.
+pub fn load() -> Result<&'static Theme, LabeledError> {
+    static THEME: OnceLock<Result<Theme, String>> = OnceLock::new();
+
+    let theme = THEME.get_or_init(|| match theme_path() {
+        Some(path) if path.is_file() => {
+            read(&path).map_err(|err| format!("{}: {}", path.display(), err))
+        }
+        _ => Ok(Theme::default()),
+    });
+    theme
+        .as_ref()
+        .map_err(|err| LabeledError::new("Invalid tree theme file").with_help(err.clone()))
+}
+
+fn theme_path() -> Option<PathBuf> {
+    let config_dir = std::env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .filter(|dir| dir.is_absolute())
+        .or_else(|| dirs::home_dir().map(|home| home.join(".config")))?;
+    Some(config_dir.join("nu_plugin_tree").join("theme.toml"))
+}
+
+fn read(path: &Path) -> Result<Theme, String> {
+    let contents = std::fs::read_to_string(path).map_err(|err| err.to_string())?;
+    parse(&contents)
+}
+
+fn parse(contents: &str) -> Result<Theme, String> {
+    let file: ThemeFile = toml::from_str(contents).map_err(|err| err.to_string())?;
+
+    let mut branches = BranchStyle::default();
+    if let Some(charset) = &file.charset {
+        branches.characters = charset.parse::<Charset>()?.indent_chars();
+    }
+    for (value, slot) in [
+        (
+            file.characters.down_and_right,
+            &mut branches.characters.down_and_right,
+        ),
+        (file.characters.down, &mut branches.characters.down),
+        (
+            file.characters.turn_right,
+            &mut branches.characters.turn_right,
+        ),
+        (file.characters.right, &mut branches.characters.right),
+        (file.characters.empty, &mut branches.characters.empty),
+    ] {
+        if let Some(value) = value {
+            *slot = value;
+        }
+    }
+    if let Some(indent) = file.indent {
+        if indent < MIN_INDENT {
+            return Err(format!("the indent must be at least {MIN_INDENT}"));
+        }
+        branches.indent = indent;
+    }
+
+    Ok(Theme {
+        branches,
+        icons: file.icons,
+        icon_colors: parse_colors(file.icon_colors)?,
+        colors: parse_colors(file.colors)?,
+    })
+}
+
+fn parse_colors(colors: HashMap<String, String>) -> Result<HashMap<String, Color>, String> {
+    let mut parsed = HashMap::with_capacity(colors.len());
+    for (key, color) in colors {
+        let value =
+            parse_color(&color).ok_or_else(|| format!("unknown color '{color}' for '{key}'"))?;
+        parsed.insert(key, value);
+    }
+    Ok(parsed)
+}
+
+/// Parses a `#rrggbb` color or the name of one of the basic terminal colors.
+fn parse_color(color: &str) -> Option<Color> {
+    if color.starts_with('#') {
+        return view::color_from_hex(color)
+            .ok()
+            .filter(|color| *color != Color::Default);
+    }
+    Some(match color.to_ascii_lowercase().as_str() {
+        "black" => Color::Black,
+        "red" => Color::Red,
+        "green" => Color::Green,
+        "yellow" => Color::Yellow,
+        "blue" => Color::Blue,
+        "purple" | "magenta" => Color::Purple,
+        "cyan" => Color::Cyan,
+        "white" => Color::White,
+        "dark_gray" | "dark_grey" => Color::DarkGray,
+        "light_red" => Color::LightRed,
+        "light_green" => Color::LightGreen,
+        "light_yellow" => Color::LightYellow,
+        "light_blue" => Color::LightBlue,
+        "light_purple" | "light_magenta" => Color::LightPurple,
+        "light_cyan" => Color::LightCyan,
+        "light_gray" | "light_grey" => Color::LightGray,
+        _ => return None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_theme() {
+        let theme = parse(
+            r##"
+            indent = 2
+            [characters]
+            turn_right = "╰"
+            [icons]
+            rs = "R"
+            directory = "D"
+            [colors]
+            "Cargo.toml" = "#ff8700"
+            md = "yellow"
+            "##,
+        )
+        .unwrap();
+
+        assert_eq!(theme.branches.indent, 2);
+        assert_eq!(theme.branches.characters.turn_right, "╰");
+        assert_eq!(theme.icon("main.rs", false), Some("R"));
+        assert_eq!(theme.icon("src", true), Some("D"));
+        assert_eq!(theme.icon("notes.txt", false), None);
+        assert_eq!(
+            theme.name_style("Cargo.toml", false),
+            Some(Color::Rgb(0xff, 0x87, 0x00).normal())
+        );
+        assert_eq!(
+            theme.name_style("README.md", false),
+            Some(Color::Yellow.normal())
+        );
+    }
+
+    #[test]
+    fn test_parse_theme_rejects_bad_values() {
+        assert!(parse("indent = 1").is_err());
+        assert!(parse("[colors]\nrs = \"chartreuse\"").is_err());
+        assert!(parse("unknown = true").is_err());
+    }
+}
@@ -43,6 +43,121 @@ pub fn format_permissions(mode: u32) -> String {
     )
 }
 
+/// Formats a file name so that every byte of it can be told apart: bytes that
+/// aren't valid UTF-8 become `\xNN`, and control characters and backslashes
+/// are escaped, like `ls -b`.
+pub fn escape_name(name: &std::ffi::OsStr) -> String {
+    let mut escaped = String::new();
+    for chunk in name.as_encoded_bytes().utf8_chunks() {
+        for c in chunk.valid().chars() {
+            match c {
+                '\\' => escaped.push_str("\\\\"),
+                c => push_char(&mut escaped, c),
+            }
+        }
+        for byte in chunk.invalid() {
+            escaped.push_str(&format!("\\x{byte:02x}"));
+        }
+    }
+    escaped
+}
+
+/// Whether a directory entry is hidden without `--all`: its name starts with a
+/// dot or, on Windows, it has the hidden attribute. This matches the walker.
+pub fn is_hidden(entry: &std::fs::DirEntry) -> bool {
+    #[cfg(windows)]
+    {
+        use std::os::windows::fs::MetadataExt;
+        const FILE_ATTRIBUTE_HIDDEN: u32 = 0x2;
+        // Windows keeps the metadata read with the directory, so this is free.
+        if entry
+            .metadata()
+            .is_ok_and(|md| md.file_attributes() & FILE_ATTRIBUTE_HIDDEN != 0)
+        {
+            return true;
+        }
+    }
+    entry.file_name().as_encoded_bytes().starts_with(b".")
+}
+
+/// Escapes the control characters in a name, so that drawing it can't break
+/// its line or send escape sequences to the terminal.
+pub fn escape_control(name: &str) -> String {
+    let mut escaped = String::with_capacity(name.len());
+    for c in name.chars() {
+        push_char(&mut escaped, c);
+    }
+    escaped
+}
+
+fn push_char(escaped: &mut String, c: char) {
+    match c {
+        '\n' => escaped.push_str("\\n"),
+        '\t' => escaped.push_str("\\t"),
+        '\r' => escaped.push_str("\\r"),
+        c if c.is_control() => escaped.push_str(&format!("\\x{:02x}", c as u32)),
+        c => escaped.push(c),
+    }
+}
+
+/// Formats a file name without losing any of it: bytes that aren't valid
+/// UTF-8 become `\xNN`, and everything else is kept as is.
+pub fn escape_invalid(name: &std::ffi::OsStr) -> String {
+    let mut escaped = String::new();
+    for chunk in name.as_encoded_bytes().utf8_chunks() {
+        escaped.push_str(chunk.valid());
+        for byte in chunk.invalid() {
+            escaped.push_str(&format!("\\x{byte:02x}"));
+        }
+    }
+    escaped
+}
+
+/// Runs `f` on every item on up to `threads` threads, for work that reads
+/// files, such as comparing their contents. The results keep the order of
+/// the items. Up to one thread runs `f` on the calling thread.
+pub fn parallel_map<T: Sync, R: Send>(
+    items: &[T],
+    threads: usize,
+    f: impl Fn(&T) -> R + Sync,
+) -> Vec<R> {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    let threads = threads.min(items.len());
+    if threads <= 1 {
+        return items.iter().map(f).collect();
+    }
+    // Each thread takes the next item as it finishes one, so a few slow files
+    // don't hold up the rest.
+    let next = AtomicUsize::new(0);
+    let mut results: Vec<Option<R>> = std::iter::repeat_with(|| None).take(items.len()).collect();
+    std::thread::scope(|scope| {
+        let workers: Vec<_> = (0..threads)
+            .map(|_| {
+                scope.spawn(|| {
+                    let mut done = Vec::new();
+                    loop {
+                        let i = next.fetch_add(1, Ordering::Relaxed);
+                        let Some(item) = items.get(i) else {
+                            return done;
+                        };
+                        done.push((i, f(item)));
+                    }
+                })
+            })
+            .collect();
+        for worker in workers {
+            let done = worker
+                .join()
+                .unwrap_or_else(|panic| std::panic::resume_unwind(panic));
+            for (i, result) in done {
+                results[i] = Some(result);
+            }
+        }
+    });
+    results.into_iter().flatten().collect()
+}
+
 pub fn get_ls_colors(lscolors_env_string: Option<String>) -> lscolors::LsColors {
     if let Some(s) = lscolors_env_string {
         lscolors::LsColors::from_string(&s)
@@ -412,4 +527,34 @@ mod tests {
         let mode_user_only = 0o700;
         assert_eq!(format_permissions(mode_user_only), "rwx------");
     }
+
+    #[test]
+    fn test_parallel_map_keeps_order() {
+        let items: Vec<u64> = (0..100).collect();
+        let squares: Vec<u64> = items.iter().map(|i| i * i).collect();
+        assert_eq!(parallel_map(&items, 1, |i| i * i), squares);
+        assert_eq!(parallel_map(&items, 4, |i| i * i), squares);
+        assert!(parallel_map(&[] as &[u64], 4, |i| i * i).is_empty());
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_escape_name() {
+        use std::ffi::OsStr;
+        use std::os::unix::ffi::OsStrExt;
+
+        assert_eq!(escape_name(OsStr::new("plain.txt")), "plain.txt");
+        assert_eq!(
+            escape_name(OsStr::from_bytes(b"caf\xe9\nnotes\\.txt")),
+            "caf\\xe9\\nnotes\\\\.txt"
+        );
+        assert_eq!(
+            escape_invalid(OsStr::from_bytes(b"caf\xe9 notes.txt")),
+            "caf\\xe9 notes.txt"
+        );
+        assert_eq!(
+            escape_control("a\x1b[31mb\nc\\d.txt"),
+            "a\\x1b[31mb\\nc\\d.txt"
+        );
+    }
 }
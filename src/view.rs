@@ -3,6 +3,10 @@
 
 // use crate::app::ViewArgs;
 use crate::git;
+use crate::patterns::Patterns;
+use crate::progress::Progress;
+use crate::provider;
+use crate::theme;
 // use crate::icons;
 use crate::utils;
 // use colored::{control, Colorize};
@@ -11,10 +15,19 @@ use lscolors::LsColors;
 // use lscolors::style;
 use devicons::icon_for_file;
 use nu_ansi_term::{Color, Style};
+use nu_protocol::Signals;
+use ptree::print_config::{ASCII_CHARS_TICK, IndentChars, UTF_CHARS};
+use std::collections::{HashMap, HashSet, hash_map};
 use std::{
     fmt, fs,
     io::{self, Write},
-    path::PathBuf,
+    path::{Path, PathBuf},
+    str::FromStr,
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicUsize, Ordering},
+    },
+    time::SystemTime,
 };
 
 // Platform-specific import for unix permissions
@@ -41,6 +54,258 @@ impl fmt::Display for ColorChoice {
     }
 }
 
+impl ColorChoice {
+    /// Returns the style unchanged, or a plain style when color is turned off.
+    pub fn style(&self, style: Style) -> Style {
+        if *self == ColorChoice::Never {
+            Style::new()
+        } else {
+            style
+        }
+    }
+}
+
+/// Defines the choices for the --charset option.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Default)]
+pub enum Charset {
+    #[default]
+    Utf8,
+    Ascii,
+}
+
+impl Charset {
+    /// Returns the characters used to draw the branches of the tree.
+    pub fn indent_chars(&self) -> IndentChars {
+        match self {
+            Charset::Utf8 => UTF_CHARS.into(),
+            Charset::Ascii => ASCII_CHARS_TICK.into(),
+        }
+    }
+}
+
+/// Implements the Display trait for Charset to show possible values in help messages.
+impl fmt::Display for Charset {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Charset::Utf8 => write!(f, "utf8"),
+            Charset::Ascii => write!(f, "ascii"),
+        }
+    }
+}
+
+impl FromStr for Charset {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "utf8" | "utf-8" | "utf" => Ok(Charset::Utf8),
+            "ascii" => Ok(Charset::Ascii),
+            _ => Err(format!(
+                "Unknown charset '{s}', expected one of: {}, {}",
+                Charset::Utf8,
+                Charset::Ascii
+            )),
+        }
+    }
+}
+
+/// A metadata column that can be shown in front of each entry with `--columns`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Column {
+    Permissions,
+    Size,
+    Modified,
+    Owner,
+    Git,
+}
+
+impl Column {
+    /// The column's name in the records returned by `--as-records`.
+    pub fn record_key(&self) -> &'static str {
+        match self {
+            Column::Permissions => "permissions",
+            Column::Size => "size",
+            Column::Modified => "modified",
+            Column::Owner => "owner",
+            Column::Git => "git_status",
+        }
+    }
+
+    /// The text shown for an entry in this column.
+    pub fn cell(&self, entry: &Entry) -> String {
+        match self {
+            Column::Permissions => entry.permissions.clone().unwrap_or_else(|| "-".repeat(10)),
+            Column::Size => entry.size.map(utils::format_size).unwrap_or_default(),
+            Column::Modified => entry
+                .modified
+                .map(|modified| {
+                    chrono::DateTime::<chrono::Local>::from(modified)
+                        .format("%Y-%m-%d %H:%M")
+                        .to_string()
+                })
+                .unwrap_or_default(),
+            Column::Owner => entry.owner.clone().unwrap_or_default(),
+            Column::Git => match (entry.git_status, entry.diff_stat) {
+                (Some(status), Some(stat)) => format!("{} {stat}", status.get_char()),
+                (Some(status), None) => status.get_char().to_string(),
+                (None, _) => String::new(),
+            },
+        }
+    }
+}
+
+impl FromStr for Column {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "perms" | "permissions" => Ok(Column::Permissions),
+            "size" => Ok(Column::Size),
+            "modified" | "mtime" => Ok(Column::Modified),
+            "owner" | "user" => Ok(Column::Owner),
+            "git" | "git_status" => Ok(Column::Git),
+            _ => Err(format!(
+                "unknown column '{s}', expected perms, size, modified, owner or git"
+            )),
+        }
+    }
+}
+
+/// The number of directories and files drawn, and the total size of the files.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Totals {
+    pub dirs: usize,
+    pub files: usize,
+    pub size: u64,
+}
+
+impl Totals {
+    pub fn of(entries: &[Entry]) -> Self {
+        let mut totals = Totals::default();
+        for entry in entries {
+            if entry.is_dir {
+                totals.dirs += 1;
+            } else {
+                totals.files += 1;
+                totals.size += entry.size.unwrap_or(0);
+            }
+        }
+        totals
+    }
+}
+
+/// The line printed below the tree.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub enum Summary {
+    /// `3 directories, 12 files`, counted the same way whatever the numbers.
+    #[default]
+    Counts,
+    /// GNU `tree`'s wording, which uses the singular for counts of one.
+    Gnu,
+    /// No summary, like `eza --tree`.
+    None,
+    /// A custom line where `{dirs}`, `{files}` and `{size}` are replaced by the totals.
+    Template(String),
+}
+
+impl Summary {
+    /// Formats the summary line, if there is one.
+    pub fn render(&self, totals: Totals) -> Option<String> {
+        match self {
+            Summary::Counts => Some(format!(
+                "{} directories, {} files",
+                totals.dirs, totals.files
+            )),
+            Summary::Gnu => Some(format!(
+                "{} director{}, {} file{}",
+                totals.dirs,
+                if totals.dirs == 1 { "y" } else { "ies" },
+                totals.files,
+                if totals.files == 1 { "" } else { "s" }
+            )),
+            Summary::None => None,
+            Summary::Template(template) => Some(
+                template
+                    .replace("{dirs}", &totals.dirs.to_string())
+                    .replace("{files}", &totals.files.to_string())
+                    .replace("{size}", &utils::format_size(totals.size)),
+            ),
+        }
+    }
+
+    /// Whether the summary shows the total size, which needs the files' metadata.
+    pub fn needs_size(&self) -> bool {
+        matches!(self, Summary::Template(template) if template.contains("{size}"))
+    }
+}
+
+impl FromStr for Summary {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "counts" => Ok(Summary::Counts),
+            "gnu" => Ok(Summary::Gnu),
+            "eza" | "none" => Ok(Summary::None),
+            template if template.contains('{') => Ok(Summary::Template(template.to_string())),
+            _ => Err(format!(
+                "unknown summary '{s}', expected counts, gnu, eza, none or a template such as \"{{dirs}} dirs / {{files}} files / {{size}}\""
+            )),
+        }
+    }
+}
+
+/// How the branches of the tree are drawn, shared by path mode and data mode.
+#[derive(Clone, Debug)]
+pub struct BranchStyle {
+    /// The characters used to draw the connectors.
+    pub characters: IndentChars,
+    /// The width of one level of indentation, including the connector.
+    pub indent: usize,
+}
+
+impl Default for BranchStyle {
+    fn default() -> Self {
+        Self {
+            characters: Charset::default().indent_chars(),
+            indent: 4,
+        }
+    }
+}
+
+/// The prefixes used to draw one level of the tree.
+pub struct Connectors {
+    /// Prefix for an entry that has siblings after it (`├── `).
+    pub branch: String,
+    /// Prefix for the last entry of a directory (`└── `).
+    pub last: String,
+    /// Continuation for an ancestor that has siblings after it (`│   `).
+    pub vertical: String,
+    /// Continuation for an ancestor that was the last entry (`    `).
+    pub empty: String,
+}
+
+impl Connectors {
+    /// Builds the connectors for the given characters, using the same layout as ptree.
+    pub fn new(chars: &IndentChars, indent: usize) -> Self {
+        let pad = indent.saturating_sub(2);
+        Self {
+            branch: format!("{}{} ", chars.down_and_right, chars.right.repeat(pad)),
+            last: format!("{}{} ", chars.turn_right, chars.right.repeat(pad)),
+            vertical: format!("{}{} ", chars.down, chars.empty.repeat(pad)),
+            empty: format!("{}{} ", chars.empty, chars.empty.repeat(pad)),
+        }
+    }
+}
+
+/// A rendered entry waiting for its tree connectors.
+pub struct Row {
+    pub depth: usize,
+    /// Columns drawn before the connectors, such as the git status.
+    pub leading: String,
+    pub label: String,
+}
+
 /// Arguments for the classic `view` command.
 #[derive(Debug, Default)]
 pub struct ViewArgs {
@@ -71,233 +336,1373 @@ pub struct ViewArgs {
     /// Show git status for files and directories.
     // #[arg(short = 'G', long)]
     pub git_status: bool,
+    /// Mark files matched by `.gitignore` with `!` and dim them.
+    pub git_ignored: bool,
+    /// Only keep entries with uncommitted changes, and the directories above them.
+    pub git_dirty_only: bool,
+    /// Read the git status of files inside submodules from their own repositories.
+    pub git_submodules: bool,
+    /// Show how many lines each changed file adds and removes.
+    pub git_diff_stats: bool,
+    /// Color names by how recently they were last committed.
+    pub git_age: bool,
     /// Display file-specific icons (requires a Nerd Font).
     // #[arg(long, help = "Display file-specific icons (requires a Nerd Font)")]
     pub icons: bool,
+    /// The characters and indentation used to draw the tree branches.
+    pub branches: BranchStyle,
+    /// Icon and color overrides from the theme file.
+    pub theme: Option<&'static theme::Theme>,
+    /// The metadata columns to show in front of each entry, in order, replacing
+    /// the default git status, permissions and size decorations.
+    pub columns: Option<Vec<Column>>,
+    /// Number the entries, so they can be referred to unambiguously.
+    pub numbered: bool,
+    /// Show the size, modification time and permissions of a symlink's target
+    /// rather than of the link itself.
+    pub dereference: bool,
+    /// Descend into symlinked directories. Links back to a directory above
+    /// them are shown but not followed.
+    pub follow_symlinks: bool,
+    /// Don't cross into other filesystems, like mounted network shares.
+    pub one_file_system: bool,
+    /// Escape backslashes in names too, on top of the control characters and
+    /// bytes that aren't valid UTF-8, which are always escaped.
+    pub escape: bool,
+    /// Wrap names in double quotes, like `tree -Q`.
+    pub quote: bool,
+    /// The line printed below the tree.
+    pub summary: Summary,
+    /// The `--pattern` and `--ignore` filters.
+    pub patterns: Patterns,
+    /// How many threads walk the tree. Up to one uses the sequential walker.
+    pub threads: usize,
+    /// Stops the walk when Nushell is interrupted with ctrl-c, keeping the
+    /// entries walked so far.
+    pub signals: Option<Signals>,
+    /// Don't descend into directories holding more than this many entries.
+    pub file_limit: Option<usize>,
+    /// Keep at most this many entries, counting the rest.
+    pub max_entries: Option<usize>,
+    /// Show a progress line on stderr while a long walk runs. Off for callers
+    /// that draw on the terminal themselves, like `tree explore`.
+    pub progress: bool,
 }
 
-/// Executes the classic directory tree view
-pub fn run(args: &ViewArgs, ls_colors: &LsColors) -> anyhow::Result<()> {
-    // eprintln!("Running view with args: {:?}", args);
-    if !args.path.is_dir() {
-        anyhow::bail!("'{}' is not a directory.", args.path.display());
+impl ViewArgs {
+    fn interrupted(&self) -> bool {
+        self.signals.as_ref().is_some_and(Signals::interrupted)
     }
 
-    let canonical_root = fs::canonicalize(&args.path)?;
+    /// Whether entries are filtered once the whole tree was walked, so the
+    /// walk can't stop at `max_entries`.
+    fn filters_after_walk(&self) -> bool {
+        self.git_dirty_only || self.patterns.has_glob()
+    }
 
-    //TODO: Change this to nu_protocol's color handling UseAnsiColoring::Auto/True/False
-    // engine.get_config()?.use_ansi_coloring = true;
+    /// Whether a walk that kept `kept` entries only counts the rest. When
+    /// entries are filtered after the walk, the cap is applied to the ones
+    /// that are kept instead.
+    fn full(&self, kept: usize) -> bool {
+        !self.filters_after_walk() && self.max_entries.is_some_and(|max| kept >= max)
+    }
+}
 
-    // match args.color {
-    //     ColorChoice::Always => control::set_override(true),
-    //     ColorChoice::Never => control::set_override(false),
-    //     ColorChoice::Auto => {}
-    // }
+/// How many entries path mode shows unless `--max-entries` says otherwise.
+pub const DEFAULT_MAX_ENTRIES: usize = 100_000;
 
-    if writeln!(
-        io::stdout(),
-        "{}",
-        //args.path.display().to_string().blue().bold()
-        Style::new().bold().paint(args.path.display().to_string())
-    )
-    .is_err()
-    {
-        return Ok(());
+/// Everything path mode shows from an entry's metadata, which is read with a
+/// single syscall however many columns use it.
+#[derive(Default)]
+struct Stat {
+    size: Option<u64>,
+    modified: Option<SystemTime>,
+    permissions: Option<String>,
+    /// The owner's user id, named later from the walk's shared cache.
+    uid: Option<u32>,
+}
+
+impl Stat {
+    fn of(metadata: &fs::Metadata) -> Self {
+        #[cfg(unix)]
+        let (permissions, uid) = {
+            use std::os::unix::fs::MetadataExt;
+            let file_type_char = if metadata.is_symlink() {
+                'l'
+            } else if metadata.is_dir() {
+                'd'
+            } else {
+                '-'
+            };
+            let mode = metadata.permissions().mode();
+            let permissions = format!("{}{}", file_type_char, utils::format_permissions(mode));
+            (permissions, Some(metadata.uid()))
+        };
+        #[cfg(not(unix))]
+        let (permissions, uid) = {
+            // Junctions are reparse points that the standard library reports as
+            // symlinks, like the directory symlinks made by `mklink /d`.
+            let file_type_char = if metadata.is_symlink() {
+                'l'
+            } else if metadata.is_dir() {
+                'd'
+            } else {
+                '-'
+            };
+            (format!("{file_type_char}---------"), None)
+        };
+
+        Stat {
+            size: Some(metadata.len()).filter(|_| !metadata.is_dir()),
+            modified: metadata.modified().ok(),
+            permissions: Some(permissions),
+            uid,
+        }
     }
+}
 
-    let git_repo_status = if args.git_status {
-        git::load_status(&canonical_root)?
-    } else {
-        None
+/// A repository the walk is in. It starts at the walked directory `dir`, which
+/// is at `prefix` in the repository.
+struct RepoAt {
+    dir: PathBuf,
+    prefix: PathBuf,
+    status: git::GitRepoStatus,
+}
+
+impl RepoAt {
+    /// What the repository says about a path below `dir`.
+    fn lookup(&self, path: &Path, is_dir: bool) -> Option<GitInfo> {
+        let relative = self.prefix.join(path.strip_prefix(&self.dir).ok()?);
+        Some(GitInfo {
+            status: self.status.status(&relative),
+            submodule: is_dir && self.status.is_submodule(&relative),
+            diff_stat: self.status.diff_stats.get(&relative).copied(),
+        })
+    }
+}
+
+/// What git says about an entry.
+#[derive(Default)]
+struct GitInfo {
+    status: Option<git::FileStatus>,
+    submodule: bool,
+    diff_stat: Option<git::DiffStat>,
+}
+
+/// A walked entry, with what was read about it from the filesystem.
+struct Walked {
+    entry: ignore::DirEntry,
+    stat: Stat,
+    /// Identifies a directory, to spot when it is shown again.
+    dir_key: Option<DirKey>,
+    /// What the repository holding the root says about the entry.
+    git: GitInfo,
+    /// Whether a directory that is neither ignored nor a submodule has a
+    /// `.git` of its own, when git statuses are asked for.
+    has_git_dir: bool,
+}
+
+impl Walked {
+    /// Reads the metadata and git status of an entry, which is where most of
+    /// a walk's time goes, so the parallel walker does it on its own threads.
+    fn read(
+        entry: ignore::DirEntry,
+        args: &ViewArgs,
+        with_metadata: bool,
+        repo: Option<&RepoAt>,
+    ) -> Self {
+        // Like `ls -l`, a symlink shows its own metadata unless dereferencing is
+        // asked for, as with `ls -L`. Broken links always show their own.
+        let is_dir = entry.file_type().is_some_and(|ft| ft.is_dir());
+        let metadata = if !with_metadata || (args.dirs_only && !is_dir) {
+            None
+        } else if args.dereference && entry.path_is_symlink() {
+            fs::metadata(entry.path())
+                .ok()
+                .or_else(|| entry.metadata().ok())
+        } else {
+            entry.metadata().ok()
+        };
+        let dir_key = if is_dir {
+            dir_key(entry.path(), metadata.as_ref())
+        } else {
+            None
+        };
+        let git = repo
+            .and_then(|repo| repo.lookup(entry.path(), is_dir))
+            .unwrap_or_default();
+        let has_git_dir = (args.git_status || args.git_dirty_only)
+            && is_dir
+            && !git.submodule
+            && git.status != Some(git::FileStatus::Ignored)
+            && entry.path().join(".git").exists();
+        Walked {
+            entry,
+            stat: metadata.as_ref().map(Stat::of).unwrap_or_default(),
+            dir_key,
+            git,
+            has_git_dir,
+        }
+    }
+}
+
+/// Walks the tree on several threads, returning the entries sorted by path so
+/// that each directory is followed by its contents, as in a sequential walk.
+///
+/// The walk stops once it sent more than `--max-entries`, unless entries are
+/// filtered after the walk. Which entries it reached by then depends on the
+/// threads, and the rest aren't counted, so the second value tells whether it
+/// stopped there.
+fn walk_parallel(
+    mut builder: WalkBuilder,
+    args: &ViewArgs,
+    with_metadata: bool,
+    repo: Option<&RepoAt>,
+    progress: &mut Progress,
+) -> (Vec<Result<Walked, ignore::Error>>, bool) {
+    builder.threads(args.threads);
+    // The root is sent too, so one more than the cap means some were left out.
+    let cap = args
+        .max_entries
+        .filter(|_| !args.filters_after_walk())
+        .map(|max| max + 1);
+    let sent = AtomicUsize::new(0);
+    let (tx, rx) = std::sync::mpsc::channel();
+    let mut results: Vec<_> = std::thread::scope(|scope| {
+        let sent = &sent;
+        scope.spawn(move || {
+            builder.build_parallel().run(|| {
+                let tx = tx.clone();
+                Box::new(move |result| {
+                    if args.interrupted()
+                        || cap.is_some_and(|cap| sent.fetch_add(1, Ordering::Relaxed) > cap)
+                    {
+                        return ignore::WalkState::Quit;
+                    }
+                    let result = result.map(|entry| Walked::read(entry, args, with_metadata, repo));
+                    match tx.send(result) {
+                        Ok(()) => ignore::WalkState::Continue,
+                        Err(_) => ignore::WalkState::Quit,
+                    }
+                })
+            })
+        });
+        rx.into_iter()
+            .inspect(|result| {
+                if let Ok(walked) = result {
+                    progress.tick(walked.entry.path());
+                }
+            })
+            .collect()
+    });
+    // Errors are sorted by the path they are about, so a directory that can't
+    // be read is followed by its error, as the walker sent them. Errors without
+    // a path are reported first.
+    let key = |result: &Result<Walked, ignore::Error>| match result {
+        Ok(walked) => Some(walked.entry.path().to_path_buf()),
+        Err(err) => error_location(err).0.map(Path::to_path_buf),
     };
-    let status_cache = git_repo_status.as_ref().map(|s| &s.cache);
-    let repo_root = git_repo_status.as_ref().map(|s| &s.root);
+    results.sort_by_cached_key(key);
+    let capped = cap.is_some_and(|cap| sent.into_inner() > cap);
+    (results, capped)
+}
+
+/// The depth, ancestor and link of an error about a symlink that leads back
+/// to a directory above it.
+fn symlink_loop(err: &ignore::Error) -> Option<(usize, &Path, &Path)> {
+    match err {
+        ignore::Error::WithDepth { depth, err } => match err.as_ref() {
+            ignore::Error::Loop { ancestor, child } => Some((*depth, ancestor, child)),
+            _ => None,
+        },
+        _ => None,
+    }
+}
 
+/// The path and depth an error is about, when the walker knows them. For a
+/// symlink loop, this is the link.
+fn error_location(err: &ignore::Error) -> (Option<&Path>, Option<usize>) {
+    match err {
+        ignore::Error::WithDepth { depth, err } => (error_location(err).0, Some(*depth)),
+        ignore::Error::WithPath { path, .. } => (Some(path), None),
+        ignore::Error::WithLineNumber { err, .. } => error_location(err),
+        ignore::Error::Loop { child, .. } => (Some(child), None),
+        _ => (None, None),
+    }
+}
+
+/// What went wrong, without the path it went wrong at, such as `permission denied`.
+fn error_message(err: &ignore::Error) -> String {
+    match err {
+        ignore::Error::WithDepth { err, .. }
+        | ignore::Error::WithPath { err, .. }
+        | ignore::Error::WithLineNumber { err, .. } => error_message(err),
+        ignore::Error::Io(err) if err.kind() != io::ErrorKind::Other => err.kind().to_string(),
+        err => err.to_string(),
+    }
+}
+
+/// Creates a walker with the filtering options shared by every mode that walks a directory.
+///
+/// With `crowded`, the walker doesn't descend into directories holding more
+/// entries than its limit, and records how many they hold.
+pub fn walk_builder(args: &ViewArgs, crowded: Option<Arc<CrowdedDirs>>) -> WalkBuilder {
     let mut builder = WalkBuilder::new(&args.path);
-    builder.hidden(!args.all).git_ignore(args.gitignore);
+    builder
+        .hidden(!args.all)
+        .git_ignore(args.gitignore)
+        .follow_links(args.follow_symlinks)
+        .same_file_system(args.one_file_system)
+        // Siblings come in the same order as from the parallel walk, which
+        // sorts by path.
+        .sort_by_file_name(|a, b| a.cmp(b));
     if let Some(level) = args.level {
         builder.max_depth(Some(level));
     }
+    if !args.patterns.is_empty() || crowded.is_some() {
+        let patterns = args.patterns.clone();
+        builder.filter_entry(move |entry| {
+            patterns.allows(entry) && crowded.as_ref().is_none_or(|crowded| crowded.visit(entry))
+        });
+    }
+    builder
+}
+
+/// The directories found to hold more entries than `--filelimit`, with how
+/// many entries each holds. The root is always shown in full.
+pub struct CrowdedDirs {
+    limit: usize,
+    counts: Mutex<HashMap<PathBuf, usize>>,
+}
+
+impl CrowdedDirs {
+    pub fn new(limit: usize) -> Self {
+        CrowdedDirs {
+            limit,
+            counts: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Whether the walker should visit an entry: everything but the contents
+    /// of crowded directories. Directories are counted as they are reached,
+    /// before the walker reads them.
+    ///
+    /// The counts are only locked to look them up and to record one, so the
+    /// threads of a parallel walk read directories side by side.
+    fn visit(&self, entry: &ignore::DirEntry) -> bool {
+        if entry.depth() > 1
+            && let Some(parent) = entry.path().parent()
+            && self.count(parent).is_some()
+        {
+            return false;
+        }
+        if entry.depth() > 0
+            && entry.file_type().is_some_and(|ft| ft.is_dir())
+            && let Ok(dir) = fs::read_dir(entry.path())
+        {
+            let count = dir.count();
+            if count > self.limit {
+                self.counts
+                    .lock()
+                    .unwrap_or_else(|err| err.into_inner())
+                    .insert(entry.path().to_path_buf(), count);
+            }
+        }
+        true
+    }
+
+    /// How many entries a directory holds, if it was too many to show.
+    fn count(&self, path: &Path) -> Option<usize> {
+        let counts = self.counts.lock().unwrap_or_else(|err| err.into_inner());
+        counts.get(path).copied()
+    }
+}
 
-    let mut dir_count = 0;
-    let mut file_count = 0;
+/// Everything path mode knows about one walked entry.
+#[derive(Debug, Default)]
+pub struct Entry {
+    /// The path as walked, starting with the root path.
+    pub path: PathBuf,
+    pub name: String,
+    /// The depth below the root, which is at depth 0.
+    pub depth: usize,
+    pub is_dir: bool,
+    pub is_symlink: bool,
+    /// Whether the entry comes from a remote provider rather than the local filesystem.
+    pub remote: bool,
+    pub size: Option<u64>,
+    pub modified: Option<SystemTime>,
+    pub git_status: Option<git::FileStatus>,
+    /// The permissions in `ls -l` form, e.g. `drwxr-xr-x`.
+    pub permissions: Option<String>,
+    /// The name of the user owning the entry.
+    pub owner: Option<String>,
+    /// For a directory that was already shown elsewhere in the tree, the path
+    /// where it was first shown. Its contents are not repeated.
+    pub duplicate_of: Option<PathBuf>,
+    /// Whether the directory is the root of a git submodule.
+    pub submodule: bool,
+    /// The lines changed since the last commit, when asked for.
+    pub diff_stat: Option<git::DiffStat>,
+    /// When the entry was last committed, when asked for.
+    pub committed: Option<SystemTime>,
+    /// For a directory with more entries than `--filelimit`, how many entries
+    /// it holds. Its contents are not shown.
+    pub skipped_entries: Option<usize>,
+    /// Whether the entry is a symlink to a directory, or a junction on Windows,
+    /// that the walk didn't descend into.
+    pub dir_link: bool,
+    /// Why the entry, or the contents of a directory, couldn't be read.
+    pub error: Option<String>,
+}
+
+/// An entry together with the entries below it.
+pub struct Node<'a> {
+    pub entry: &'a Entry,
+    pub children: Vec<Node<'a>>,
+}
 
-    for result in builder.build() {
-        let entry = match result {
-            Ok(entry) => entry,
+/// Rebuilds the nesting of a depth-first walk.
+pub fn nest(entries: &[Entry]) -> Vec<Node<'_>> {
+    fn take<'a>(entries: &'a [Entry], i: &mut usize, depth: usize) -> Vec<Node<'a>> {
+        let mut nodes = Vec::new();
+        while let Some(entry) = entries.get(*i).filter(|e| e.depth == depth) {
+            *i += 1;
+            let children = take(entries, i, depth + 1);
+            nodes.push(Node { entry, children });
+        }
+        nodes
+    }
+
+    let mut i = 0;
+    take(entries, &mut i, 1)
+}
+
+/// The entries of a walk, in depth-first order.
+#[derive(Debug, Default)]
+pub struct Walk {
+    pub entries: Vec<Entry>,
+    /// Whether Nushell was interrupted during the walk, which then only holds
+    /// the entries walked so far.
+    pub interrupted: bool,
+    /// Errors that belong to no entry, such as the root being unreadable.
+    /// Errors about an entry are kept in its `error`.
+    pub errors: Vec<String>,
+    /// How many entries were left out past `--max-entries`.
+    pub omitted: usize,
+    /// Whether a parallel walk stopped at `--max-entries` without counting
+    /// the entries it left out.
+    pub capped: bool,
+}
+
+impl Walk {
+    /// The entries of a walk that ran to its end. Totals worked out from an
+    /// interrupted walk would be wrong, so it is an error.
+    pub fn complete(self) -> anyhow::Result<Vec<Entry>> {
+        if self.interrupted {
+            anyhow::bail!("the walk was interrupted");
+        }
+        Ok(self.entries)
+    }
+}
+
+/// Walks the tree described by `args`, in depth-first order.
+///
+/// Metadata (size, modification time and permissions) is only read when
+/// `with_metadata` is set, since it costs a syscall per entry.
+pub fn walk(args: &ViewArgs, with_metadata: bool) -> anyhow::Result<Walk> {
+    if let Some(source) = args
+        .path
+        .to_str()
+        .map(provider::from_url)
+        .transpose()?
+        .flatten()
+    {
+        return Ok(walk_remote(args, &source));
+    }
+    if !args.path.is_dir() {
+        anyhow::bail!("'{}' is not a directory.", args.path.display());
+    }
+
+    // On Windows, `fs::canonicalize` returns `\\?\C:\...` and `\\?\UNC\...`
+    // paths, which never match the ones git reports. They are simplified back to
+    // `C:\...` and `\\server\share\...` unless they are too long for that.
+    let canonical_root = dunce::canonicalize(&args.path)?;
+
+    let with_git = args.git_status || args.git_dirty_only;
+    let status_options = git::StatusOptions {
+        ignored: args.git_ignored,
+        submodules: args.git_submodules,
+        diff_stats: args.git_diff_stats,
+    };
+    let git_repo_status = if with_git {
+        git::load_status(&canonical_root, status_options)?
+    } else {
+        None
+    };
+
+    let commit_times = if args.git_age {
+        git::commit_times(&canonical_root, args.signals.as_ref())?
+    } else {
+        None
+    };
+    // Entries are looked up by their path in the repository: where the root
+    // is in the repository, followed by the entry's path below the root. This
+    // is worked out once, rather than canonicalizing every entry.
+    let in_repo = |repo_root: &Path| {
+        canonical_root
+            .strip_prefix(repo_root)
+            .ok()
+            .map(Path::to_path_buf)
+    };
+    // The repository holding the root is looked up by the walker. Nested
+    // repositories and linked worktrees found below it are looked up as they
+    // come, innermost last.
+    let root_repo = git_repo_status.and_then(|status| {
+        Some(RepoAt {
+            dir: args.path.clone(),
+            prefix: in_repo(&status.root)?,
+            status,
+        })
+    });
+    let mut nested_repos: Vec<RepoAt> = Vec::new();
+    let commit_prefix = commit_times
+        .as_ref()
+        .and_then(|commits| in_repo(&commits.root));
+
+    let crowded = args
+        .file_limit
+        .map(|limit| Arc::new(CrowdedDirs::new(limit)));
+    let builder = walk_builder(args, crowded.clone());
+    let mut progress = Progress::new(args.progress);
+    let mut capped = false;
+    let walked: Box<dyn Iterator<Item = Result<Walked, ignore::Error>>> = if args.threads > 1 {
+        let walked;
+        (walked, capped) = walk_parallel(
+            builder,
+            args,
+            with_metadata,
+            root_repo.as_ref(),
+            &mut progress,
+        );
+        Box::new(walked.into_iter())
+    } else {
+        Box::new(
+            builder
+                .build()
+                .take_while(|_| !args.interrupted())
+                .inspect(|result| {
+                    if let Ok(entry) = result {
+                        progress.tick(entry.path());
+                    }
+                })
+                .map(|result| {
+                    result.map(|entry| Walked::read(entry, args, with_metadata, root_repo.as_ref()))
+                }),
+        )
+    };
+    let mut entries = Vec::new();
+    // The first path at which each directory was reached, to spot bind mounts and
+    // junctions that show the same directory again.
+    let mut seen_dirs: HashMap<DirKey, PathBuf> = HashMap::new();
+    // The contents of a repeated directory are only shown at its first occurrence.
+    let mut skip_under: Option<PathBuf> = None;
+    let mut owners = Owners::default();
+    // Entries past `max_entries` are only counted.
+    let mut omitted = 0;
+    let mut errors = Vec::new();
+
+    for result in walked {
+        let Walked {
+            entry,
+            stat,
+            dir_key,
+            git,
+            has_git_dir,
+        } = match result {
+            Ok(walked) => walked,
             Err(err) => {
-                eprintln!("ERROR: {}", err);
+                // A link back to a directory above it is drawn like a
+                // directory shown twice, without its contents.
+                if let Some((depth, ancestor, child)) = symlink_loop(&err) {
+                    if args.full(entries.len()) {
+                        omitted += 1;
+                        continue;
+                    }
+                    entries.push(Entry {
+                        path: child.to_path_buf(),
+                        name: child
+                            .file_name()
+                            .map(utils::escape_invalid)
+                            .unwrap_or_default(),
+                        depth,
+                        is_dir: true,
+                        is_symlink: true,
+                        duplicate_of: Some(ancestor.to_path_buf()),
+                        ..Default::default()
+                    });
+                    continue;
+                }
+                // A directory that can't be read is reported right after it,
+                // and an entry that can't be read at all is drawn by itself.
+                let (path, depth) = error_location(&err);
+                let message = error_message(&err);
+                match (path, entries.last_mut()) {
+                    (Some(path), Some(last)) if last.path == path => last.error = Some(message),
+                    (Some(path), _)
+                        if path != args.path
+                            && !skip_under
+                                .as_ref()
+                                .is_some_and(|skipped| path.starts_with(skipped)) =>
+                    {
+                        if args.full(entries.len()) {
+                            omitted += 1;
+                            continue;
+                        }
+                        entries.push(Entry {
+                            path: path.to_path_buf(),
+                            name: path
+                                .file_name()
+                                .map(utils::escape_invalid)
+                                .unwrap_or_default(),
+                            depth: depth.unwrap_or_else(|| {
+                                path.strip_prefix(&args.path)
+                                    .map_or(1, |relative| relative.components().count())
+                            }),
+                            error: Some(message),
+                            ..Default::default()
+                        });
+                    }
+                    _ => errors.push(err.to_string()),
+                }
                 continue;
             }
         };
 
+        let is_dir = entry.file_type().is_some_and(|ft| ft.is_dir());
+        if let Some(skipped) = &skip_under {
+            if entry.path().starts_with(skipped) {
+                continue;
+            }
+            skip_under = None;
+        }
+        let duplicate_of = dir_key.and_then(|key| match seen_dirs.entry(key) {
+            hash_map::Entry::Occupied(first) => Some(first.get().clone()),
+            hash_map::Entry::Vacant(slot) => {
+                slot.insert(entry.path().to_path_buf());
+                None
+            }
+        });
+
         if entry.depth() == 0 {
             continue;
         }
-
-        let is_dir = entry.file_type().is_some_and(|ft| ft.is_dir());
+        if duplicate_of.is_some() {
+            skip_under = Some(entry.path().to_path_buf());
+        }
         if args.dirs_only && !is_dir {
             continue;
         }
+        if args.full(entries.len()) {
+            omitted += 1;
+            continue;
+        }
 
-        let git_status_str = if let (Some(cache), Some(root)) = (status_cache, repo_root) {
-            if let Ok(canonical_entry) = entry.path().canonicalize() {
-                if let Ok(relative_path) = canonical_entry.strip_prefix(root) {
-                    cache
-                        .get(relative_path)
-                        .map(|s| {
-                            let status_char = s.get_char();
-                            let color = match s {
-                                git::FileStatus::New | git::FileStatus::Renamed => {
-                                    Color::Green.normal()
-                                }
-                                git::FileStatus::Modified | git::FileStatus::Typechange => {
-                                    Color::Yellow.normal()
-                                }
-                                git::FileStatus::Deleted => Color::Red.normal(),
-                                git::FileStatus::Conflicted => Color::LightRed.normal(),
-                                git::FileStatus::Untracked => Color::Magenta.normal(),
-                            };
-                            // format!("{} ", status_char).color(color).to_string()
-                            color.paint(format!("{status_char} ")).to_string()
-                        })
-                        .unwrap_or_else(|| "  ".to_string())
-                } else {
-                    "  ".to_string()
-                }
-            } else {
-                "  ".to_string()
-            }
-        } else {
-            String::new()
-        };
-
-        let metadata = if args.size || args.permissions {
-            entry.metadata().ok()
-        } else {
-            None
-        };
-        let permissions_str = if args.permissions {
-            let perms = if let Some(md) = &metadata {
-                // <-- Use 'md' here
-                #[cfg(unix)]
-                {
-                    // Use 'md' for Unix-specific logic
-                    let mode = md.permissions().mode();
-                    let file_type_char = if md.is_dir() { 'd' } else { '-' };
-                    format!("{}{}", file_type_char, utils::format_permissions(mode))
-                }
-                #[cfg(not(unix))]
-                {
-                    // This line tells the compiler we've intentionally not used 'md' on non-Unix systems
-                    let _ = md;
-                    "----------".to_string()
-                }
-            } else {
-                "----------".to_string()
-            };
-            format!("{} ", perms)
-        } else {
-            String::new()
+        let below_root = entry.path().strip_prefix(&args.path).ok();
+        let GitInfo {
+            status: mut git_status,
+            submodule,
+            diff_stat,
+        } = match nested_repos
+            .iter()
+            .rev()
+            .find(|repo| entry.path().starts_with(&repo.dir))
+        {
+            Some(repo) => repo.lookup(entry.path(), is_dir).unwrap_or_default(),
+            None => git,
         };
+        // A directory with its own `.git` that isn't a submodule is a nested
+        // repository or a linked worktree. The enclosing repository only sees
+        // it as untracked, so its files are looked up in its own status.
+        if has_git_dir
+            && !submodule
+            && git_status != Some(git::FileStatus::Ignored)
+            && let Ok(Some(nested)) = git::load_status(entry.path(), status_options)
+        {
+            git_status = git_status.or(nested.summary());
+            nested_repos.push(RepoAt {
+                dir: entry.path().to_path_buf(),
+                prefix: PathBuf::new(),
+                status: nested,
+            });
+        }
+        let committed = commit_times.as_ref().and_then(|commits| {
+            let relative_path = commit_prefix.as_ref()?.join(below_root?);
+            commits.times.get(&relative_path).copied()
+        });
 
-        let indent = "    ".repeat(entry.depth().saturating_sub(1));
-        let name = entry.file_name().to_string_lossy();
-        let icon_str = if args.icons {
-            // let (icon, color) = icons::get_icon_for_path(entry.path(), is_dir);
-            let icon_info = icon_for_file(&entry.path(), &None);
-            // format!("{} ", icon.color(color))
-            Style::new()
-                .fg(lookup_ansi_color_style(icon_info.color))
-                .paint(format!("{} ", icon_info.icon))
-                .to_string()
-        } else {
-            String::new()
-        };
-        let size_str = if args.size && !is_dir {
-            metadata
+        entries.push(Entry {
+            name: utils::escape_invalid(entry.file_name()),
+            depth: entry.depth(),
+            is_dir,
+            is_symlink: entry.path_is_symlink(),
+            remote: false,
+            size: stat.size,
+            modified: stat.modified,
+            git_status,
+            permissions: stat.permissions,
+            owner: stat.uid.and_then(|uid| owners.name(uid)),
+            duplicate_of,
+            submodule,
+            diff_stat,
+            committed,
+            skipped_entries: crowded
                 .as_ref()
-                .map(|m| format!(" ({})", utils::format_size(m.len())))
-                .unwrap_or_default()
-        } else {
-            String::new()
-        };
+                .filter(|_| is_dir)
+                .and_then(|crowded| crowded.count(entry.path())),
+            dir_link: !is_dir && is_dir_link(&entry),
+            error: None,
+            path: entry.into_path(),
+        });
+    }
 
-        // --- Corrected Logic Block ---
-        let ls_style = ls_colors
-            .style_for_path(entry.path())
-            .cloned()
-            .unwrap_or_default();
-        //let mut styled_name = name.to_string().normal();
-        // let mut styled_name = Style::new().normal().paint(name.to_string());
-        let mut styled_name = Style::new();
-
-        if let Some(fg) = ls_style.foreground {
-            use lscolors::Color as LsColor;
-            let color = match fg {
-                LsColor::Black => Color::Black,
-                LsColor::Red => Color::Red,
-                LsColor::Green => Color::Green,
-                LsColor::Yellow => Color::Yellow,
-                LsColor::Blue => Color::Blue,
-                LsColor::Magenta => Color::Magenta,
-                LsColor::Cyan => Color::Cyan,
-                LsColor::White => Color::White,
-                LsColor::BrightBlack => Color::DarkGray,
-                LsColor::BrightRed => Color::LightRed,
-                LsColor::BrightGreen => Color::LightGreen,
-                LsColor::BrightYellow => Color::LightYellow,
-                LsColor::BrightBlue => Color::LightBlue,
-                LsColor::BrightMagenta => Color::LightMagenta,
-                LsColor::BrightCyan => Color::LightCyan,
-                LsColor::BrightWhite => Color::LightGray,
-                LsColor::Fixed(_) => Color::White,
-                LsColor::RGB(r, g, b) => Color::Rgb(r, g, b),
-            };
-            // styled_name = styled_name.color(color);
-            styled_name = styled_name.fg(color)
+    progress.clear();
+    Ok(finish(args, entries, errors, omitted, capped))
+}
+
+/// Walks a directory listed by a provider, filtered and capped like a local walk.
+fn walk_remote(args: &ViewArgs, source: &provider::Source) -> Walk {
+    let root = args.path.to_string_lossy();
+    let root = root.trim_end_matches('/');
+    let path_of =
+        |walk_entry: &provider::WalkEntry| PathBuf::from(format!("{root}/{}", walk_entry.path));
+    let keep = |walk_entry: &provider::WalkEntry| {
+        args.patterns
+            .allows_path(&path_of(walk_entry), walk_entry.entry.is_dir)
+    };
+    let stop = || args.interrupted();
+    let options = provider::WalkOptions {
+        max_depth: args.level,
+        all: args.all,
+        keep: &keep,
+        stop: &stop,
+    };
+    let (walked, errors) = provider::walk(source, &options);
+
+    let mut entries = Vec::new();
+    let mut omitted = 0;
+    for walk_entry in walked {
+        let path = path_of(&walk_entry);
+        let entry = walk_entry.entry;
+        if args.dirs_only && !entry.is_dir {
+            continue;
         }
+        if args.full(entries.len()) {
+            omitted += 1;
+            continue;
+        }
+        entries.push(Entry {
+            path,
+            name: entry.name,
+            depth: walk_entry.depth,
+            is_dir: entry.is_dir,
+            remote: true,
+            size: entry.size.filter(|_| !entry.is_dir),
+            error: walk_entry.error,
+            ..Default::default()
+        });
+    }
+    finish(args, entries, errors, omitted, false)
+}
 
-        if ls_style.font_style.bold {
-            // styled_name = styled_name.bold();
-            styled_name = styled_name.bold();
+/// Applies the filters that need the whole walk, and `--max-entries` after
+/// them, to the entries of a local or remote walk.
+fn finish(
+    args: &ViewArgs,
+    mut entries: Vec<Entry>,
+    errors: Vec<String>,
+    mut omitted: usize,
+    capped: bool,
+) -> Walk {
+    if args.git_dirty_only {
+        // Directories have the status of the changes below them, so this keeps
+        // the ancestors of every changed file.
+        entries.retain(|entry| {
+            entry
+                .git_status
+                .is_some_and(|status| status != git::FileStatus::Ignored)
+        });
+    }
+    if args.patterns.has_glob() {
+        // Files were already filtered by the walk; directories stay when the
+        // glob matches them or something below them.
+        let matched: Vec<bool> = entries
+            .iter()
+            .map(|entry| !entry.is_dir || args.patterns.matches_glob(&entry.path, true))
+            .collect();
+        let mut above_matches = HashSet::new();
+        for (entry, _) in entries
+            .iter()
+            .zip(&matched)
+            .filter(|(_, matched)| **matched)
+        {
+            above_matches.extend(entry.path.ancestors().skip(1).map(Path::to_path_buf));
         }
-        if ls_style.font_style.italic {
-            styled_name = styled_name.italic();
+        let mut matched = matched.into_iter();
+        entries
+            .retain(|entry| matched.next().unwrap_or(false) || above_matches.contains(&entry.path));
+    }
+    if let Some(max) = args.max_entries
+        && entries.len() > max
+    {
+        omitted += entries.len() - max;
+        entries.truncate(max);
+    }
+    Walk {
+        entries,
+        interrupted: args.interrupted(),
+        errors,
+        omitted,
+        capped,
+    }
+}
+
+/// Looks up the names of file owners, remembering each user it has seen.
+#[derive(Default)]
+struct Owners {
+    #[cfg(unix)]
+    names: HashMap<u32, Option<String>>,
+}
+
+impl Owners {
+    #[cfg(unix)]
+    fn name(&mut self, uid: u32) -> Option<String> {
+        self.names
+            .entry(uid)
+            .or_insert_with(|| {
+                let user = nix::unistd::User::from_uid(uid.into()).ok().flatten();
+                Some(user.map_or_else(|| uid.to_string(), |user| user.name))
+            })
+            .clone()
+    }
+
+    #[cfg(not(unix))]
+    fn name(&mut self, _uid: u32) -> Option<String> {
+        None
+    }
+}
+
+/// Identifies a directory on disk, whatever path it was reached through.
+#[cfg(unix)]
+type DirKey = (u64, u64);
+#[cfg(not(unix))]
+type DirKey = PathBuf;
+
+/// Whether an entry is a link to a directory: a symlink or, on Windows, a
+/// junction. Links aren't descended into unless `--follow-symlinks` is given,
+/// which keeps loops like `Application Data` out of the walk.
+fn is_dir_link(entry: &ignore::DirEntry) -> bool {
+    if !entry.path_is_symlink() {
+        return false;
+    }
+    #[cfg(windows)]
+    if let Some(file_type) = entry.file_type() {
+        use std::os::windows::fs::FileTypeExt;
+        return file_type.is_symlink_dir();
+    }
+    entry.path().is_dir()
+}
+
+/// Reuses the entry's metadata when it describes the directory itself, rather
+/// than a symlink to it.
+#[cfg(unix)]
+fn dir_key(path: &Path, metadata: Option<&fs::Metadata>) -> Option<DirKey> {
+    use std::os::unix::fs::MetadataExt;
+    let key = |metadata: &fs::Metadata| (metadata.dev(), metadata.ino());
+    match metadata.filter(|metadata| metadata.is_dir()) {
+        Some(metadata) => Some(key(metadata)),
+        None => fs::metadata(path).ok().map(|metadata| key(&metadata)),
+    }
+}
+
+#[cfg(not(unix))]
+fn dir_key(path: &Path, _metadata: Option<&fs::Metadata>) -> Option<DirKey> {
+    // Canonicalizing resolves junctions to the directory they point to.
+    fs::canonicalize(path).ok()
+}
+
+/// Executes the classic directory tree view, writing it to `out`
+pub fn run(args: &ViewArgs, ls_colors: &LsColors, out: &mut dyn Write) -> anyhow::Result<()> {
+    // eprintln!("Running view with args: {:?}", args);
+    let walk = walk(args, needs_metadata(args))?;
+    draw(args, &walk, ls_colors, out);
+    Ok(())
+}
+
+/// Draws a tree for each root, one after the other, followed by a single
+/// summary of all of them. The summary is the first root's.
+pub fn run_forest(
+    roots: Vec<ViewArgs>,
+    ls_colors: &LsColors,
+    out: &mut dyn Write,
+) -> anyhow::Result<()> {
+    let Some(summary) = roots.first().map(|args| args.summary.clone()) else {
+        return Ok(());
+    };
+    let mut totals = Totals::default();
+    for (i, mut args) in roots.into_iter().enumerate() {
+        let walk = walk(&args, needs_metadata(&args))?;
+        let tree = Totals::of(&walk.entries);
+        totals.dirs += tree.dirs;
+        totals.files += tree.files;
+        totals.size += tree.size;
+        if i > 0 {
+            _ = writeln!(out);
         }
-        if ls_style.font_style.underline {
-            styled_name = styled_name.underline();
+        args.summary = Summary::None;
+        draw(&args, &walk, ls_colors, out);
+    }
+    if let Some(summary) = summary.render(totals) {
+        _ = writeln!(out, "\n{}", summary);
+    }
+    Ok(())
+}
+
+/// Whether the walk needs the entries' metadata for what is drawn.
+fn needs_metadata(args: &ViewArgs) -> bool {
+    args.size || args.permissions || args.columns.is_some() || args.summary.needs_size()
+}
+
+/// Draws a walk that was already made, such as a cached walk. What kept the
+/// walk from being complete is drawn below its entries.
+pub fn draw(args: &ViewArgs, walk: &Walk, ls_colors: &LsColors, out: &mut dyn Write) {
+    let entries = &walk.entries;
+    //TODO: Change this to nu_protocol's color handling UseAnsiColoring::Auto/True/False
+    // engine.get_config()?.use_ansi_coloring = true;
+
+    // match args.color {
+    //     ColorChoice::Always => control::set_override(true),
+    //     ColorChoice::Never => control::set_override(false),
+    //     ColorChoice::Auto => {}
+    // }
+    let styled = |style: Style| args.color.style(style);
+
+    if writeln!(
+        out,
+        "{}",
+        //args.path.display().to_string().blue().bold()
+        styled(Style::new().bold()).paint(args.path.display().to_string())
+    )
+    .is_err()
+    {
+        return;
+    }
+    // Directories have the status of the changes below them, so the top-level
+    // entries tell whether anything in the tree changed.
+    let dirty = entries.iter().any(|entry| {
+        entry.depth == 1
+            && entry
+                .git_status
+                .is_some_and(|status| status != git::FileStatus::Ignored)
+    });
+    // Remote paths aren't local directories, so they have no repository.
+    if args.git_status
+        && args.path.is_dir()
+        && let Ok(Some(head)) = git::head_info(&args.path, dirty)
+        && writeln!(
+            out,
+            "{}",
+            styled(Style::new().dimmed()).paint(head.to_string())
+        )
+        .is_err()
+    {
+        return;
+    }
+
+    // Diff stats are padded to the widest, so the tree stays aligned.
+    let stat_width = entries
+        .iter()
+        .filter_map(|entry| entry.diff_stat)
+        .map(|stat| stat.to_string().len())
+        .max()
+        .unwrap_or(0);
+    let mut rows: Vec<Row> = entries
+        .iter()
+        .map(|entry| render_entry(entry, args, ls_colors, stat_width))
+        .collect();
+    if let Some(columns) = &args.columns {
+        let entries: Vec<&Entry> = entries.iter().collect();
+        column_cells(args, columns, &entries, &mut rows);
+    }
+    if args.numbered {
+        let width = rows.len().to_string().len();
+        for (i, row) in rows.iter_mut().enumerate() {
+            let number = styled(Style::new().dimmed()).paint(format!("{:>width$} ", i + 1));
+            row.leading = format!("{}{}", number, row.leading);
         }
-        // --- End Corrected Logic Block ---
+    }
+    // Errors about the walk itself are drawn first, where GNU tree puts the
+    // error about an unreadable root.
+    let errors = walk.errors.iter().map(|message| Row {
+        depth: 1,
+        leading: String::new(),
+        label: styled(Color::Red.normal())
+            .paint(format!("[error: {message}]"))
+            .to_string(),
+    });
+    rows.splice(0..0, errors);
+    if walk.capped || walk.omitted > 0 {
+        rows.push(Row {
+            depth: 1,
+            leading: String::new(),
+            label: if walk.capped {
+                "… more entries".to_string()
+            } else {
+                format!("… {} more entries", walk.omitted)
+            },
+        });
+    }
+    if walk.interrupted {
+        rows.push(Row {
+            depth: 1,
+            leading: String::new(),
+            label: "… interrupted".to_string(),
+        });
+    }
+    write_rows(args, &rows, Totals::of(entries), out);
+}
+
+/// Renders the decorations and name of an entry. Its diff stat, if any, is
+/// padded to `stat_width`.
+pub fn render_entry(
+    entry: &Entry,
+    args: &ViewArgs,
+    ls_colors: &LsColors,
+    stat_width: usize,
+) -> Row {
+    let styled = |style: Style| args.color.style(style);
+    let theme = args.theme;
 
-        if is_dir {
-            dir_count += 1;
+    // With explicit columns, the leading columns are laid out by `column_cells` instead.
+    let long_format = args.columns.is_none();
+    let git_status_str = if args.git_status && long_format && !entry.remote {
+        entry
+            .git_status
+            .map(|s| {
+                let status_char = s.get_char();
+                // format!("{} ", status_char).color(color).to_string()
+                styled(git_style(s))
+                    .paint(format!("{status_char} "))
+                    .to_string()
+            })
+            .unwrap_or_else(|| "  ".to_string())
+    } else {
+        String::new()
+    };
+    let diff_stat_str = if args.git_diff_stats && long_format && stat_width > 0 {
+        match entry.diff_stat {
+            Some(stat) => {
+                let padding = " ".repeat(stat_width - stat.to_string().len());
+                format!(
+                    "{} {}{padding} ",
+                    styled(Color::Green.normal()).paint(format!("+{}", stat.added)),
+                    styled(Color::Red.normal()).paint(format!("-{}", stat.removed))
+                )
+            }
+            None => " ".repeat(stat_width + 1),
+        }
+    } else {
+        String::new()
+    };
+
+    let permissions_str = if args.permissions && long_format && !entry.remote {
+        format!("{} ", entry.permissions.as_deref().unwrap_or("----------"))
+    } else {
+        String::new()
+    };
+
+    let icon_str = if args.icons {
+        // A trailing slash lets devicons pick the directory icon without touching the filesystem.
+        let icon_path = if entry.is_dir {
+            PathBuf::from(format!("{}/", entry.name))
         } else {
-            file_count += 1;
+            entry.path.clone()
+        };
+        let icon_info = icon_for_file(&icon_path, &None);
+        let icon = theme
+            .and_then(|t| t.icon(&entry.name, entry.is_dir))
+            .map(str::to_string)
+            .unwrap_or_else(|| icon_info.icon.to_string());
+        let icon_color = theme
+            .and_then(|t| t.icon_color(&entry.name, entry.is_dir))
+            .unwrap_or_else(|| lookup_ansi_color_style(icon_info.color));
+        // format!("{} ", icon.color(color))
+        styled(Style::new().fg(icon_color))
+            .paint(format!("{} ", icon))
+            .to_string()
+    } else {
+        String::new()
+    };
+    let size_str = match entry.size {
+        Some(size) if args.size && long_format => format!(" ({})", utils::format_size(size)),
+        _ => String::new(),
+    };
+    let duplicate_str = match &entry.duplicate_of {
+        Some(first) => {
+            let shown = match first.strip_prefix(&args.path) {
+                Ok(relative) if relative.as_os_str().is_empty() => &args.path,
+                Ok(relative) => relative,
+                Err(_) => first,
+            };
+            if entry.path.starts_with(first) {
+                format!(" [loops back to {}]", shown.display())
+            } else {
+                format!(" [same as {}]", shown.display())
+            }
         }
+        None => String::new(),
+    };
+    let submodule_str = if entry.submodule {
+        " [submodule]"
+    } else if entry.dir_link {
+        " [linked dir]"
+    } else {
+        ""
+    };
+    let skipped_str = match entry.skipped_entries {
+        Some(count) => format!(" [ {count} entries ]"),
+        None => String::new(),
+    };
+    let error_str = match &entry.error {
+        Some(_) if entry.is_dir => " [error opening dir]".to_string(),
+        Some(message) => format!(" [error: {message}]"),
+        None => String::new(),
+    };
+
+    let ls_style = if !entry.remote {
+        ls_colors.style_for_path(&entry.path)
+    } else if entry.is_dir {
+        ls_colors.style_for_indicator(lscolors::Indicator::Directory)
+    } else {
+        ls_colors.style_for_str(&entry.name)
+    }
+    .cloned()
+    .unwrap_or_default();
+    let name_style = if entry.git_status == Some(git::FileStatus::Ignored) {
+        Style::new().dimmed()
+    } else if args.git_age
+        && let Some(committed) = entry.committed
+    {
+        age_style(committed)
+    } else {
+        theme
+            .and_then(|t| t.name_style(&entry.name, entry.is_dir))
+            .unwrap_or_else(|| ansi_style(&ls_style))
+    };
 
-        if writeln!(
-            io::stdout(),
-            "{}{}{}└── {}{}{}",
+    Row {
+        depth: entry.depth,
+        leading: format!(
+            "{}{}{}",
             git_status_str,
+            diff_stat_str,
             //permissions_str.dimmed(),
-            Style::new().dimmed().paint(permissions_str),
-            indent,
+            styled(Style::new().dimmed()).paint(permissions_str)
+        ),
+        label: format!(
+            "{}{}{}{}{}{}{}",
             icon_str,
-            styled_name.paint(name),
+            styled(name_style).paint(display_name(entry, args)),
             // size_str.dimmed()
-            Style::new().dimmed().paint(size_str)
-        )
-        .is_err()
-        {
+            styled(Style::new().dimmed()).paint(size_str),
+            styled(Style::new().dimmed()).paint(duplicate_str),
+            styled(Style::new().dimmed()).paint(submodule_str),
+            styled(Style::new().dimmed()).paint(skipped_str),
+            styled(Color::Red.normal()).paint(error_str)
+        ),
+    }
+}
+
+/// The name drawn for an entry, fully escaped if asked for. Otherwise only
+/// control characters and the bytes that aren't valid UTF-8 are escaped, so a
+/// name can't break its line or restyle the terminal.
+fn display_name(entry: &Entry, args: &ViewArgs) -> String {
+    let name = match entry.path.file_name() {
+        Some(name) if args.escape && !entry.remote => utils::escape_name(name),
+        _ => utils::escape_control(&entry.name),
+    };
+    if args.quote {
+        format!("\"{}\"", name.replace('"', "\\\""))
+    } else {
+        name
+    }
+}
+
+fn git_style(status: git::FileStatus) -> Style {
+    match status {
+        git::FileStatus::New | git::FileStatus::Renamed => Color::Green.normal(),
+        git::FileStatus::Modified | git::FileStatus::Typechange => Color::Yellow.normal(),
+        git::FileStatus::Deleted => Color::Red.normal(),
+        git::FileStatus::Conflicted => Color::LightRed.normal(),
+        git::FileStatus::Untracked => Color::Magenta.normal(),
+        git::FileStatus::Ignored => Style::new().dimmed(),
+    }
+}
+
+/// A heat map of how long ago something was committed: red for today, through
+/// orange, yellow, green and blue, to grey for more than a year ago.
+fn age_style(committed: SystemTime) -> Style {
+    const DAY: u64 = 24 * 60 * 60;
+    let age = SystemTime::now()
+        .duration_since(committed)
+        .unwrap_or_default()
+        .as_secs();
+    let color = match age {
+        age if age < DAY => 196,
+        age if age < 7 * DAY => 208,
+        age if age < 30 * DAY => 220,
+        age if age < 90 * DAY => 34,
+        age if age < 365 * DAY => 39,
+        _ => 244,
+    };
+    Color::Fixed(color).normal()
+}
+
+/// Lays out the selected metadata columns in front of each row, padding every
+/// column to its widest cell.
+pub fn column_cells(args: &ViewArgs, columns: &[Column], entries: &[&Entry], rows: &mut [Row]) {
+    let styled = |style: Style| args.color.style(style);
+    let cells: Vec<Vec<String>> = entries
+        .iter()
+        .map(|entry| columns.iter().map(|column| column.cell(entry)).collect())
+        .collect();
+    let widths: Vec<usize> = (0..columns.len())
+        .map(|i| {
+            cells
+                .iter()
+                .map(|row| row[i].chars().count())
+                .max()
+                .unwrap_or(0)
+        })
+        .collect();
+
+    for ((row, entry), cells) in rows.iter_mut().zip(entries).zip(cells) {
+        row.leading.clear();
+        for ((column, cell), width) in columns.iter().zip(cells).zip(&widths) {
+            let padded = match column {
+                Column::Size => format!("{cell:>width$} "),
+                _ => format!("{cell:<width$} "),
+            };
+            let style = match (column, entry.git_status) {
+                (Column::Git, Some(status)) => git_style(status),
+                _ => Style::new().dimmed(),
+            };
+            row.leading
+                .push_str(&styled(style).paint(padded).to_string());
+        }
+    }
+}
+
+/// Draws the rows with their tree connectors, followed by the summary line.
+pub fn write_rows(args: &ViewArgs, rows: &[Row], totals: Totals, out: &mut dyn Write) {
+    for line in row_lines(args, rows) {
+        if writeln!(out, "{}", line).is_err() {
             break;
         }
     }
 
-    let summary = format!("\n{} directories, {} files", dir_count, file_count);
-    _ = writeln!(io::stdout(), "{}", summary);
+    if let Some(summary) = args.summary.render(totals) {
+        _ = writeln!(out, "\n{}", summary);
+    }
+}
 
-    Ok(())
+/// Returns the line drawn for each row, with its tree connectors.
+pub fn row_lines(args: &ViewArgs, rows: &[Row]) -> Vec<String> {
+    let connectors = Connectors::new(&args.branches.characters, args.branches.indent);
+    let is_last = last_siblings(rows);
+    // Tracks, for each ancestor level, whether that ancestor was the last of its siblings.
+    let mut ancestors: Vec<bool> = Vec::new();
+    let mut lines = Vec::with_capacity(rows.len());
+    for (row, last) in rows.iter().zip(is_last) {
+        ancestors.truncate(row.depth.saturating_sub(1));
+        let mut prefix = String::new();
+        for ancestor_last in &ancestors {
+            prefix.push_str(if *ancestor_last {
+                &connectors.empty
+            } else {
+                &connectors.vertical
+            });
+        }
+        prefix.push_str(if last {
+            &connectors.last
+        } else {
+            &connectors.branch
+        });
+        ancestors.push(last);
+
+        lines.push(format!("{}{}{}", row.leading, prefix, row.label));
+    }
+    lines
+}
+
+/// Converts an LS_COLORS style into the equivalent terminal style.
+fn ansi_style(ls_style: &lscolors::Style) -> Style {
+    //let mut styled_name = name.to_string().normal();
+    // let mut styled_name = Style::new().normal().paint(name.to_string());
+    let mut styled_name = Style::new();
+
+    if let Some(fg) = ls_style.foreground {
+        use lscolors::Color as LsColor;
+        let color = match fg {
+            LsColor::Black => Color::Black,
+            LsColor::Red => Color::Red,
+            LsColor::Green => Color::Green,
+            LsColor::Yellow => Color::Yellow,
+            LsColor::Blue => Color::Blue,
+            LsColor::Magenta => Color::Magenta,
+            LsColor::Cyan => Color::Cyan,
+            LsColor::White => Color::White,
+            LsColor::BrightBlack => Color::DarkGray,
+            LsColor::BrightRed => Color::LightRed,
+            LsColor::BrightGreen => Color::LightGreen,
+            LsColor::BrightYellow => Color::LightYellow,
+            LsColor::BrightBlue => Color::LightBlue,
+            LsColor::BrightMagenta => Color::LightMagenta,
+            LsColor::BrightCyan => Color::LightCyan,
+            LsColor::BrightWhite => Color::LightGray,
+            LsColor::Fixed(_) => Color::White,
+            LsColor::RGB(r, g, b) => Color::Rgb(r, g, b),
+        };
+        // styled_name = styled_name.color(color);
+        styled_name = styled_name.fg(color)
+    }
+
+    if ls_style.font_style.bold {
+        // styled_name = styled_name.bold();
+        styled_name = styled_name.bold();
+    }
+    if ls_style.font_style.italic {
+        styled_name = styled_name.italic();
+    }
+    if ls_style.font_style.underline {
+        styled_name = styled_name.underline();
+    }
+    styled_name
+}
+
+/// Determines for each row whether it is the last entry of its parent directory.
+///
+/// Rows are expected in depth-first order, as produced by the walker.
+fn last_siblings(rows: &[Row]) -> Vec<bool> {
+    let mut is_last = vec![false; rows.len()];
+    // Whether a later sibling has been seen at each depth while scanning backwards.
+    let mut has_following: Vec<bool> = Vec::new();
+    for (i, row) in rows.iter().enumerate().rev() {
+        has_following.resize(row.depth + 1, false);
+        is_last[i] = !has_following[row.depth];
+        has_following[row.depth] = true;
+    }
+    is_last
 }
 
 pub fn lookup_ansi_color_style(s: &str) -> Color {
@@ -326,3 +1731,278 @@ pub fn color_from_hex(hex_color: &str) -> std::result::Result<Color, std::num::P
         ))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("nu_plugin_tree_view_{name}_{}", std::process::id()));
+        _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    /// Draws a walk without colors, one string per line.
+    fn drawn(args: &ViewArgs, walk: &Walk) -> Vec<String> {
+        let mut out = Vec::new();
+        draw(args, walk, &LsColors::default(), &mut out);
+        String::from_utf8(out)
+            .unwrap()
+            .lines()
+            .map(str::to_string)
+            .collect()
+    }
+
+    fn args_for(path: &Path) -> ViewArgs {
+        ViewArgs {
+            path: path.to_path_buf(),
+            color: ColorChoice::Never,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_walk_errors_are_kept_and_drawn() {
+        let dir = temp_dir("errors");
+        fs::write(dir.join("open.txt"), "").unwrap();
+        let args = args_for(&dir);
+        let walk = Walk {
+            entries: vec![Entry {
+                path: dir.join("open.txt"),
+                name: "open.txt".to_string(),
+                depth: 1,
+                ..Default::default()
+            }],
+            errors: vec!["Permission denied".to_string()],
+            ..Default::default()
+        };
+        assert_eq!(
+            drawn(&args, &walk)[1..3],
+            ["├── [error: Permission denied]", "└── open.txt"]
+        );
+
+        #[cfg(unix)]
+        {
+            fs::create_dir(dir.join("locked")).unwrap();
+            fs::set_permissions(dir.join("locked"), fs::Permissions::from_mode(0o000)).unwrap();
+            // Root can read any directory, so there is no error to see.
+            if fs::read_dir(dir.join("locked")).is_err() {
+                let walk = super::walk(&args, false).unwrap();
+                assert!(walk.errors.is_empty());
+                let locked = walk.entries.iter().find(|e| e.name == "locked").unwrap();
+                assert!(locked.error.is_some());
+                let lines = drawn(&args, &walk);
+                assert!(lines.contains(&"├── locked [error opening dir]".to_string()));
+            }
+            fs::set_permissions(dir.join("locked"), fs::Permissions::from_mode(0o755)).unwrap();
+        }
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_entries_past_the_cap_are_counted() {
+        let dir = temp_dir("cap");
+        for name in ["a", "b", "c", "d"] {
+            fs::write(dir.join(name), "").unwrap();
+        }
+        let args = ViewArgs {
+            max_entries: Some(2),
+            ..args_for(&dir)
+        };
+        let walk = walk(&args, false).unwrap();
+        assert_eq!(walk.entries.len(), 2);
+        assert_eq!(walk.omitted, 2);
+        assert_eq!(
+            drawn(&args, &walk)[1..4],
+            ["├── a", "├── b", "└── … 2 more entries"]
+        );
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_parallel_walks_stop_at_the_cap() {
+        let dir = temp_dir("parallel_cap");
+        for name in 0..50 {
+            fs::write(dir.join(name.to_string()), "").unwrap();
+        }
+        let args = ViewArgs {
+            max_entries: Some(5),
+            threads: 4,
+            ..args_for(&dir)
+        };
+        let walk = walk(&args, false).unwrap();
+        assert_eq!(walk.entries.len(), 5);
+        assert!(walk.capped);
+        assert_eq!(drawn(&args, &walk)[6], "└── … more entries");
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_crowded_dirs_show_their_count_instead_of_children() {
+        let dir = temp_dir("filelimit");
+        fs::create_dir_all(dir.join("big")).unwrap();
+        fs::create_dir_all(dir.join("small")).unwrap();
+        for name in ["a", "b", "c", "d", "e"] {
+            fs::write(dir.join("big").join(name), "").unwrap();
+        }
+        fs::write(dir.join("small/a"), "").unwrap();
+        let args = ViewArgs {
+            file_limit: Some(3),
+            ..args_for(&dir)
+        };
+        let walk = walk(&args, false).unwrap();
+        assert_eq!(
+            drawn(&args, &walk)[1..4],
+            ["├── big [ 5 entries ]", "└── small", "    └── a"]
+        );
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_symlink_loops_are_drawn_once() {
+        let dir = temp_dir("loop");
+        fs::create_dir_all(dir.join("a")).unwrap();
+        fs::write(dir.join("a/file"), "").unwrap();
+        std::os::unix::fs::symlink("..", dir.join("a/up")).unwrap();
+        let args = ViewArgs {
+            follow_symlinks: true,
+            ..args_for(&dir)
+        };
+        let walk = walk(&args, false).unwrap();
+        let up: Vec<_> = walk.entries.iter().filter(|e| e.name == "up").collect();
+        assert_eq!(up.len(), 1);
+        assert_eq!(up[0].duplicate_of.as_deref(), Some(dir.as_path()));
+        assert_eq!(walk.entries.len(), 3);
+        assert_eq!(
+            drawn(&args, &walk)[1..4],
+            [
+                "└── a".to_string(),
+                "    ├── file".to_string(),
+                format!("    └── up [loops back to {}]", dir.display()),
+            ]
+        );
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_names_stay_on_one_line() {
+        let entry = |name: &str| Entry {
+            path: Path::new("root").join(name),
+            name: name.to_string(),
+            depth: 1,
+            ..Default::default()
+        };
+        let walk = Walk {
+            entries: vec![entry("a\nb"), entry("\x1b[31mred"), entry("say \"hi\"")],
+            ..Default::default()
+        };
+        let args = args_for(Path::new("root"));
+        assert_eq!(
+            drawn(&args, &walk)[1..4],
+            ["├── a\\nb", "├── \\x1b[31mred", "└── say \"hi\""]
+        );
+
+        let args = ViewArgs {
+            quote: true,
+            ..args
+        };
+        assert_eq!(
+            drawn(&args, &walk)[1..4],
+            [
+                "├── \"a\\nb\"",
+                "├── \"\\x1b[31mred\"",
+                "└── \"say \\\"hi\\\"\""
+            ]
+        );
+    }
+
+    #[test]
+    fn test_globs_keep_only_matches_and_their_parents() {
+        let dir = temp_dir("glob");
+        fs::create_dir_all(dir.join("src/inner")).unwrap();
+        fs::create_dir_all(dir.join("docs")).unwrap();
+        fs::create_dir_all(dir.join("empty")).unwrap();
+        for file in ["src/a.rs", "src/b.txt", "src/inner/c.rs", "docs/d.md"] {
+            fs::write(dir.join(file), "").unwrap();
+        }
+        let (base, glob) = crate::patterns::split_glob("src/**/*.rs").unwrap();
+        let root = dir.join(base);
+        let args = ViewArgs {
+            patterns: Patterns::default().with_glob(&root, &glob).unwrap(),
+            ..args_for(&dir)
+        };
+        let walk = walk(&args, false).unwrap();
+        let kept: Vec<_> = walk
+            .entries
+            .iter()
+            .map(|entry| entry.path.strip_prefix(&dir).unwrap())
+            .collect();
+        assert_eq!(
+            kept,
+            [
+                Path::new("src"),
+                Path::new("src/a.rs"),
+                Path::new("src/inner"),
+                Path::new("src/inner/c.rs"),
+            ]
+        );
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_git_dirty_only_keeps_changes_and_their_parents() {
+        let dir = temp_dir("dirty");
+        fs::create_dir_all(dir.join("src")).unwrap();
+        fs::create_dir_all(dir.join("docs")).unwrap();
+        for file in [
+            "clean.txt",
+            "src/changed.rs",
+            "src/clean.rs",
+            "docs/guide.md",
+        ] {
+            fs::write(dir.join(file), "committed").unwrap();
+        }
+        let repo = git2::Repository::init(&dir).unwrap();
+        let mut index = repo.index().unwrap();
+        index
+            .add_all(["*"], git2::IndexAddOption::DEFAULT, None)
+            .unwrap();
+        index.write().unwrap();
+        let tree = repo.find_tree(index.write_tree().unwrap()).unwrap();
+        let signature = git2::Signature::now("nu", "nu@example.com").unwrap();
+        repo.commit(Some("HEAD"), &signature, &signature, "init", &tree, &[])
+            .unwrap();
+        fs::write(dir.join("src/changed.rs"), "changed").unwrap();
+        fs::write(dir.join("new.txt"), "").unwrap();
+
+        let args = ViewArgs {
+            git_dirty_only: true,
+            ..args_for(&dir)
+        };
+        let walk = walk(&args, false).unwrap();
+        let kept: Vec<_> = walk
+            .entries
+            .iter()
+            .map(|entry| entry.path.strip_prefix(&dir).unwrap())
+            .collect();
+        assert_eq!(
+            kept,
+            [
+                Path::new("new.txt"),
+                Path::new("src"),
+                Path::new("src/changed.rs"),
+            ]
+        );
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,511 @@
+//! Implements `tree watch`, which watches a directory and reacts whenever the
+//! visible tree changes, or with `--live` keeps the tree redrawn in place.
+//!
+//! Change detection reuses the walker from the classic view, so the same hidden
+//! file and ignore rules decide what counts as a change. The plugin holds the
+//! terminal while it watches, and a hook that fails is reported without
+//! stopping the watch.
+
+use crate::{TreePlugin, config, theme, view};
+use crossterm::terminal::{Clear, ClearType};
+use crossterm::{cursor, execute};
+use notify::{RecursiveMode, Watcher};
+use nu_plugin::{EngineInterface, EvaluatedCall, PluginCommand};
+use nu_protocol::{
+    Category, Example, LabeledError, PipelineData, Signature, Span, Spanned, SyntaxShape, Value,
+    engine::Closure, record,
+};
+use std::{
+    collections::BTreeMap,
+    io::{self, Write},
+    path::{Path, PathBuf},
+    process::Command,
+    sync::mpsc::{self, RecvTimeoutError},
+    time::{Duration, SystemTime},
+};
+
+/// How long to wait for further events before reacting to a burst of changes.
+const DEFAULT_DEBOUNCE: Duration = Duration::from_millis(250);
+
+/// How often to check for ctrl-c while no events are arriving.
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+pub struct TreeWatch;
+
+impl PluginCommand for TreeWatch {
+    type Plugin = TreePlugin;
+
+    fn name(&self) -> &str {
+        "tree watch"
+    }
+
+    fn signature(&self) -> Signature {
+        Signature::build(self.name())
+            .optional(
+                "path",
+                SyntaxShape::Filepath,
+                "the directory to watch (defaults to the current directory)",
+            )
+            .named(
+                "exec",
+                SyntaxShape::OneOf(vec![
+                    SyntaxShape::Closure(Some(vec![SyntaxShape::Record(vec![])])),
+                    SyntaxShape::List(Box::new(SyntaxShape::String)),
+                    SyntaxShape::String,
+                ]),
+                "a closure or external command to run when the tree changes",
+                Some('e'),
+            )
+            .named(
+                "debounce",
+                SyntaxShape::Duration,
+                "how long the tree must be quiet before reacting (default 250ms)",
+                Some('d'),
+            )
+            .switch("all", "include hidden files", Some('a'))
+            .switch(
+                "gitignore",
+                "respect .gitignore and other ignore files",
+                Some('g'),
+            )
+            .named(
+                "level",
+                SyntaxShape::Int,
+                "maximum depth to watch",
+                Some('L'),
+            )
+            .switch(
+                "live",
+                "clear the screen and redraw the tree whenever it changes",
+                Some('l'),
+            )
+            .category(Category::Experimental)
+    }
+
+    fn description(&self) -> &str {
+        "Watch a directory and react when its tree changes."
+    }
+
+    fn examples(&self) -> Vec<Example<'_>> {
+        vec![
+            Example {
+                example: "tree watch src --exec {|changes| print $changes.modified }",
+                description: "Print the modified files whenever something under src changes",
+                result: None,
+            },
+            Example {
+                example: "tree watch --live --level 2",
+                description: "Keep an up-to-date view of the project structure on screen",
+                result: None,
+            },
+            Example {
+                example: "tree watch --gitignore --exec 'cargo check'",
+                description: "Run an external command when a file that isn't ignored changes",
+                result: None,
+            },
+        ]
+    }
+
+    fn run(
+        &self,
+        plugin: &TreePlugin,
+        engine: &EngineInterface,
+        call: &EvaluatedCall,
+        _input: PipelineData,
+    ) -> Result<PipelineData, LabeledError> {
+        let cwd = PathBuf::from(engine.get_current_dir()?);
+        let path = match call.opt::<PathBuf>(0)? {
+            Some(path) => cwd.join(path),
+            None => cwd,
+        };
+        let hook = match call.get_flag_value("exec") {
+            Some(value) => Some(Hook::from_value(value)?),
+            None => None,
+        };
+        let debounce = call
+            .get_flag::<Duration>("debounce")?
+            .unwrap_or(DEFAULT_DEBOUNCE);
+        let level = call
+            .get_flag::<i64>("level")?
+            .map(|level| level.max(0) as usize);
+
+        let view_args = view::ViewArgs {
+            path,
+            all: call.has_flag("all")?,
+            gitignore: call.has_flag("gitignore")?,
+            level,
+            branches: config::branch_style(engine, call)?,
+            theme: Some(theme::load()?),
+            summary: config::summary(engine)?,
+            signals: Some(engine.signals().clone()),
+            ..Default::default()
+        };
+        if !view_args.path.is_dir() {
+            return Err(LabeledError::new(format!(
+                "'{}' is not a directory.",
+                view_args.path.display()
+            ))
+            .with_label("can't watch this path", call.head));
+        }
+
+        let ls_colors = plugin.ls_colors(engine)?;
+        let live = call.has_flag("live")?;
+        // The tree, the changes and the hooks' output go straight to the
+        // terminal until the watch ends. Without a terminal, as in a script,
+        // they are written all the same.
+        let _foreground = engine.enter_foreground().ok();
+        let draw = || -> Result<(), LabeledError> {
+            let mut out = io::stdout();
+            if live {
+                // Redraw in place, like `watch tree`.
+                execute!(out, Clear(ClearType::All), cursor::MoveTo(0, 0)).map_err(|err| {
+                    LabeledError::new(format!("Error clearing the screen: {err}"))
+                })?;
+            }
+            view::run(&view_args, &ls_colors, &mut out).map_err(|err| {
+                LabeledError::new(format!("Error trying to create a tree view: {err}"))
+            })
+        };
+        draw()?;
+
+        let (tx, rx) = mpsc::channel();
+        let mut watcher = notify::recommended_watcher(tx)
+            .map_err(|err| LabeledError::new(format!("Error creating file watcher: {err}")))?;
+        watcher
+            .watch(&view_args.path, RecursiveMode::Recursive)
+            .map_err(|err| LabeledError::new(format!("Error watching directory: {err}")))?;
+
+        let mut previous = snapshot(&view_args);
+        loop {
+            match rx.recv_timeout(POLL_INTERVAL) {
+                Ok(Err(err)) => report(&format!("Error watching directory: {err}")),
+                Ok(Ok(_)) => {}
+                Err(RecvTimeoutError::Timeout) => {
+                    if engine.signals().interrupted() {
+                        break;
+                    }
+                    continue;
+                }
+                Err(RecvTimeoutError::Disconnected) => break,
+            }
+
+            // Let a burst of events settle before walking the tree again.
+            while rx.recv_timeout(debounce).is_ok() && !engine.signals().interrupted() {}
+            if engine.signals().interrupted() {
+                break;
+            }
+
+            let current = snapshot(&view_args);
+            let changes = Changes::between(&previous, &current);
+            previous = current;
+            if changes.is_empty() {
+                continue;
+            }
+
+            if live {
+                draw()?;
+            }
+            match &hook {
+                Some(hook) => {
+                    if let Err(err) = hook.run(engine, &view_args.path, &changes, call.head) {
+                        report(&format!("Error: {err}"));
+                    }
+                }
+                None if !live => _ = changes.write(&view_args.path, &mut io::stdout()),
+                None => {}
+            }
+        }
+
+        Ok(PipelineData::Empty)
+    }
+}
+
+/// What to run when the tree changes.
+enum Hook {
+    Closure(Spanned<Closure>),
+    Command(Vec<String>),
+}
+
+impl Hook {
+    fn from_value(value: Value) -> Result<Self, LabeledError> {
+        let span = value.span();
+        match value {
+            Value::Closure { val, .. } => Ok(Hook::Closure(Spanned { item: *val, span })),
+            Value::String { val, .. } => {
+                let command = split_command(&val).map_err(|err| {
+                    LabeledError::new("Invalid --exec value").with_label(err, span)
+                })?;
+                Self::command(command, span)
+            }
+            Value::List { vals, .. } => {
+                let mut command = Vec::with_capacity(vals.len());
+                for val in vals {
+                    command.push(val.coerce_into_string()?);
+                }
+                Self::command(command, span)
+            }
+            other => Err(LabeledError::new("Invalid --exec value").with_label(
+                format!("expected a closure or command, got {}", other.get_type()),
+                span,
+            )),
+        }
+    }
+
+    fn command(command: Vec<String>, span: Span) -> Result<Self, LabeledError> {
+        if command.is_empty() {
+            return Err(LabeledError::new("Invalid --exec value")
+                .with_label("the command to run is empty", span));
+        }
+        Ok(Hook::Command(command))
+    }
+
+    fn run(
+        &self,
+        engine: &EngineInterface,
+        root: &Path,
+        changes: &Changes,
+        head: Span,
+    ) -> Result<(), LabeledError> {
+        match self {
+            Hook::Closure(closure) => {
+                let changes = changes.to_value(head);
+                engine.eval_closure(closure, vec![changes.clone()], Some(changes))?;
+            }
+            Hook::Command(command) => {
+                let status = Command::new(&command[0])
+                    .args(&command[1..])
+                    .current_dir(root)
+                    .status()
+                    .map_err(|err| {
+                        LabeledError::new(format!("failed to run '{}': {err}", command[0]))
+                    })?;
+                if !status.success() {
+                    return Err(LabeledError::new(format!(
+                        "'{}' exited with {status}",
+                        command.join(" ")
+                    )));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Shows a problem that doesn't stop the watch on stderr.
+fn report(message: &str) {
+    _ = writeln!(io::stderr(), "{message}");
+}
+
+/// Splits a command line into words like a POSIX shell: at unquoted
+/// whitespace, with single quotes keeping everything, double quotes keeping
+/// all but backslash escapes, and a backslash escaping the next character.
+fn split_command(line: &str) -> Result<Vec<String>, String> {
+    let mut words = Vec::new();
+    let mut word: Option<String> = None;
+    let mut chars = line.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            c if c.is_whitespace() => words.extend(word.take()),
+            '\'' => {
+                let word = word.get_or_insert_default();
+                loop {
+                    match chars.next() {
+                        Some('\'') => break,
+                        Some(c) => word.push(c),
+                        None => return Err("a single quote isn't closed".to_string()),
+                    }
+                }
+            }
+            '"' => {
+                let word = word.get_or_insert_default();
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some('\\') => match chars.next() {
+                            Some(c @ ('"' | '\\' | '$' | '`')) => word.push(c),
+                            Some(c) => {
+                                word.push('\\');
+                                word.push(c);
+                            }
+                            None => return Err("a double quote isn't closed".to_string()),
+                        },
+                        Some(c) => word.push(c),
+                        None => return Err("a double quote isn't closed".to_string()),
+                    }
+                }
+            }
+            '\\' => match chars.next() {
+                Some(c) => word.get_or_insert_default().push(c),
+                None => return Err("the command ends with a backslash".to_string()),
+            },
+            c => word.get_or_insert_default().push(c),
+        }
+    }
+    words.extend(word);
+    Ok(words)
+}
+
+/// The state of a single entry, used to tell whether it changed between walks.
+#[derive(PartialEq)]
+struct EntryState {
+    is_dir: bool,
+    len: u64,
+    modified: Option<SystemTime>,
+}
+
+/// Every entry visible to the walker, keyed by path.
+type Snapshot = BTreeMap<PathBuf, EntryState>;
+
+/// Walks the tree for a snapshot, stopping early when Nushell is interrupted.
+fn snapshot(args: &view::ViewArgs) -> Snapshot {
+    view::walk_builder(args, None)
+        .build()
+        .take_while(|_| {
+            !args
+                .signals
+                .as_ref()
+                .is_some_and(|signals| signals.interrupted())
+        })
+        .filter_map(Result::ok)
+        .filter(|entry| entry.depth() > 0)
+        .map(|entry| {
+            let metadata = entry.metadata().ok();
+            let is_dir = entry.file_type().is_some_and(|ft| ft.is_dir());
+            let state = EntryState {
+                is_dir,
+                // A directory's size and mtime change with its children, which are reported on their own.
+                len: if is_dir {
+                    0
+                } else {
+                    metadata.as_ref().map_or(0, |m| m.len())
+                },
+                modified: if is_dir {
+                    None
+                } else {
+                    metadata.and_then(|m| m.modified().ok())
+                },
+            };
+            (entry.into_path(), state)
+        })
+        .collect()
+}
+
+/// The differences between two snapshots of the tree.
+#[derive(Default)]
+struct Changes {
+    added: Vec<PathBuf>,
+    removed: Vec<PathBuf>,
+    modified: Vec<PathBuf>,
+}
+
+impl Changes {
+    fn between(old: &Snapshot, new: &Snapshot) -> Self {
+        let mut changes = Changes::default();
+        for (path, state) in new {
+            match old.get(path) {
+                None => changes.added.push(path.clone()),
+                Some(old_state) if old_state != state => changes.modified.push(path.clone()),
+                Some(_) => {}
+            }
+        }
+        changes.removed = old
+            .keys()
+            .filter(|path| !new.contains_key(*path))
+            .cloned()
+            .collect();
+        changes
+    }
+
+    fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.modified.is_empty()
+    }
+
+    fn to_value(&self, span: Span) -> Value {
+        let paths = |paths: &[PathBuf]| {
+            Value::list(
+                paths
+                    .iter()
+                    .map(|p| Value::string(p.to_string_lossy(), span))
+                    .collect(),
+                span,
+            )
+        };
+        Value::record(
+            record! {
+                "added" => paths(&self.added),
+                "removed" => paths(&self.removed),
+                "modified" => paths(&self.modified),
+            },
+            span,
+        )
+    }
+
+    /// Writes one line per change, relative to the watched root.
+    fn write(&self, root: &Path, out: &mut impl Write) -> io::Result<()> {
+        let relative = |path: &PathBuf| {
+            path.strip_prefix(root)
+                .unwrap_or(path)
+                .display()
+                .to_string()
+        };
+        for path in &self.added {
+            writeln!(out, "+ {}", relative(path))?;
+        }
+        for path in &self.removed {
+            writeln!(out, "- {}", relative(path))?;
+        }
+        for path in &self.modified {
+            writeln!(out, "~ {}", relative(path))?;
+        }
+        out.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn state(len: u64) -> EntryState {
+        EntryState {
+            is_dir: false,
+            len,
+            modified: None,
+        }
+    }
+
+    #[test]
+    fn test_changes_between_snapshots() {
+        let old = Snapshot::from([
+            (PathBuf::from("a"), state(1)),
+            (PathBuf::from("b"), state(2)),
+            (PathBuf::from("c"), state(3)),
+        ]);
+        let new = Snapshot::from([
+            (PathBuf::from("a"), state(1)),
+            (PathBuf::from("c"), state(4)),
+            (PathBuf::from("d"), state(5)),
+        ]);
+        let changes = Changes::between(&old, &new);
+        assert_eq!(changes.added, vec![PathBuf::from("d")]);
+        assert_eq!(changes.removed, vec![PathBuf::from("b")]);
+        assert_eq!(changes.modified, vec![PathBuf::from("c")]);
+        assert!(Changes::between(&new, &new).is_empty());
+
+        let mut out = Vec::new();
+        changes.write(Path::new(""), &mut out).unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(), "+ d\n- b\n~ c\n");
+    }
+
+    #[test]
+    fn test_commands_split_like_a_shell() {
+        assert_eq!(
+            split_command(r#"cargo test -- "a b" 'c "d"' e\ f"#).unwrap(),
+            vec!["cargo", "test", "--", "a b", "c \"d\"", "e f"]
+        );
+        assert_eq!(
+            split_command(r#"echo "" x"#).unwrap(),
+            vec!["echo", "", "x"]
+        );
+        assert!(split_command("echo 'open").is_err());
+    }
+}